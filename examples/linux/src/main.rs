@@ -6,7 +6,7 @@ use embassy_sync::mutex::Mutex;
 use embassy_time::Duration;
 use embassy_time::Timer;
 use heimlig::client::api::Api;
-use heimlig::common::jobs::{RequestType, Response};
+use heimlig::common::jobs::{ClientId, Request, RequestId, RequestType, Response};
 use heimlig::hsm::core::Builder;
 use heimlig::hsm::keystore::KeyInfo;
 use heimlig::hsm::workers::rng_worker::RngWorker;
@@ -53,7 +53,9 @@ async fn core_task(
 
     loop {
         core.execute().await.expect("failed to forward request");
-        Timer::after(Duration::from_millis(100)).await;
+        if !core.has_pending() {
+            Timer::after(Duration::from_millis(100)).await;
+        }
     }
 }
 
@@ -96,37 +98,31 @@ async fn client_task(
         Timer::after(Duration::from_millis(1000)).await;
         let random_output = Box::leak(Box::new([0u8; 16]));
         let request_size = random_output.len();
-        let request_id = api
-            .get_random(random_output.as_mut_slice())
-            .await
-            .expect("failed to call randomness API");
-        info!(target: "CLIENT", "--> request:  random data (id={}) (size={})", request_id.as_u32(), request_size);
+        let request = Request::GetRandom {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            output: random_output.as_mut_slice(),
+        };
+        info!(target: "CLIENT", "--> request:  random data (size={})", request_size);
 
-        // Receive response
-        loop {
-            match api.recv_response().await {
-                None => Timer::after(Duration::from_millis(10)).await, // Continue waiting for response
-                Some(response) => {
-                    match response {
-                        Response::GetRandom {
-                            client_id: _client_id,
-                            request_id,
-                            data,
-                        } => {
-                            info!(target: "CLIENT",
-                                "<-- response: random data (id={}) (size={}): {}",
-                                request_id.as_u32(),
-                                data.len(),
-                                hex::encode(&data)
-                            );
-                            // release the memory
-                            drop(unsafe { Box::from_raw(data) });
-                            break; // Send next request
-                        }
-                        _ => error!(target: "CLIENT", "Unexpected response type"),
-                    };
-                }
+        // Wait for the response
+        match api.request(request).await {
+            Ok(Response::GetRandom {
+                client_id: _client_id,
+                request_id,
+                data,
+            }) => {
+                info!(target: "CLIENT",
+                    "<-- response: random data (id={}) (size={}): {}",
+                    request_id.as_u32(),
+                    data.len(),
+                    hex::encode(&data)
+                );
+                // release the memory
+                drop(unsafe { Box::from_raw(data) });
             }
+            Ok(_) => error!(target: "CLIENT", "Unexpected response type"),
+            Err(e) => error!(target: "CLIENT", "Request failed: {:?}", e),
         }
     }
 }
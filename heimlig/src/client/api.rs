@@ -1,4 +1,7 @@
-use crate::common::jobs::{ClientId, HashAlgorithm, Request, RequestId, Response};
+use crate::common::jobs::{
+    self, AeadAlgorithm, CbcPadding, ClientId, HashAlgorithm, HashAlgorithms, PublicKeyEncoding,
+    Request, RequestId, Response, Segments, SignatureEncoding,
+};
 use crate::hsm::keystore::KeyId;
 use futures::{Sink, SinkExt, Stream, StreamExt};
 
@@ -12,13 +15,22 @@ pub struct Api<'data, Req: Sink<Request<'data>>, Resp: Stream<Item = Response<'d
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Error {
     Send,
+    /// The response stream terminated before a response was received.
+    StreamTerminated,
+    /// The HSM answered the request with an error.
+    Job(jobs::Error),
+    /// The received response does not belong to the sent request or has an unexpected type.
+    UnexpectedResponse,
+    /// The request does not expect a response on success, so there is nothing to wait for.
+    NoResponseExpected,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum SymmetricAlgorithm {
     ChaCha20Poly1305,
     AesGcm,
-    AesCbc,
+    /// AES-CBC with the given padding scheme.
+    AesCbc(CbcPadding),
 }
 
 impl<
@@ -41,6 +53,40 @@ impl<
         self.responses.next().await
     }
 
+    /// Send a request, wait for its response and convert the response with `f`.
+    ///
+    /// Error responses are returned as [Error::Job] without calling `f`. As the next received
+    /// response is expected to belong to `request`, no other requests must be in flight.
+    /// Fire-and-forget requests must not be mixed with this function, as a late error response
+    /// to one of them would be taken as the response to `request`. Requests that do not expect a
+    /// response are rejected with [Error::NoResponseExpected] without being sent.
+    pub async fn call_map<T>(
+        &mut self,
+        request: Request<'data>,
+        f: impl FnOnce(Response<'data>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        if !request.expects_response() {
+            return Err(Error::NoResponseExpected);
+        }
+        let request_id = self.send_request(request).await?;
+        let response = self.recv_response().await.ok_or(Error::StreamTerminated)?;
+        if response.get_request_id() != request_id {
+            return Err(Error::UnexpectedResponse);
+        }
+        match response {
+            Response::Error { error, .. } => Err(Error::Job(error)),
+            response => f(response),
+        }
+    }
+
+    /// Send a request and wait for its response.
+    ///
+    /// Same as [Api::call_map] but returns successful responses unchanged. Error responses are
+    /// returned as [Error::Job].
+    pub async fn request(&mut self, request: Request<'data>) -> Result<Response<'data>, Error> {
+        self.call_map(request, Ok).await
+    }
+
     /// Request random bytes and write to provided buffer.
     pub async fn get_random(&mut self, output: &'data mut [u8]) -> Result<RequestId, Error> {
         let request = Request::GetRandom {
@@ -62,6 +108,43 @@ impl<
             request_id: RequestId::default(),
             key_id,
             overwrite,
+            no_response: false,
+        };
+        self.send_request(request).await
+    }
+
+    /// Same as [Api::generate_symmetric_key] but the HSM only sends a response if the request
+    /// fails.
+    pub async fn generate_symmetric_key_no_response(
+        &mut self,
+        key_id: KeyId,
+        overwrite: bool,
+    ) -> Result<RequestId, Error> {
+        let request = Request::GenerateSymmetricKey {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            overwrite,
+            no_response: true,
+        };
+        self.send_request(request).await
+    }
+
+    /// Generate an HMAC key sized to the block size of `hash_algorithm` and store it in the HSM.
+    /// The key slot `key_id` has to be a symmetric key of exactly that size. Block sizes above
+    /// [crate::hsm::keystore::KeyType::MAX_SYMMETRIC_KEY_SIZE] are rejected with `InvalidSymmetricKeySize`.
+    pub async fn generate_hmac_key(
+        &mut self,
+        key_id: KeyId,
+        hash_algorithm: HashAlgorithm,
+        overwrite: bool,
+    ) -> Result<RequestId, Error> {
+        let request = Request::GenerateHmacKey {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            hash_algorithm,
+            overwrite,
         };
         self.send_request(request).await
     }
@@ -77,6 +160,23 @@ impl<
             request_id: RequestId::default(),
             key_id,
             overwrite,
+            no_response: false,
+        };
+        self.send_request(request).await
+    }
+
+    /// Same as [Api::generate_key_pair] but the HSM only sends a response if the request fails.
+    pub async fn generate_key_pair_no_response(
+        &mut self,
+        key_id: KeyId,
+        overwrite: bool,
+    ) -> Result<RequestId, Error> {
+        let request = Request::GenerateKeyPair {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            overwrite,
+            no_response: true,
         };
         self.send_request(request).await
     }
@@ -137,12 +237,14 @@ impl<
         &mut self,
         key_id: KeyId,
         public_key: &'data mut [u8],
+        encoding: PublicKeyEncoding,
     ) -> Result<RequestId, Error> {
         let request = Request::ExportPublicKey {
             client_id: ClientId::default(),
             request_id: RequestId::default(),
             key_id,
             public_key,
+            encoding,
         };
         self.send_request(request).await
     }
@@ -173,6 +275,251 @@ impl<
         self.send_request(request).await
     }
 
+    /// List the IDs of all keys stored in the HSM, starting at key ID `start`.
+    /// At most `ids.len()` IDs are returned per request. If more keys are stored, the response
+    /// contains the ID to use as `start` of the next request.
+    pub async fn list_keys(
+        &mut self,
+        start: KeyId,
+        ids: &'data mut [KeyId],
+    ) -> Result<RequestId, Error> {
+        let request = Request::ListKeys {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            start,
+            ids,
+        };
+        self.send_request(request).await
+    }
+
+    /// Query the crate version, protocol version and feature flags the HSM core was built with.
+    pub async fn version(&mut self) -> Result<RequestId, Error> {
+        let request = Request::Version {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+        };
+        self.send_request(request).await
+    }
+
+    /// Query the sizes of the ciphertext and the tag an AEAD encryption of `plaintext_len` bytes
+    /// produces. This allows sizing buffers before sending data.
+    pub async fn aead_output_len(
+        &mut self,
+        algorithm: AeadAlgorithm,
+        plaintext_len: usize,
+    ) -> Result<RequestId, Error> {
+        let request = Request::AeadOutputLen {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            algorithm,
+            plaintext_len,
+        };
+        self.send_request(request).await
+    }
+
+    /// Zeroize all keys stored in the HSM and lock it.
+    /// After the confirmation is received, the HSM rejects all further requests.
+    pub async fn emergency_erase(&mut self) -> Result<RequestId, Error> {
+        let request = Request::EmergencyErase {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+        };
+        self.send_request(request).await
+    }
+
+    /// Calculate the digest of `data`. The `digest` buffer must be at least the digest size of
+    /// `hash_algorithm`.
+    pub async fn hash(
+        &mut self,
+        hash_algorithm: HashAlgorithm,
+        data: &'data [u8],
+        digest: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::Hash {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            hash_algorithm,
+            data,
+            digest,
+        };
+        self.send_request(request).await
+    }
+
+    /// Calculate the digests of `data` for all `hash_algorithms` at once. The digests are
+    /// concatenated in the order of the [HashAlgorithm] variants. The `digests` buffer must be at
+    /// least [HashAlgorithms::digests_size] bytes long.
+    pub async fn hash_multi(
+        &mut self,
+        hash_algorithms: HashAlgorithms,
+        data: &'data [u8],
+        digests: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::HashMulti {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            hash_algorithms,
+            data,
+            digests,
+        };
+        self.send_request(request).await
+    }
+
+    /// Calculate the digest of `segments` as if they were concatenated. The `digest` buffer must
+    /// be at least the digest size of `hash_algorithm`.
+    pub async fn hash_segments(
+        &mut self,
+        hash_algorithm: HashAlgorithm,
+        segments: Segments<'data>,
+        digest: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::HashSegments {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            hash_algorithm,
+            segments,
+            digest,
+        };
+        self.send_request(request).await
+    }
+
+    /// Check that the stored private key `private_key_id` belongs to `expected_public_key`.
+    /// The public key is derived from the private key and compared to the expected one.
+    pub async fn verify_key_matches(
+        &mut self,
+        private_key_id: KeyId,
+        expected_public_key: &'data [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::VerifyKeyMatches {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            private_key_id,
+            expected_public_key,
+        };
+        self.send_request(request).await
+    }
+
+    /// Compute a fingerprint of a key stored in the HSM without exposing the key.
+    /// The `fingerprint` buffer must have the digest size of `hash_algorithm`.
+    /// A symmetric key fingerprint acts as a key check value that allows confirming guesses of the
+    /// key, so it is only available for keys with export permission.
+    pub async fn key_fingerprint(
+        &mut self,
+        key_id: KeyId,
+        hash_algorithm: HashAlgorithm,
+        fingerprint: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::KeyFingerprint {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            hash_algorithm,
+            fingerprint,
+        };
+        self.send_request(request).await
+    }
+
+    /// Perform an ECDH key agreement with the private key `private_key_id` and the peer's
+    /// `public_key`, derive a symmetric key from the shared secret with HKDF-SHA-256 and store it
+    /// under `key_id`. The size of the derived key is given by the key slot `key_id`.
+    /// The shared secret is never exposed.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn ecdh_hkdf(
+        &mut self,
+        private_key_id: KeyId,
+        public_key: &'data [u8],
+        salt: &'data [u8],
+        info: &'data [u8],
+        key_id: KeyId,
+        overwrite: bool,
+    ) -> Result<RequestId, Error> {
+        let request = Request::EcdhHkdf {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            private_key_id,
+            public_key,
+            salt,
+            info,
+            key_id,
+            overwrite,
+        };
+        self.send_request(request).await
+    }
+
+    /// Sign a device-identity statement with the attestation key `key_id`.
+    /// The statement is written to `statement` and consists of a fixed label, the public key of
+    /// the attestation key and `nonce`. `key_id` must be the attestation key configured in the
+    /// ECC worker, and that key must not be exportable.
+    pub async fn attest(
+        &mut self,
+        key_id: KeyId,
+        nonce: &'data [u8],
+        statement: &'data mut [u8],
+        signature: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::Attest {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            nonce,
+            statement,
+            signature,
+        };
+        self.send_request(request).await
+    }
+
+    /// Replace a symmetric key stored in the HSM with a new key derived from it.
+    /// The key must be allowed to be overwritten.
+    pub async fn rekey(&mut self, key_id: KeyId) -> Result<RequestId, Error> {
+        let request = Request::Rekey {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+        };
+        self.send_request(request).await
+    }
+
+    /// Serialize all exportable keys and encrypt them with AES-GCM under the key encryption key
+    /// `kek_id`. The KEK itself is not part of the backup. The number of keys skipped because they
+    /// are not exportable is returned along with the backup.
+    ///
+    /// # Arguments
+    ///
+    /// * `kek_id`: The AES-128 or AES-256 key used to encrypt the backup
+    /// * `iv`: The GCM initialization vector. Must never be reused with the same KEK.
+    /// * `blob`: Output buffer for the backup. Must be large enough for all exportable keys.
+    pub async fn backup_keystore(
+        &mut self,
+        kek_id: KeyId,
+        iv: &'data [u8],
+        blob: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::BackupKeystore {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            kek_id,
+            iv,
+            blob,
+        };
+        self.send_request(request).await
+    }
+
+    /// Decrypt a backup created by `backup_keystore()` and import all keys contained in it.
+    /// No key is imported if the backup cannot be authenticated or contains an invalid key.
+    /// `blob` is zeroized after processing.
+    pub async fn restore_keystore(
+        &mut self,
+        kek_id: KeyId,
+        blob: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::RestoreKeystore {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            kek_id,
+            blob,
+        };
+        self.send_request(request).await
+    }
+
     /// Symmetrically encrypt a buffer in-place using a key stored in the HSM.
     ///
     /// # Arguments
@@ -215,18 +562,48 @@ impl<
                 aad,
                 tag,
             },
-            SymmetricAlgorithm::AesCbc => Request::EncryptAesCbc {
+            SymmetricAlgorithm::AesCbc(padding) => Request::EncryptAesCbc {
                 client_id: Default::default(),
                 request_id: Default::default(),
                 key_id,
                 iv: nonce,
                 buffer,
+                padding,
                 plaintext_size,
             },
         };
         self.send_request(request).await
     }
 
+    /// Encrypt a buffer in-place with AES-GCM using a nonce generated by the HSM.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_id`: The key identifier to use
+    /// * `nonce`: Buffer of `GCM_IV_SIZE` bytes receiving the generated nonce
+    /// * `buffer`: The buffer containing the plaintext
+    /// * `aad`: 'Additional authenticated data' to be used for tag computation
+    /// * `tag`: Buffer for the generated tag
+    pub async fn encrypt_aes_gcm_random_nonce(
+        &mut self,
+        key_id: KeyId,
+        nonce: &'data mut [u8],
+        buffer: &'data mut [u8],
+        aad: &'data [u8],
+        tag: &'data mut [u8],
+    ) -> Result<RequestId, Error> {
+        let request = Request::EncryptAesGcmRandomNonce {
+            client_id: ClientId::default(),
+            request_id: RequestId::default(),
+            key_id,
+            iv: nonce,
+            buffer,
+            aad,
+            tag,
+        };
+        self.send_request(request).await
+    }
+
     /// Symmetrically encrypt a buffer in-place using a caller-provided key.
     ///
     /// # Arguments
@@ -269,12 +646,13 @@ impl<
                 aad,
                 tag,
             },
-            SymmetricAlgorithm::AesCbc => Request::EncryptAesCbcExternalKey {
+            SymmetricAlgorithm::AesCbc(padding) => Request::EncryptAesCbcExternalKey {
                 client_id: Default::default(),
                 request_id: Default::default(),
                 key,
                 iv: nonce,
                 buffer,
+                padding,
                 plaintext_size,
             },
         };
@@ -319,12 +697,13 @@ impl<
                 aad,
                 tag,
             },
-            SymmetricAlgorithm::AesCbc => Request::DecryptAesCbc {
+            SymmetricAlgorithm::AesCbc(padding) => Request::DecryptAesCbc {
                 client_id: Default::default(),
                 request_id: Default::default(),
                 key_id,
                 iv: nonce,
                 buffer,
+                padding,
             },
         };
         self.send_request(request).await
@@ -370,22 +749,26 @@ impl<
                 aad,
                 tag,
             },
-            SymmetricAlgorithm::AesCbc => Request::DecryptAesCbcExternalKey {
+            SymmetricAlgorithm::AesCbc(padding) => Request::DecryptAesCbcExternalKey {
                 client_id: Default::default(),
                 request_id: Default::default(),
                 key,
                 iv: nonce,
                 buffer,
+                padding,
             },
         };
         self.send_request(request).await
     }
 
     /// Calculate the AES-CMAC of a message using a key stored in the HSM.
+    /// If `message_type` is not empty, the MAC is calculated with a key derived from the stored
+    /// key and `message_type` instead. This prevents reusing MACs across message types.
     pub async fn calculate_aes_cmac(
         &mut self,
         key_id: KeyId,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data mut [u8],
     ) -> Result<RequestId, Error> {
         let request = Request::CalculateAesCmac {
@@ -393,6 +776,7 @@ impl<
             request_id: RequestId::default(),
             key_id,
             message,
+            message_type,
             tag,
         };
         self.send_request(request).await
@@ -416,10 +800,12 @@ impl<
     }
 
     /// Verify the AES-CMAC of a message using a key stored in the HSM.
+    /// `message_type` must match the one used to calculate the MAC.
     pub async fn verify_aes_cmac(
         &mut self,
         key_id: KeyId,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data [u8],
     ) -> Result<RequestId, Error> {
         let request = Request::VerifyAesCmac {
@@ -427,6 +813,7 @@ impl<
             request_id: RequestId::default(),
             key_id,
             message,
+            message_type,
             tag,
         };
         self.send_request(request).await
@@ -449,11 +836,15 @@ impl<
         self.send_request(request).await
     }
 
+    /// Calculate the HMAC of a message using a key stored in the HSM.
+    /// If `message_type` is not empty, the MAC is calculated with a key derived from the stored
+    /// key and `message_type` instead. This prevents reusing MACs across message types.
     pub async fn calculate_hmac(
         &mut self,
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data mut [u8],
     ) -> Result<RequestId, Error> {
         let request = Request::CalculateHmac {
@@ -462,6 +853,7 @@ impl<
             key_id,
             hash_algorithm,
             message,
+            message_type,
             tag,
         };
         self.send_request(request).await
@@ -485,11 +877,14 @@ impl<
         self.send_request(request).await
     }
 
+    /// Verify the HMAC of a message using a key stored in the HSM.
+    /// `message_type` must match the one used to calculate the MAC.
     pub async fn verify_hmac(
         &mut self,
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data [u8],
     ) -> Result<RequestId, Error> {
         let request = Request::VerifyHmac {
@@ -498,6 +893,7 @@ impl<
             key_id,
             hash_algorithm,
             message,
+            message_type,
             tag,
         };
         self.send_request(request).await
@@ -522,6 +918,7 @@ impl<
     }
 
     /// Sign a prehashed message using a key stored in the HSM
+    /// The attestation key of the worker can not be used.
     pub async fn sign(
         &mut self,
         key_id: KeyId,
@@ -566,6 +963,7 @@ impl<
         message: &'data [u8],
         prehashed: bool,
         signature: &'data [u8],
+        encoding: SignatureEncoding,
     ) -> Result<RequestId, Error> {
         let request = Request::Verify {
             client_id: ClientId::default(),
@@ -574,6 +972,7 @@ impl<
             message,
             prehashed,
             signature,
+            encoding,
         };
         self.send_request(request).await
     }
@@ -585,6 +984,7 @@ impl<
         message: &'data [u8],
         prehashed: bool,
         signature: &'data [u8],
+        encoding: SignatureEncoding,
     ) -> Result<RequestId, Error> {
         let request = Request::VerifyExternalKey {
             client_id: ClientId::default(),
@@ -593,6 +993,7 @@ impl<
             message,
             prehashed,
             signature,
+            encoding,
         };
         self.send_request(request).await
     }
@@ -617,3 +1018,29 @@ impl<
         id
     }
 }
+
+/// Mapper for [Api::call_map] returning the random bytes of a `GetRandom` response.
+pub fn random_bytes(response: Response<'_>) -> Result<&mut [u8], Error> {
+    match response {
+        Response::GetRandom { data, .. } => Ok(data),
+        _ => Err(Error::UnexpectedResponse),
+    }
+}
+
+/// Mapper for [Api::call_map] returning the tag of a MAC calculation response.
+pub fn tag(response: Response<'_>) -> Result<&mut [u8], Error> {
+    match response {
+        Response::CalculateAesCmac { tag, .. } | Response::CalculateHmac { tag, .. } => Ok(tag),
+        _ => Err(Error::UnexpectedResponse),
+    }
+}
+
+/// Mapper for [Api::call_map] returning the result of a MAC or signature verification response.
+pub fn verified(response: Response<'_>) -> Result<bool, Error> {
+    match response {
+        Response::VerifyAesCmac { verified, .. }
+        | Response::VerifyHmac { verified, .. }
+        | Response::Verify { verified, .. } => Ok(verified),
+        _ => Err(Error::UnexpectedResponse),
+    }
+}
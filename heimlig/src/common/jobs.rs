@@ -1,7 +1,9 @@
+use crate::common::limits::MAX_HASH_SEGMENTS;
 use crate::hsm::keystore;
 use crate::hsm::keystore::{Curve, KeyId};
+use strum::EnumCount;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumCount)]
 pub enum Error {
     /// No worker found for received request type.
     NoWorkerForRequest,
@@ -19,6 +21,117 @@ pub enum Error {
     Crypto(crate::crypto::Error),
     /// A key store error occurred.
     KeyStore(keystore::Error),
+    /// The HSM is locked after an emergency erase and rejects all requests.
+    Locked,
+    /// The request type is not on the allow-list of the core.
+    OperationNotPermitted,
+    /// Decryption or verification failed. Reported instead of the specific cryptographic error
+    /// by a core that uses [DecryptionErrorPolicy::Generic](crate::hsm::core::DecryptionErrorPolicy).
+    AuthenticationFailed,
+}
+
+/// Number of all distinct errors, including the nested crypto and key store errors.
+const NUM_ERRORS: usize = Error::COUNT - 2 + crate::crypto::Error::COUNT + keystore::Error::COUNT;
+
+/// All job errors. Used to build the table returned by [Error::all_codes].
+const ALL_ERRORS: [Error; NUM_ERRORS] = [
+    Error::NoWorkerForRequest,
+    Error::UnexpectedRequestType,
+    Error::RequestTooLarge,
+    Error::NoKeyStore,
+    Error::Send,
+    Error::StreamTerminated,
+    Error::Locked,
+    Error::OperationNotPermitted,
+    Error::AuthenticationFailed,
+    Error::Crypto(crate::crypto::Error::Encrypt),
+    Error::Crypto(crate::crypto::Error::Decrypt),
+    Error::Crypto(crate::crypto::Error::Sign),
+    Error::Crypto(crate::crypto::Error::Verify),
+    Error::Crypto(crate::crypto::Error::InvalidSymmetricKeySize),
+    Error::Crypto(crate::crypto::Error::InvalidIvSize),
+    Error::Crypto(crate::crypto::Error::InvalidTagSize),
+    Error::Crypto(crate::crypto::Error::InvalidBufferSize),
+    Error::Crypto(crate::crypto::Error::InvalidPadding),
+    Error::Crypto(crate::crypto::Error::InvalidPrivateKey),
+    Error::Crypto(crate::crypto::Error::InvalidPublicKey),
+    Error::Crypto(crate::crypto::Error::InvalidSignatureSize),
+    Error::Crypto(crate::crypto::Error::InvalidSignature),
+    Error::Crypto(crate::crypto::Error::InvalidDigestSize),
+    Error::Crypto(crate::crypto::Error::InvalidSignatureEncoding),
+    Error::Crypto(crate::crypto::Error::WeakPublicKey),
+    Error::Crypto(crate::crypto::Error::IntegrityCheckFailed),
+    Error::Crypto(crate::crypto::Error::LengthMismatch),
+    Error::Crypto(crate::crypto::Error::ReseedRequired),
+    Error::Crypto(crate::crypto::Error::EntropyFailure),
+    Error::KeyStore(keystore::Error::NotAllowed),
+    Error::KeyStore(keystore::Error::KeyNotFound),
+    Error::KeyStore(keystore::Error::KeyAlreadyExists),
+    Error::KeyStore(keystore::Error::KeyStoreTooSmall),
+    Error::KeyStore(keystore::Error::DuplicateIds),
+    Error::KeyStore(keystore::Error::InvalidKeyId),
+    Error::KeyStore(keystore::Error::InvalidKeyType),
+    Error::KeyStore(keystore::Error::InvalidBufferSize),
+];
+
+impl Error {
+    /// Stable numeric code of this error.
+    ///
+    /// Codes never change between versions, new errors get new codes. The upper byte identifies
+    /// the error group (job, crypto or key store), the lower byte the error inside the group.
+    pub const fn code(&self) -> u32 {
+        use crate::crypto::Error as CryptoError;
+        use keystore::Error as KeyStoreError;
+        match self {
+            Error::NoWorkerForRequest => 0x001,
+            Error::UnexpectedRequestType => 0x002,
+            Error::RequestTooLarge => 0x003,
+            Error::NoKeyStore => 0x004,
+            Error::Send => 0x005,
+            Error::StreamTerminated => 0x006,
+            Error::Locked => 0x007,
+            Error::OperationNotPermitted => 0x008,
+            Error::AuthenticationFailed => 0x009,
+            Error::Crypto(e) => match e {
+                CryptoError::Encrypt => 0x101,
+                CryptoError::Decrypt => 0x102,
+                CryptoError::Sign => 0x103,
+                CryptoError::Verify => 0x104,
+                CryptoError::InvalidSymmetricKeySize => 0x105,
+                CryptoError::InvalidIvSize => 0x106,
+                CryptoError::InvalidTagSize => 0x107,
+                CryptoError::InvalidBufferSize => 0x108,
+                CryptoError::InvalidPadding => 0x109,
+                CryptoError::InvalidPrivateKey => 0x10a,
+                CryptoError::InvalidPublicKey => 0x10b,
+                CryptoError::InvalidSignatureSize => 0x10c,
+                CryptoError::InvalidSignature => 0x10d,
+                CryptoError::InvalidDigestSize => 0x10e,
+                CryptoError::InvalidSignatureEncoding => 0x10f,
+                CryptoError::WeakPublicKey => 0x110,
+                CryptoError::IntegrityCheckFailed => 0x111,
+                CryptoError::LengthMismatch => 0x112,
+                CryptoError::ReseedRequired => 0x113,
+                CryptoError::EntropyFailure => 0x114,
+            },
+            Error::KeyStore(e) => match e {
+                KeyStoreError::NotAllowed => 0x201,
+                KeyStoreError::KeyNotFound => 0x202,
+                KeyStoreError::KeyAlreadyExists => 0x203,
+                KeyStoreError::KeyStoreTooSmall => 0x204,
+                KeyStoreError::DuplicateIds => 0x205,
+                KeyStoreError::InvalidKeyId => 0x206,
+                KeyStoreError::InvalidKeyType => 0x207,
+                KeyStoreError::InvalidBufferSize => 0x208,
+            },
+        }
+    }
+
+    /// Mapping of all errors to their stable numeric codes (see [Error::code]).
+    /// Clients can use it to build human-readable error tables without hardcoding the codes.
+    pub fn all_codes() -> impl Iterator<Item = (Error, u32)> {
+        ALL_ERRORS.iter().map(|e| (*e, e.code()))
+    }
 }
 
 impl From<keystore::Error> for Error {
@@ -93,7 +206,132 @@ pub enum HashAlgorithm {
     Sha3_512,
 }
 
+impl HashAlgorithm {
+    /// Internal block size of the hash function in bytes. This is the preferred size of HMAC keys.
+    pub const fn block_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha2_256 => 64,
+            HashAlgorithm::Sha2_384 | HashAlgorithm::Sha2_512 => 128,
+            HashAlgorithm::Sha3_256 => 136,
+            HashAlgorithm::Sha3_384 => 104,
+            HashAlgorithm::Sha3_512 => 72,
+        }
+    }
+
+    /// Size of the digest in bytes.
+    pub const fn digest_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha2_256 | HashAlgorithm::Sha3_256 => crate::crypto::hash::SHA256_SIZE,
+            HashAlgorithm::Sha2_384 | HashAlgorithm::Sha3_384 => crate::crypto::hash::SHA384_SIZE,
+            HashAlgorithm::Sha2_512 | HashAlgorithm::Sha3_512 => crate::crypto::hash::SHA512_SIZE,
+        }
+    }
+}
+
+/// Set of hash algorithms. Used to calculate several digests of the same data at once.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct HashAlgorithms(u8);
+
+impl HashAlgorithms {
+    const ALL: [HashAlgorithm; 6] = [
+        HashAlgorithm::Sha2_256,
+        HashAlgorithm::Sha2_384,
+        HashAlgorithm::Sha2_512,
+        HashAlgorithm::Sha3_256,
+        HashAlgorithm::Sha3_384,
+        HashAlgorithm::Sha3_512,
+    ];
+
+    const fn bit(hash_algorithm: HashAlgorithm) -> u8 {
+        1 << hash_algorithm as u8
+    }
+
+    /// Add `hash_algorithm` to the set.
+    pub const fn with(self, hash_algorithm: HashAlgorithm) -> Self {
+        Self(self.0 | Self::bit(hash_algorithm))
+    }
+
+    pub const fn contains(&self, hash_algorithm: HashAlgorithm) -> bool {
+        self.0 & Self::bit(hash_algorithm) != 0
+    }
+
+    /// Bit `n` is set if the `n`-th variant of [HashAlgorithm] is in the set.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Create a set from its bit representation. Returns `None` if unknown bits are set.
+    pub const fn from_bits(bits: u8) -> Option<Self> {
+        if bits >> Self::ALL.len() != 0 {
+            return None;
+        }
+        Some(Self(bits))
+    }
+
+    /// Iterate over the algorithms in the set in the order of the [HashAlgorithm] variants.
+    pub fn iter(&self) -> impl Iterator<Item = HashAlgorithm> {
+        let set = *self;
+        Self::ALL.into_iter().filter(move |a| set.contains(*a))
+    }
+
+    /// Combined size of the digests of all algorithms in the set.
+    pub fn digests_size(&self) -> usize {
+        self.iter().map(|a| a.digest_size()).sum()
+    }
+}
+
+/// Scatter list of input segments that are hashed as if they were concatenated.
+pub type Segments<'data> = heapless::Vec<&'data [u8], MAX_HASH_SEGMENTS>;
+
+/// Authenticated encryption algorithms supported by the HSM.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AeadAlgorithm {
+    AesGcm,
+    AesCcm,
+    ChaChaPoly,
+}
+
+impl AeadAlgorithm {
+    /// Size of the authentication tag in bytes.
+    pub const fn tag_size(&self) -> usize {
+        match self {
+            AeadAlgorithm::AesGcm => crate::crypto::aes::GCM_TAG_SIZE,
+            AeadAlgorithm::AesCcm => crate::crypto::aes::CCM_TAG_SIZE,
+            AeadAlgorithm::ChaChaPoly => crate::crypto::chacha20poly1305::TAG_SIZE,
+        }
+    }
+}
+
+/// Padding scheme used for AES-CBC.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CbcPadding {
+    /// PKCS#7 padding as specified in RFC 5652.
+    Pkcs7,
+    /// ISO/IEC 7816-4 padding: A single `0x80` byte followed by zero bytes.
+    Iso7816,
+    /// No padding. Plaintext and ciphertext sizes must be multiples of the block size.
+    NoPadding,
+}
+
+/// Encoding of ECDSA signatures passed for verification.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SignatureEncoding {
+    /// Fixed-size concatenation of the `r` and `s` values.
+    Raw,
+    /// ASN.1 DER-encoded `ECDSA-Sig-Value` as specified in RFC 3279.
+    Der,
+}
+
+/// Encoding of exported elliptic curve public keys.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PublicKeyEncoding {
+    /// Concatenated X and Y coordinates.
+    Uncompressed,
+    /// SEC1 compressed point: a tag byte followed by the X coordinate.
+    Compressed,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, EnumCount)]
 pub enum RequestType {
     GetRandom,
     GenerateSymmetricKey,
@@ -130,9 +368,31 @@ pub enum RequestType {
     VerifyExternalKey,
     Ecdh,
     EcdhExternalPrivateKey,
+    Rekey,
+    EmergencyErase,
+    KeyFingerprint,
+    BackupKeystore,
+    RestoreKeystore,
+    ListKeys,
+    EcdhHkdf,
+    GenerateHmacKey,
+    Attest,
+    Version,
+    AeadOutputLen,
+    Hash,
+    VerifyKeyMatches,
+    HashMulti,
+    EncryptAesGcmRandomNonce,
+    HashSegments,
 }
 
 /// A request for the HSM to perform a cryptographic task.
+///
+/// Key generation requests have a `no_response` flag. If it is set, the HSM does not send a
+/// response if the request succeeds. Failed requests are still answered with a
+/// [Response::Error], so clients of fire-and-forget requests must be prepared to receive error
+/// responses for them. Requests that carry buffers are always answered, as the response is the
+/// only signal that the HSM is done with the buffers.
 #[derive(Debug)]
 pub enum Request<'data> {
     GetRandom {
@@ -145,12 +405,14 @@ pub enum Request<'data> {
         request_id: RequestId,
         key_id: KeyId,
         overwrite: bool,
+        no_response: bool,
     },
     GenerateKeyPair {
         client_id: ClientId,
         request_id: RequestId,
         key_id: KeyId,
         overwrite: bool,
+        no_response: bool,
     },
     ImportSymmetricKey {
         client_id: ClientId,
@@ -178,6 +440,7 @@ pub enum Request<'data> {
         request_id: RequestId,
         key_id: KeyId,
         public_key: &'data mut [u8],
+        encoding: PublicKeyEncoding,
     },
     ExportPrivateKey {
         client_id: ClientId,
@@ -235,6 +498,17 @@ pub enum Request<'data> {
         aad: &'data [u8],
         tag: &'data mut [u8],
     },
+    /// Like `EncryptAesGcm`, but the HSM generates a random nonce and writes it to `iv`, which
+    /// must be `GCM_IV_SIZE` bytes long.
+    EncryptAesGcmRandomNonce {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        iv: &'data mut [u8],
+        buffer: &'data mut [u8],
+        aad: &'data [u8],
+        tag: &'data mut [u8],
+    },
     EncryptAesGcmExternalKey {
         client_id: ClientId,
         request_id: RequestId,
@@ -268,6 +542,7 @@ pub enum Request<'data> {
         key_id: KeyId,
         iv: &'data [u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
         plaintext_size: usize,
     },
     EncryptAesCbcExternalKey {
@@ -276,6 +551,7 @@ pub enum Request<'data> {
         key: &'data [u8],
         iv: &'data [u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
         plaintext_size: usize,
     },
     DecryptAesCbc {
@@ -284,6 +560,7 @@ pub enum Request<'data> {
         key_id: KeyId,
         iv: &'data [u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
     },
     DecryptAesCbcExternalKey {
         client_id: ClientId,
@@ -291,12 +568,14 @@ pub enum Request<'data> {
         key: &'data [u8],
         iv: &'data [u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
     },
     CalculateAesCmac {
         client_id: ClientId,
         request_id: RequestId,
         key_id: KeyId,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data mut [u8],
     },
     CalculateAesCmacExternalKey {
@@ -311,6 +590,7 @@ pub enum Request<'data> {
         request_id: RequestId,
         key_id: KeyId,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data [u8],
     },
     VerifyAesCmacExternalKey {
@@ -326,6 +606,7 @@ pub enum Request<'data> {
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data mut [u8],
     },
     CalculateHmacExternalKey {
@@ -342,6 +623,7 @@ pub enum Request<'data> {
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &'data [u8],
+        message_type: &'data [u8],
         tag: &'data [u8],
     },
     VerifyHmacExternalKey {
@@ -375,6 +657,7 @@ pub enum Request<'data> {
         message: &'data [u8],
         prehashed: bool,
         signature: &'data [u8],
+        encoding: SignatureEncoding,
     },
     VerifyExternalKey {
         client_id: ClientId,
@@ -383,6 +666,7 @@ pub enum Request<'data> {
         message: &'data [u8],
         prehashed: bool,
         signature: &'data [u8],
+        encoding: SignatureEncoding,
     },
     Ecdh {
         client_id: ClientId,
@@ -399,6 +683,103 @@ pub enum Request<'data> {
         private_key: &'data [u8],
         shared_secret: &'data mut [u8],
     },
+    Rekey {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+    },
+    EmergencyErase {
+        client_id: ClientId,
+        request_id: RequestId,
+    },
+    KeyFingerprint {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        hash_algorithm: HashAlgorithm,
+        fingerprint: &'data mut [u8],
+    },
+    BackupKeystore {
+        client_id: ClientId,
+        request_id: RequestId,
+        kek_id: KeyId,
+        iv: &'data [u8],
+        blob: &'data mut [u8],
+    },
+    RestoreKeystore {
+        client_id: ClientId,
+        request_id: RequestId,
+        kek_id: KeyId,
+        blob: &'data mut [u8],
+    },
+    ListKeys {
+        client_id: ClientId,
+        request_id: RequestId,
+        start: KeyId,
+        ids: &'data mut [KeyId],
+    },
+    EcdhHkdf {
+        client_id: ClientId,
+        request_id: RequestId,
+        private_key_id: KeyId,
+        public_key: &'data [u8],
+        salt: &'data [u8],
+        info: &'data [u8],
+        key_id: KeyId,
+        overwrite: bool,
+    },
+    GenerateHmacKey {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        hash_algorithm: HashAlgorithm,
+        overwrite: bool,
+    },
+    Attest {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        nonce: &'data [u8],
+        statement: &'data mut [u8],
+        signature: &'data mut [u8],
+    },
+    Version {
+        client_id: ClientId,
+        request_id: RequestId,
+    },
+    AeadOutputLen {
+        client_id: ClientId,
+        request_id: RequestId,
+        algorithm: AeadAlgorithm,
+        plaintext_len: usize,
+    },
+    Hash {
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithm: HashAlgorithm,
+        data: &'data [u8],
+        digest: &'data mut [u8],
+    },
+    VerifyKeyMatches {
+        client_id: ClientId,
+        request_id: RequestId,
+        private_key_id: KeyId,
+        expected_public_key: &'data [u8],
+    },
+    HashMulti {
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithms: HashAlgorithms,
+        data: &'data [u8],
+        digests: &'data mut [u8],
+    },
+    HashSegments {
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithm: HashAlgorithm,
+        segments: Segments<'data>,
+        digest: &'data mut [u8],
+    },
 }
 
 impl RequestType {
@@ -413,6 +794,10 @@ impl RequestType {
                 | RequestType::ExportPublicKey
                 | RequestType::ExportPrivateKey
                 | RequestType::IsKeyAvailable
+                | RequestType::EmergencyErase
+                | RequestType::ListKeys
+                | RequestType::Version
+                | RequestType::AeadOutputLen
         )
     }
 
@@ -490,6 +875,13 @@ pub enum Response<'data> {
         buffer: &'data mut [u8],
         tag: &'data mut [u8],
     },
+    EncryptAesGcmRandomNonce {
+        client_id: ClientId,
+        request_id: RequestId,
+        iv: &'data mut [u8],
+        buffer: &'data mut [u8],
+        tag: &'data mut [u8],
+    },
     DecryptAesGcm {
         client_id: ClientId,
         request_id: RequestId,
@@ -540,6 +932,84 @@ pub enum Response<'data> {
         request_id: RequestId,
         shared_secret: &'data mut [u8],
     },
+    Rekey {
+        client_id: ClientId,
+        request_id: RequestId,
+    },
+    EmergencyErase {
+        client_id: ClientId,
+        request_id: RequestId,
+    },
+    KeyFingerprint {
+        client_id: ClientId,
+        request_id: RequestId,
+        fingerprint: &'data mut [u8],
+    },
+    BackupKeystore {
+        client_id: ClientId,
+        request_id: RequestId,
+        blob: &'data mut [u8],
+        skipped: u32,
+    },
+    RestoreKeystore {
+        client_id: ClientId,
+        request_id: RequestId,
+        restored: u32,
+    },
+    KeyList {
+        client_id: ClientId,
+        request_id: RequestId,
+        ids: &'data mut [KeyId],
+        next: Option<KeyId>,
+    },
+    EcdhHkdf {
+        client_id: ClientId,
+        request_id: RequestId,
+    },
+    GenerateHmacKey {
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+    },
+    Attest {
+        client_id: ClientId,
+        request_id: RequestId,
+        statement: &'data mut [u8],
+        signature: &'data mut [u8],
+    },
+    Version {
+        client_id: ClientId,
+        request_id: RequestId,
+        crate_version: &'static str,
+        protocol_version: u32,
+        features: u32,
+    },
+    AeadOutputLen {
+        client_id: ClientId,
+        request_id: RequestId,
+        ciphertext_len: usize,
+        tag_len: usize,
+    },
+    Hash {
+        client_id: ClientId,
+        request_id: RequestId,
+        digest: &'data mut [u8],
+    },
+    VerifyKeyMatches {
+        client_id: ClientId,
+        request_id: RequestId,
+        matches: bool,
+    },
+    HashMulti {
+        client_id: ClientId,
+        request_id: RequestId,
+        digests: &'data mut [u8],
+    },
+    HashSegments {
+        client_id: ClientId,
+        request_id: RequestId,
+        digest: &'data mut [u8],
+    },
 }
 
 impl<'data> Request<'data> {
@@ -564,6 +1034,7 @@ impl<'data> Request<'data> {
             }
             Request::EncryptAesGcm { .. } => RequestType::EncryptAesGcm,
             Request::EncryptAesGcmExternalKey { .. } => RequestType::EncryptAesGcmExternalKey,
+            Request::EncryptAesGcmRandomNonce { .. } => RequestType::EncryptAesGcmRandomNonce,
             Request::DecryptAesGcm { .. } => RequestType::DecryptAesGcm,
             Request::DecryptAesGcmExternalKey { .. } => RequestType::DecryptAesGcmExternalKey,
             Request::EncryptAesCbc { .. } => RequestType::EncryptAesCbc,
@@ -584,6 +1055,21 @@ impl<'data> Request<'data> {
             Request::VerifyExternalKey { .. } => RequestType::VerifyExternalKey,
             Request::Ecdh { .. } => RequestType::Ecdh,
             Request::EcdhExternalPrivateKey { .. } => RequestType::EcdhExternalPrivateKey,
+            Request::Rekey { .. } => RequestType::Rekey,
+            Request::EmergencyErase { .. } => RequestType::EmergencyErase,
+            Request::KeyFingerprint { .. } => RequestType::KeyFingerprint,
+            Request::BackupKeystore { .. } => RequestType::BackupKeystore,
+            Request::RestoreKeystore { .. } => RequestType::RestoreKeystore,
+            Request::ListKeys { .. } => RequestType::ListKeys,
+            Request::EcdhHkdf { .. } => RequestType::EcdhHkdf,
+            Request::GenerateHmacKey { .. } => RequestType::GenerateHmacKey,
+            Request::Attest { .. } => RequestType::Attest,
+            Request::Version { .. } => RequestType::Version,
+            Request::AeadOutputLen { .. } => RequestType::AeadOutputLen,
+            Request::Hash { .. } => RequestType::Hash,
+            Request::VerifyKeyMatches { .. } => RequestType::VerifyKeyMatches,
+            Request::HashMulti { .. } => RequestType::HashMulti,
+            Request::HashSegments { .. } => RequestType::HashSegments,
         }
     }
 
@@ -604,6 +1090,7 @@ impl<'data> Request<'data> {
             Request::DecryptChaChaPolyExternalKey { client_id, .. } => client_id,
             Request::EncryptAesGcm { client_id, .. } => client_id,
             Request::EncryptAesGcmExternalKey { client_id, .. } => client_id,
+            Request::EncryptAesGcmRandomNonce { client_id, .. } => client_id,
             Request::DecryptAesGcm { client_id, .. } => client_id,
             Request::DecryptAesGcmExternalKey { client_id, .. } => client_id,
             Request::EncryptAesCbc { client_id, .. } => client_id,
@@ -624,6 +1111,21 @@ impl<'data> Request<'data> {
             Request::VerifyExternalKey { client_id, .. } => client_id,
             Request::Ecdh { client_id, .. } => client_id,
             Request::EcdhExternalPrivateKey { client_id, .. } => client_id,
+            Request::Rekey { client_id, .. } => client_id,
+            Request::EmergencyErase { client_id, .. } => client_id,
+            Request::KeyFingerprint { client_id, .. } => client_id,
+            Request::BackupKeystore { client_id, .. } => client_id,
+            Request::RestoreKeystore { client_id, .. } => client_id,
+            Request::ListKeys { client_id, .. } => client_id,
+            Request::EcdhHkdf { client_id, .. } => client_id,
+            Request::GenerateHmacKey { client_id, .. } => client_id,
+            Request::Attest { client_id, .. } => client_id,
+            Request::Version { client_id, .. } => client_id,
+            Request::AeadOutputLen { client_id, .. } => client_id,
+            Request::Hash { client_id, .. } => client_id,
+            Request::VerifyKeyMatches { client_id, .. } => client_id,
+            Request::HashMulti { client_id, .. } => client_id,
+            Request::HashSegments { client_id, .. } => client_id,
         }
     }
 
@@ -644,6 +1146,7 @@ impl<'data> Request<'data> {
             Request::DecryptChaChaPolyExternalKey { request_id, .. } => request_id,
             Request::EncryptAesGcm { request_id, .. } => request_id,
             Request::EncryptAesGcmExternalKey { request_id, .. } => request_id,
+            Request::EncryptAesGcmRandomNonce { request_id, .. } => request_id,
             Request::DecryptAesGcm { request_id, .. } => request_id,
             Request::DecryptAesGcmExternalKey { request_id, .. } => request_id,
             Request::EncryptAesCbc { request_id, .. } => request_id,
@@ -664,9 +1167,40 @@ impl<'data> Request<'data> {
             Request::VerifyExternalKey { request_id, .. } => request_id,
             Request::Ecdh { request_id, .. } => request_id,
             Request::EcdhExternalPrivateKey { request_id, .. } => request_id,
+            Request::Rekey { request_id, .. } => request_id,
+            Request::EmergencyErase { request_id, .. } => request_id,
+            Request::KeyFingerprint { request_id, .. } => request_id,
+            Request::BackupKeystore { request_id, .. } => request_id,
+            Request::RestoreKeystore { request_id, .. } => request_id,
+            Request::ListKeys { request_id, .. } => request_id,
+            Request::EcdhHkdf { request_id, .. } => request_id,
+            Request::GenerateHmacKey { request_id, .. } => request_id,
+            Request::Attest { request_id, .. } => request_id,
+            Request::Version { request_id, .. } => request_id,
+            Request::AeadOutputLen { request_id, .. } => request_id,
+            Request::Hash { request_id, .. } => request_id,
+            Request::VerifyKeyMatches { request_id, .. } => request_id,
+            Request::HashMulti { request_id, .. } => request_id,
+            Request::HashSegments { request_id, .. } => request_id,
         }
     }
 
+    /// Whether the client waits for a response to this request.
+    /// Workers and the core must only send error responses for requests that return `false` here.
+    /// Use [Response::is_sent] to decide.
+    pub fn expects_response(&self) -> bool {
+        !matches!(
+            self,
+            Request::GenerateSymmetricKey {
+                no_response: true,
+                ..
+            } | Request::GenerateKeyPair {
+                no_response: true,
+                ..
+            }
+        )
+    }
+
     pub fn set_client_id(&mut self, new_client_id: ClientId) {
         match self {
             Request::GetRandom { client_id, .. } => *client_id = new_client_id,
@@ -684,6 +1218,7 @@ impl<'data> Request<'data> {
             Request::DecryptChaChaPolyExternalKey { client_id, .. } => *client_id = new_client_id,
             Request::EncryptAesGcm { client_id, .. } => *client_id = new_client_id,
             Request::EncryptAesGcmExternalKey { client_id, .. } => *client_id = new_client_id,
+            Request::EncryptAesGcmRandomNonce { client_id, .. } => *client_id = new_client_id,
             Request::DecryptAesGcm { client_id, .. } => *client_id = new_client_id,
             Request::DecryptAesGcmExternalKey { client_id, .. } => *client_id = new_client_id,
             Request::EncryptAesCbc { client_id, .. } => *client_id = new_client_id,
@@ -704,6 +1239,21 @@ impl<'data> Request<'data> {
             Request::VerifyExternalKey { client_id, .. } => *client_id = new_client_id,
             Request::Ecdh { client_id, .. } => *client_id = new_client_id,
             Request::EcdhExternalPrivateKey { client_id, .. } => *client_id = new_client_id,
+            Request::Rekey { client_id, .. } => *client_id = new_client_id,
+            Request::EmergencyErase { client_id, .. } => *client_id = new_client_id,
+            Request::KeyFingerprint { client_id, .. } => *client_id = new_client_id,
+            Request::BackupKeystore { client_id, .. } => *client_id = new_client_id,
+            Request::RestoreKeystore { client_id, .. } => *client_id = new_client_id,
+            Request::ListKeys { client_id, .. } => *client_id = new_client_id,
+            Request::EcdhHkdf { client_id, .. } => *client_id = new_client_id,
+            Request::GenerateHmacKey { client_id, .. } => *client_id = new_client_id,
+            Request::Attest { client_id, .. } => *client_id = new_client_id,
+            Request::Version { client_id, .. } => *client_id = new_client_id,
+            Request::AeadOutputLen { client_id, .. } => *client_id = new_client_id,
+            Request::Hash { client_id, .. } => *client_id = new_client_id,
+            Request::VerifyKeyMatches { client_id, .. } => *client_id = new_client_id,
+            Request::HashMulti { client_id, .. } => *client_id = new_client_id,
+            Request::HashSegments { client_id, .. } => *client_id = new_client_id,
         }
     }
 
@@ -728,6 +1278,7 @@ impl<'data> Request<'data> {
             }
             Request::EncryptAesGcm { request_id, .. } => *request_id = new_request_id,
             Request::EncryptAesGcmExternalKey { request_id, .. } => *request_id = new_request_id,
+            Request::EncryptAesGcmRandomNonce { request_id, .. } => *request_id = new_request_id,
             Request::DecryptAesGcm { request_id, .. } => *request_id = new_request_id,
             Request::DecryptAesGcmExternalKey { request_id, .. } => *request_id = new_request_id,
             Request::EncryptAesCbc { request_id, .. } => *request_id = new_request_id,
@@ -748,11 +1299,32 @@ impl<'data> Request<'data> {
             Request::VerifyExternalKey { request_id, .. } => *request_id = new_request_id,
             Request::Ecdh { request_id, .. } => *request_id = new_request_id,
             Request::EcdhExternalPrivateKey { request_id, .. } => *request_id = new_request_id,
+            Request::Rekey { request_id, .. } => *request_id = new_request_id,
+            Request::EmergencyErase { request_id, .. } => *request_id = new_request_id,
+            Request::KeyFingerprint { request_id, .. } => *request_id = new_request_id,
+            Request::BackupKeystore { request_id, .. } => *request_id = new_request_id,
+            Request::RestoreKeystore { request_id, .. } => *request_id = new_request_id,
+            Request::ListKeys { request_id, .. } => *request_id = new_request_id,
+            Request::EcdhHkdf { request_id, .. } => *request_id = new_request_id,
+            Request::GenerateHmacKey { request_id, .. } => *request_id = new_request_id,
+            Request::Attest { request_id, .. } => *request_id = new_request_id,
+            Request::Version { request_id, .. } => *request_id = new_request_id,
+            Request::AeadOutputLen { request_id, .. } => *request_id = new_request_id,
+            Request::Hash { request_id, .. } => *request_id = new_request_id,
+            Request::VerifyKeyMatches { request_id, .. } => *request_id = new_request_id,
+            Request::HashMulti { request_id, .. } => *request_id = new_request_id,
+            Request::HashSegments { request_id, .. } => *request_id = new_request_id,
         }
     }
 }
 
 impl<'data> Response<'data> {
+    /// Whether this response is sent for a request with the given [Request::expects_response]
+    /// value. Error responses are always sent.
+    pub fn is_sent(&self, expects_response: bool) -> bool {
+        expects_response || matches!(self, Response::Error { .. })
+    }
+
     pub fn get_client_id(&self) -> ClientId {
         *match self {
             Response::Error { client_id, .. } => client_id,
@@ -768,6 +1340,7 @@ impl<'data> Response<'data> {
             Response::EncryptChaChaPoly { client_id, .. } => client_id,
             Response::DecryptChaChaPoly { client_id, .. } => client_id,
             Response::EncryptAesGcm { client_id, .. } => client_id,
+            Response::EncryptAesGcmRandomNonce { client_id, .. } => client_id,
             Response::DecryptAesGcm { client_id, .. } => client_id,
             Response::EncryptAesCbc { client_id, .. } => client_id,
             Response::DecryptAesCbc { client_id, .. } => client_id,
@@ -778,6 +1351,21 @@ impl<'data> Response<'data> {
             Response::Sign { client_id, .. } => client_id,
             Response::Verify { client_id, .. } => client_id,
             Response::Ecdh { client_id, .. } => client_id,
+            Response::Rekey { client_id, .. } => client_id,
+            Response::EmergencyErase { client_id, .. } => client_id,
+            Response::KeyFingerprint { client_id, .. } => client_id,
+            Response::BackupKeystore { client_id, .. } => client_id,
+            Response::RestoreKeystore { client_id, .. } => client_id,
+            Response::KeyList { client_id, .. } => client_id,
+            Response::EcdhHkdf { client_id, .. } => client_id,
+            Response::GenerateHmacKey { client_id, .. } => client_id,
+            Response::Attest { client_id, .. } => client_id,
+            Response::Version { client_id, .. } => client_id,
+            Response::AeadOutputLen { client_id, .. } => client_id,
+            Response::Hash { client_id, .. } => client_id,
+            Response::VerifyKeyMatches { client_id, .. } => client_id,
+            Response::HashMulti { client_id, .. } => client_id,
+            Response::HashSegments { client_id, .. } => client_id,
         }
     }
 
@@ -796,6 +1384,7 @@ impl<'data> Response<'data> {
             Response::EncryptChaChaPoly { request_id, .. } => request_id,
             Response::DecryptChaChaPoly { request_id, .. } => request_id,
             Response::EncryptAesGcm { request_id, .. } => request_id,
+            Response::EncryptAesGcmRandomNonce { request_id, .. } => request_id,
             Response::DecryptAesGcm { request_id, .. } => request_id,
             Response::EncryptAesCbc { request_id, .. } => request_id,
             Response::DecryptAesCbc { request_id, .. } => request_id,
@@ -806,6 +1395,61 @@ impl<'data> Response<'data> {
             Response::Sign { request_id, .. } => request_id,
             Response::Verify { request_id, .. } => request_id,
             Response::Ecdh { request_id, .. } => request_id,
+            Response::Rekey { request_id, .. } => request_id,
+            Response::EmergencyErase { request_id, .. } => request_id,
+            Response::KeyFingerprint { request_id, .. } => request_id,
+            Response::BackupKeystore { request_id, .. } => request_id,
+            Response::RestoreKeystore { request_id, .. } => request_id,
+            Response::KeyList { request_id, .. } => request_id,
+            Response::EcdhHkdf { request_id, .. } => request_id,
+            Response::GenerateHmacKey { request_id, .. } => request_id,
+            Response::Attest { request_id, .. } => request_id,
+            Response::Version { request_id, .. } => request_id,
+            Response::AeadOutputLen { request_id, .. } => request_id,
+            Response::Hash { request_id, .. } => request_id,
+            Response::VerifyKeyMatches { request_id, .. } => request_id,
+            Response::HashMulti { request_id, .. } => request_id,
+            Response::HashSegments { request_id, .. } => request_id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn error_codes_are_complete_and_unique() {
+        assert_eq!(Error::all_codes().count(), NUM_ERRORS);
+        for (i, (error, code)) in Error::all_codes().enumerate() {
+            assert!(Error::all_codes()
+                .skip(i + 1)
+                .all(|(e, c)| e != error && c != code));
         }
     }
+
+    #[test]
+    fn error_codes_are_stable() {
+        let expected: [u32; NUM_ERRORS] = [
+            0x001, 0x002, 0x003, 0x004, 0x005, 0x006, 0x007, 0x008, 0x009, 0x101, 0x102, 0x103,
+            0x104, 0x105, 0x106, 0x107, 0x108, 0x109, 0x10a, 0x10b, 0x10c, 0x10d, 0x10e, 0x10f,
+            0x110, 0x111, 0x112, 0x113, 0x114, 0x201, 0x202, 0x203, 0x204, 0x205, 0x206, 0x207,
+            0x208,
+        ];
+        assert!(Error::all_codes().map(|(_, c)| c).eq(expected));
+    }
+
+    #[test]
+    fn hash_algorithms_bits() {
+        let set = HashAlgorithms::default()
+            .with(HashAlgorithm::Sha3_512)
+            .with(HashAlgorithm::Sha2_256);
+        assert_eq!(set.bits(), 0b10_0001);
+        assert_eq!(HashAlgorithms::from_bits(set.bits()), Some(set));
+        assert_eq!(HashAlgorithms::from_bits(0b100_0000), None);
+        assert!(set
+            .iter()
+            .eq([HashAlgorithm::Sha2_256, HashAlgorithm::Sha3_512]));
+        assert_eq!(set.digests_size(), 32 + 64);
+    }
 }
@@ -6,3 +6,6 @@ pub const MAX_PLAINTEXT_SIZE: usize = 1500; // Ethernet max. MTU size
 
 /// Maximum ciphertext length for symmetric encryption.
 pub const MAX_CIPHERTEXT_SIZE: usize = 1500; // Ethernet max. MTU size
+
+/// Maximum number of input segments of a segmented hash request.
+pub const MAX_HASH_SEGMENTS: usize = 4;
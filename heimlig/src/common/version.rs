@@ -0,0 +1,17 @@
+/// Version of the heimlig crate the HSM core was built from.
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Version of the request/response protocol. Incremented on incompatible changes to the jobs:
+/// * 2: `Verify` and `VerifyExternalKey` take a signature encoding.
+/// * 3: `ExportPublicKey` takes a public key encoding.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// Feature flag set if the core was built with debug assertions enabled.
+pub const FEATURE_DEBUG_ASSERTIONS: u32 = 1 << 0;
+
+/// Feature flags the core was compiled with.
+pub const FEATURES: u32 = if cfg!(debug_assertions) {
+    FEATURE_DEBUG_ASSERTIONS
+} else {
+    0
+};
@@ -103,7 +103,7 @@ mod test {
     extern crate alloc;
     use super::*;
     use crate::crypto::aes::{test::*, BLOCK_SIZE};
-    use aes::cipher::block_padding::{NoPadding, Pkcs7};
+    use aes::cipher::block_padding::{Iso7816, NoPadding, Pkcs7};
     use alloc::borrow::ToOwned;
     use heapless::Vec;
 
@@ -235,6 +235,114 @@ mod test {
         ]
     );
 
+    /// NIST SP 800-38A, appendix F.2: four blocks without padding.
+    macro_rules! define_sp800_38a_test {
+        (
+            $test_name:ident,
+            $encryptor:ident,
+            $decryptor:ident,
+            $key:expr,
+            $ciphertext:expr
+        ) => {
+            #[test]
+            fn $test_name() {
+                let key = hex::decode($key).expect("Failed to decode hex string");
+                let iv = hex::decode("000102030405060708090a0b0c0d0e0f")
+                    .expect("Failed to decode hex string");
+                let plaintext = hex::decode(concat!(
+                    "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51",
+                    "30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+                ))
+                .expect("Failed to decode hex string");
+                let ciphertext = hex::decode($ciphertext).expect("Failed to decode hex string");
+
+                let mut buffer = plaintext.clone();
+                let encrypted = $encryptor::<NoPadding>(&key, &iv, &mut buffer, plaintext.len())
+                    .expect("encryption error");
+                assert_eq!(encrypted, ciphertext);
+
+                let decrypted =
+                    $decryptor::<NoPadding>(&key, &iv, &mut buffer).expect("decryption error");
+                assert_eq!(decrypted, plaintext);
+            }
+        };
+    }
+
+    // F.2.1 and F.2.2
+    define_sp800_38a_test!(
+        test_aes128cbc_sp800_38a,
+        aes128cbc_encrypt,
+        aes128cbc_decrypt,
+        "2b7e151628aed2a6abf7158809cf4f3c",
+        concat!(
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2",
+            "73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7",
+        )
+    );
+    // F.2.3 and F.2.4
+    define_sp800_38a_test!(
+        test_aes192cbc_sp800_38a,
+        aes192cbc_encrypt,
+        aes192cbc_decrypt,
+        "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+        concat!(
+            "4f021db243bc633d7178183a9fa071e8b4d9ada9ad7dedf4e5e738763f69145a",
+            "571b242012fb7ae07fa9baac3df102e008b0e27988598881d920a9e64f5615cd",
+        )
+    );
+    // F.2.5 and F.2.6
+    define_sp800_38a_test!(
+        test_aes256cbc_sp800_38a,
+        aes256cbc_encrypt,
+        aes256cbc_decrypt,
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+        concat!(
+            "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d",
+            "39f23369a9d9bacfa530e26304231461b2eb05e2c39be9fcda6c19078c6a9d1b",
+        )
+    );
+
+    #[test]
+    fn test_aes128cbc_encrypt_decrypt_iso7816() {
+        let mut buffer = [0u8; 2 * BLOCK_SIZE];
+        buffer[..PLAINTEXT.len()].copy_from_slice(PLAINTEXT);
+        let encrypted =
+            encrypt_in_place::<Aes128, Iso7816>(KEY128, CBC_IV, &mut buffer, PLAINTEXT.len())
+                .expect("encryption error")
+                .to_owned();
+        assert_eq!(encrypted.len(), BLOCK_SIZE);
+
+        // ISO/IEC 7816-4 padding appends 0x80 followed by zeros
+        let mut manually_padded = [0u8; BLOCK_SIZE];
+        manually_padded[..PLAINTEXT.len()].copy_from_slice(PLAINTEXT);
+        manually_padded[PLAINTEXT.len()] = 0x80;
+        let expected =
+            encrypt_in_place::<Aes128, NoPadding>(KEY128, CBC_IV, &mut manually_padded, BLOCK_SIZE)
+                .expect("encryption error");
+        assert_eq!(encrypted, expected);
+
+        let decrypted =
+            decrypt_in_place::<Aes128, Iso7816>(KEY128, CBC_IV, &mut buffer[..BLOCK_SIZE])
+                .expect("decryption error");
+        assert_eq!(decrypted, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_aes128cbc_malformed_padding() {
+        // Zero bytes are neither valid PKCS#7 nor ISO/IEC 7816-4 padding
+        let mut ciphertext = [0u8; BLOCK_SIZE];
+        encrypt_in_place::<Aes128, NoPadding>(KEY128, CBC_IV, &mut ciphertext, BLOCK_SIZE)
+            .expect("encryption error");
+        assert_eq!(
+            decrypt_in_place::<Aes128, Pkcs7>(KEY128, CBC_IV, &mut ciphertext.clone()),
+            Err(Error::InvalidPadding)
+        );
+        assert_eq!(
+            decrypt_in_place::<Aes128, Iso7816>(KEY128, CBC_IV, &mut ciphertext.clone()),
+            Err(Error::InvalidPadding)
+        );
+    }
+
     macro_rules! define_aes_cbc_wrong_key_test {
         (
         $test_name:ident,
@@ -7,13 +7,16 @@ use aes::{
 };
 use ccm::{
     aead::{generic_array::ArrayLength, AeadInPlace},
-    consts::{U13, U16},
+    consts::{U13, U16, U4, U8},
     Ccm, NonceSize, Tag, TagSize,
 };
 
 pub type SupportedNonceSize = U13;
 pub type SupportedTagSize = U16;
 
+/// Tag sizes accepted by the AES-CCM functions with selectable tag size.
+pub const SUPPORTED_TAG_SIZES: [usize; 3] = [4, 8, 16];
+
 fn encrypt_in_place_detached<C, T, N>(
     key: &[u8],
     nonce: &[u8],
@@ -22,7 +25,7 @@ fn encrypt_in_place_detached<C, T, N>(
 ) -> Result<Tag<T>, Error>
 where
     C: BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + KeySizeUser + KeyInit,
-    T: ArrayLength<u8> + TagSize + Same<SupportedTagSize>,
+    T: ArrayLength<u8> + TagSize,
     N: ArrayLength<u8> + NonceSize + Same<SupportedNonceSize>,
 {
     check_sizes(key, nonce, C::KeySize::USIZE, N::USIZE)?;
@@ -40,7 +43,7 @@ fn decrypt_in_place_detached<C, T, N>(
 ) -> Result<(), Error>
 where
     C: BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + KeySizeUser + KeyInit,
-    T: ArrayLength<u8> + TagSize + Same<SupportedTagSize>,
+    T: ArrayLength<u8> + TagSize,
     N: ArrayLength<u8> + NonceSize + Same<SupportedNonceSize>,
 {
     check_sizes_with_tag(key, nonce, tag, C::KeySize::USIZE, N::USIZE, T::USIZE)?;
@@ -49,10 +52,84 @@ where
         .map_err(|_| Error::Decrypt)
 }
 
+/// AES-CCM encryption with the tag size given by the size of `tag`.
+fn encrypt_in_place_detached_with_tag_size<C>(
+    key: &[u8],
+    nonce: &[u8],
+    associated_data: &[u8],
+    buffer: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + KeySizeUser + KeyInit,
+{
+    match tag.len() {
+        4 => tag.copy_from_slice(&encrypt_in_place_detached::<C, U4, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+        )?),
+        8 => tag.copy_from_slice(&encrypt_in_place_detached::<C, U8, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+        )?),
+        16 => tag.copy_from_slice(&encrypt_in_place_detached::<C, U16, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+        )?),
+        _ => return Err(Error::InvalidTagSize),
+    }
+    Ok(())
+}
+
+/// AES-CCM decryption with the tag size given by the size of `tag`.
+fn decrypt_in_place_detached_with_tag_size<C>(
+    key: &[u8],
+    nonce: &[u8],
+    associated_data: &[u8],
+    buffer: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockSizeUser<BlockSize = U16> + BlockEncrypt + KeySizeUser + KeyInit,
+{
+    match tag.len() {
+        4 => decrypt_in_place_detached::<C, U4, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+            tag,
+        ),
+        8 => decrypt_in_place_detached::<C, U8, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+            tag,
+        ),
+        16 => decrypt_in_place_detached::<C, U16, SupportedNonceSize>(
+            key,
+            nonce,
+            associated_data,
+            buffer,
+            tag,
+        ),
+        _ => Err(Error::InvalidTagSize),
+    }
+}
+
 macro_rules! define_aes_ccm_impl {
     (
         $encryptor:ident,
         $decryptor:ident,
+        $encryptor_with_tag_size:ident,
+        $decryptor_with_tag_size:ident,
         $core:tt,
         $tag_size: tt,
         $nonce_size: tt,
@@ -75,12 +152,50 @@ macro_rules! define_aes_ccm_impl {
         ) -> Result<(), Error> {
             decrypt_in_place_detached::<$core, $tag_size, $nonce_size>(key, nonce, aad, buffer, tag)
         }
+
+        /// Encrypt `buffer` in place and write a tag of `tag.len()` bytes.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidTagSize`: The size of `tag` is not in `SUPPORTED_TAG_SIZES`.
+        /// * `InvalidSymmetricKeySize` or `InvalidIvSize`: `key` or `nonce` have invalid sizes.
+        pub fn $encryptor_with_tag_size(
+            key: &[u8],
+            nonce: &[u8],
+            aad: &[u8],
+            buffer: &mut [u8],
+            tag: &mut [u8],
+        ) -> Result<(), Error> {
+            encrypt_in_place_detached_with_tag_size::<$core>(key, nonce, aad, buffer, tag)
+        }
+
+        /// Decrypt `buffer` in place and verify the `tag` of `tag.len()` bytes. No plaintext is
+        /// released if verification fails.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidTagSize`: The size of `tag` is not in `SUPPORTED_TAG_SIZES`.
+        /// * `InvalidSymmetricKeySize` or `InvalidIvSize`: `key` or `nonce` have invalid sizes.
+        /// * `Decrypt`: The tag does not match.
+        pub fn $decryptor_with_tag_size(
+            key: &[u8],
+            nonce: &[u8],
+            aad: &[u8],
+            buffer: &mut [u8],
+            tag: &[u8],
+        ) -> Result<(), Error> {
+            decrypt_in_place_detached_with_tag_size::<$core>(key, nonce, aad, buffer, tag)
+        }
     };
 }
 
 define_aes_ccm_impl!(
     aes128ccm_encrypt,
     aes128ccm_decrypt,
+    aes128ccm_encrypt_with_tag_size,
+    aes128ccm_decrypt_with_tag_size,
     Aes128,
     SupportedTagSize,
     SupportedNonceSize,
@@ -88,6 +203,8 @@ define_aes_ccm_impl!(
 define_aes_ccm_impl!(
     aes192ccm_encrypt,
     aes192ccm_decrypt,
+    aes192ccm_encrypt_with_tag_size,
+    aes192ccm_decrypt_with_tag_size,
     Aes192,
     SupportedTagSize,
     SupportedNonceSize,
@@ -95,6 +212,8 @@ define_aes_ccm_impl!(
 define_aes_ccm_impl!(
     aes256ccm_encrypt,
     aes256ccm_decrypt,
+    aes256ccm_encrypt_with_tag_size,
+    aes256ccm_decrypt_with_tag_size,
     Aes256,
     SupportedTagSize,
     SupportedNonceSize,
@@ -341,4 +460,84 @@ mod test {
         PLAINTEXT,
         [0, 1, 8, 16, 24, 256]
     );
+
+    // RFC 3610, packet vectors #1 and #2 (8 byte tags)
+    #[test]
+    fn test_aes128ccm_rfc3610() {
+        let key: [u8; 16] = core::array::from_fn(|i| 0xc0 + i as u8);
+        let aad: [u8; 8] = core::array::from_fn(|i| i as u8);
+        for (nonce, payload_end, expected) in [
+            (
+                "00000003020100a0a1a2a3a4a5",
+                0x1f,
+                "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0",
+            ),
+            (
+                "00000004030201a0a1a2a3a4a5",
+                0x20,
+                "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916",
+            ),
+        ] {
+            let nonce = hex::decode(nonce).expect("Failed to decode hex string");
+            let expected = hex::decode(expected).expect("Failed to decode hex string");
+            let plaintext: Vec<u8, 32> = (0x08..payload_end).collect();
+            let mut buffer = plaintext.clone();
+            let mut tag = [0u8; 8];
+            aes128ccm_encrypt_with_tag_size(&key, &nonce, &aad, &mut buffer, &mut tag)
+                .expect("encryption error");
+            assert_eq!(buffer, expected[..plaintext.len()], "ciphertext mismatch");
+            assert_eq!(tag, expected[plaintext.len()..], "tag mismatch");
+            aes128ccm_decrypt_with_tag_size(&key, &nonce, &aad, &mut buffer, &tag)
+                .expect("decryption error");
+            assert_eq!(buffer, plaintext, "plaintext mismatch");
+        }
+    }
+
+    #[test]
+    fn test_aes128ccm_tag_sizes() {
+        let mut expected_tag = [0u8; CCM_TAG_SIZE];
+        let mut expected_buffer = PLAINTEXT.to_owned();
+        expected_tag.copy_from_slice(
+            &aes128ccm_encrypt(KEY128, NONCE, &[], &mut expected_buffer).expect("encryption error"),
+        );
+        let mut buffer = PLAINTEXT.to_owned();
+        let mut tag = [0u8; CCM_TAG_SIZE];
+        aes128ccm_encrypt_with_tag_size(KEY128, NONCE, &[], &mut buffer, &mut tag)
+            .expect("encryption error");
+        assert_eq!(buffer, expected_buffer);
+        assert_eq!(tag, expected_tag);
+
+        for tag_size in SUPPORTED_TAG_SIZES {
+            let mut buffer = PLAINTEXT.to_owned();
+            let mut tag = [0u8; CCM_TAG_SIZE];
+            let tag = &mut tag[..tag_size];
+            aes128ccm_encrypt_with_tag_size(KEY128, NONCE, &[], &mut buffer, tag)
+                .expect("encryption error");
+            tag[0] ^= 1;
+            assert_eq!(
+                aes128ccm_decrypt_with_tag_size(KEY128, NONCE, &[], &mut buffer, tag),
+                Err(Error::Decrypt)
+            );
+            assert_ne!(buffer, PLAINTEXT, "plaintext released on failed decryption");
+        }
+
+        for tag_size in [0, 6, 12, 17] {
+            let mut buffer = PLAINTEXT.to_owned();
+            let mut tag = [0u8; 17];
+            assert_eq!(
+                aes128ccm_encrypt_with_tag_size(
+                    KEY128,
+                    NONCE,
+                    &[],
+                    &mut buffer,
+                    &mut tag[..tag_size]
+                ),
+                Err(Error::InvalidTagSize)
+            );
+            assert_eq!(
+                aes128ccm_decrypt_with_tag_size(KEY128, NONCE, &[], &mut buffer, &tag[..tag_size]),
+                Err(Error::InvalidTagSize)
+            );
+        }
+    }
 }
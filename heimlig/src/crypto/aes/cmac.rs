@@ -260,4 +260,53 @@ mod test {
         aes256_cmac_verify,
         KEY256_SIZE
     );
+
+    const RFC4493_MESSAGE: &str = concat!(
+        "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51",
+        "30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710"
+    );
+
+    // RFC 4493, section 4: empty, block-aligned and partial final block messages
+    #[test]
+    fn test_aes128_cmac_rfc4493() {
+        let key =
+            hex::decode("2b7e151628aed2a6abf7158809cf4f3c").expect("Failed to decode hex string");
+        let message = hex::decode(RFC4493_MESSAGE).expect("Failed to decode hex string");
+        for (size, expected_tag) in [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ] {
+            let expected_tag = hex::decode(expected_tag).expect("Failed to decode hex string");
+            let mut tag = [0u8; CMAC_TAG_SIZE];
+            aes128_cmac_calculate(&key, &message[..size], &mut tag)
+                .expect("failed to calculate CMAC tag");
+            assert_eq!(tag, expected_tag.as_slice());
+            assert!(aes128_cmac_verify(&key, &message[..size], &tag)
+                .expect("failed to verify CMAC tag"));
+        }
+    }
+
+    // NIST SP 800-38B, appendix D.3
+    #[test]
+    fn test_aes256_cmac_sp800_38b() {
+        let key = hex::decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4")
+            .expect("Failed to decode hex string");
+        let message = hex::decode(RFC4493_MESSAGE).expect("Failed to decode hex string");
+        for (size, expected_tag) in [
+            (0, "028962f61b7bf89efc6b551f4667d983"),
+            (16, "28a7023f452e8f82bd4bf28d8c37c35c"),
+            (40, "aaf3d8f1de5640c232f5b169b9c911e6"),
+            (64, "e1992190549f6ed5696a2c056c315410"),
+        ] {
+            let expected_tag = hex::decode(expected_tag).expect("Failed to decode hex string");
+            let mut tag = [0u8; CMAC_TAG_SIZE];
+            aes256_cmac_calculate(&key, &message[..size], &mut tag)
+                .expect("failed to calculate CMAC tag");
+            assert_eq!(tag, expected_tag.as_slice());
+            assert!(aes256_cmac_verify(&key, &message[..size], &tag)
+                .expect("failed to verify CMAC tag"));
+        }
+    }
 }
@@ -0,0 +1,183 @@
+use super::BLOCK_SIZE;
+use crate::crypto::{check_sizes, Error};
+use aes::{
+    cipher::{
+        consts::U16, BlockCipher, BlockEncrypt, BlockSizeUser, KeyInit, KeyIvInit, StreamCipher,
+    },
+    Aes128, Aes192, Aes256,
+};
+use ctr::Ctr32BE;
+
+/// Size of the nonce in bytes. The nonce fills the counter block except for the counter itself.
+pub(crate) const NONCE_SIZE: usize = BLOCK_SIZE - core::mem::size_of::<u32>();
+
+/// AES-CTR keystream application: generic over an underlying AES implementation.
+fn aes_ctr_apply<C>(
+    key: &[u8],
+    nonce: &[u8],
+    counter_start: u32,
+    data: &mut [u8],
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    check_sizes(key, nonce, C::key_size(), NONCE_SIZE)?;
+    let mut counter_block = [0u8; BLOCK_SIZE];
+    counter_block[..NONCE_SIZE].copy_from_slice(nonce);
+    counter_block[NONCE_SIZE..].copy_from_slice(&counter_start.to_be_bytes());
+    Ctr32BE::<C>::new_from_slices(key, &counter_block)
+        .map_err(|_| Error::InvalidSymmetricKeySize)?
+        .try_apply_keystream(data)
+        .map_err(|_| Error::InvalidBufferSize)
+}
+
+macro_rules! define_aes_ctr_impl {
+    (
+        $apply:ident,
+        $core:tt,
+        $key_size:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!($doc, " encryption and decryption in place.")]
+        ///
+        /// The 16-byte counter block is the nonce followed by a 32-bit big-endian block counter.
+        /// The counter is incremented for every block of `data` and wraps around to zero after
+        /// `u32::MAX` without carrying into the nonce. As encryption and decryption are the same
+        /// operation, applying the keystream twice restores the original data. `data` does not
+        /// have to be a multiple of the block size.
+        ///
+        /// # Arguments
+        ///
+        /// * `key`: A slice containing key bytes.
+        #[doc = concat!("The key slice has to be `", stringify!($key_size), "` bytes long.")]
+        /// * `nonce`: A slice containing the nonce bytes.
+        ///   The nonce slice has to be `CTR_NONCE_SIZE` bytes long.
+        /// * `counter_start`: Counter value of the first block of `data`. Together with the block
+        ///   index this allows seeking to any block of the stream.
+        /// * `data`: A mutable slice the keystream is XORed with.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidSymmetricKeySize`:
+        #[doc = concat!("The length of the `key` is not `", stringify!($key_size), "` bytes.")]
+        /// * `InvalidIvSize`: The length of the `nonce` is not `CTR_NONCE_SIZE` bytes.
+        /// * `InvalidBufferSize`: `data` is longer than the keystream of a full counter cycle.
+        pub fn $apply(
+            key: &[u8],
+            nonce: &[u8],
+            counter_start: u32,
+            data: &mut [u8],
+        ) -> Result<(), Error> {
+            aes_ctr_apply::<$core>(key, nonce, counter_start, data)
+        }
+    };
+}
+
+define_aes_ctr_impl!(aes128ctr_apply, Aes128, KEY128_SIZE, "AES128-CTR");
+define_aes_ctr_impl!(aes192ctr_apply, Aes192, KEY192_SIZE, "AES192-CTR");
+define_aes_ctr_impl!(aes256ctr_apply, Aes256, KEY256_SIZE, "AES256-CTR");
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::crypto::aes::{test::*, CTR_NONCE_SIZE};
+
+    // NIST SP 800-38A, F.5: initial counter block f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff
+    const NIST_NONCE: &str = "f0f1f2f3f4f5f6f7f8f9fafb";
+    const NIST_COUNTER_START: u32 = 0xfcfdfeff;
+    const NIST_PLAINTEXT: &str = concat!(
+        "6bc1bee22e409f96e93d7e117393172a",
+        "ae2d8a571e03ac9c9eb76fac45af8e51",
+        "30c81c46a35ce411e5fbc1191a0a52ef",
+        "f69f2445df4f9b17ad2b417be66c3710"
+    );
+
+    macro_rules! define_aes_ctr_nist_test {
+        (
+            $test_name:ident,
+            $apply:ident,
+            $key:expr,
+            $ciphertext:expr
+        ) => {
+            #[test]
+            fn $test_name() {
+                let key = hex::decode($key).expect("failed to decode hex string");
+                let nonce = hex::decode(NIST_NONCE).expect("failed to decode hex string");
+                let plaintext = hex::decode(NIST_PLAINTEXT).expect("failed to decode hex string");
+                let ciphertext = hex::decode($ciphertext).expect("failed to decode hex string");
+
+                let mut buffer = plaintext.clone();
+                $apply(&key, &nonce, NIST_COUNTER_START, &mut buffer).expect("failed to encrypt");
+                assert_eq!(buffer, ciphertext);
+                $apply(&key, &nonce, NIST_COUNTER_START, &mut buffer).expect("failed to decrypt");
+                assert_eq!(buffer, plaintext);
+            }
+        };
+    }
+
+    define_aes_ctr_nist_test!(
+        test_aes128ctr_nist,
+        aes128ctr_apply,
+        "2b7e151628aed2a6abf7158809cf4f3c",
+        concat!(
+            "874d6191b620e3261bef6864990db6ce",
+            "9806f66b7970fdff8617187bb9fffdff",
+            "5ae4df3edbd5d35e5b4f09020db03eab",
+            "1e031dda2fbe03d1792170a0f3009cee"
+        )
+    );
+    define_aes_ctr_nist_test!(
+        test_aes192ctr_nist,
+        aes192ctr_apply,
+        "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+        concat!(
+            "1abc932417521ca24f2b0459fe7e6e0b",
+            "090339ec0aa6faefd5ccc2c6f4ce8e94",
+            "1e36b26bd1ebc670d1bd1d665620abf7",
+            "4f78a7f6d29809585a97daec58c6b050"
+        )
+    );
+    define_aes_ctr_nist_test!(
+        test_aes256ctr_nist,
+        aes256ctr_apply,
+        "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+        concat!(
+            "601ec313775789a5b7a7f504bbf3d228",
+            "f443e3ca4d62b59aca84e990cacaf5c5",
+            "2b0930daa23de94ce87017ba2d84988d",
+            "dfc9c58db67aada613c2dd08457941a6"
+        )
+    );
+
+    #[test]
+    fn test_aes_ctr_seek_and_overflow() {
+        let nonce = [7u8; CTR_NONCE_SIZE];
+        let mut stream = [0u8; 3 * BLOCK_SIZE + 5];
+        aes128ctr_apply(KEY128, &nonce, u32::MAX - 1, &mut stream).expect("failed to encrypt");
+
+        // Starting at a later counter value yields the same keystream from a block boundary
+        let mut tail = [0u8; 2 * BLOCK_SIZE];
+        aes128ctr_apply(KEY128, &nonce, u32::MAX, &mut tail).expect("failed to encrypt");
+        assert_eq!(tail, stream[BLOCK_SIZE..3 * BLOCK_SIZE]);
+
+        // The counter wraps around to zero without touching the nonce
+        let mut wrapped = [0u8; BLOCK_SIZE + 5];
+        aes128ctr_apply(KEY128, &nonce, 0, &mut wrapped).expect("failed to encrypt");
+        assert_eq!(wrapped, stream[2 * BLOCK_SIZE..]);
+    }
+
+    #[test]
+    fn test_aes_ctr_errors() {
+        let mut buffer = *b"Hello, World!";
+        let nonce = [0u8; CTR_NONCE_SIZE];
+        assert_eq!(
+            aes128ctr_apply(KEY256, &nonce, 0, &mut buffer),
+            Err(Error::InvalidSymmetricKeySize)
+        );
+        assert_eq!(
+            aes256ctr_apply(KEY256, &nonce[1..], 0, &mut buffer),
+            Err(Error::InvalidIvSize)
+        );
+    }
+}
@@ -1,9 +1,21 @@
-use crate::crypto::{check_sizes_with_tag, Error};
-use aes::{cipher::typenum::Same, cipher::Unsigned};
+use super::BLOCK_SIZE;
+use crate::crypto::{check_sizes, check_sizes_with_tag, update_padded_segments, Error};
+use aes::{
+    cipher::{
+        typenum::Same, BlockCipher, BlockEncrypt, BlockSizeUser, InnerIvInit, StreamCipher,
+        Unsigned,
+    },
+    Aes128, Aes256,
+};
 use aes_gcm::{
     aead::consts::{U12, U16},
     AeadInPlace, Aes128Gcm, Aes256Gcm, KeyInit,
 };
+use ctr::{Ctr32BE, CtrCore};
+use elliptic_curve::subtle::ConstantTimeEq;
+use ghash::{universal_hash::UniversalHash, GHash};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 use zeroize::Zeroize;
 
 pub type SupportedIvSize = U12;
@@ -64,11 +76,113 @@ where
         .map_err(|_| Error::Decrypt)
 }
 
+/// AES-GCM encryption with the associated data given as segments that are authenticated as if
+/// they were concatenated.
+fn encrypt_in_place_detached_aad_segments<C, T>(
+    key: &[u8],
+    iv: &[u8],
+    aad_segments: &[T],
+    buffer: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+    T: AsRef<[u8]>,
+{
+    let mut stream = GcmStream::<C>::new(key, iv, aad_segments)?;
+    if tag.len() != SupportedTagSize::USIZE {
+        return Err(Error::InvalidTagSize);
+    }
+    stream.check_size(buffer, buffer)?;
+    stream.ctr.apply_keystream(buffer);
+    stream.authenticate(buffer);
+    let mut computed_tag = stream.tag();
+    tag.copy_from_slice(&computed_tag);
+    computed_tag.zeroize();
+    Ok(())
+}
+
+/// AES-GCM decryption with the associated data given as segments that are authenticated as if
+/// they were concatenated. `buffer` is only decrypted if the tag matches.
+fn decrypt_in_place_detached_aad_segments<C, T>(
+    key: &[u8],
+    iv: &[u8],
+    aad_segments: &[T],
+    buffer: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+    T: AsRef<[u8]>,
+{
+    let mut stream = GcmStream::<C>::new(key, iv, aad_segments)?;
+    if tag.len() != SupportedTagSize::USIZE {
+        return Err(Error::InvalidTagSize);
+    }
+    stream.check_size(buffer, buffer)?;
+    stream.authenticate(buffer);
+    let mut computed_tag = stream.tag();
+    let matches: bool = computed_tag.ct_eq(tag).into();
+    computed_tag.zeroize();
+    if !matches {
+        return Err(Error::Decrypt);
+    }
+    stream.ctr.apply_keystream(buffer);
+    Ok(())
+}
+
+/// Size of the big-endian length prefix of length-prefixed associated data.
+pub const AAD_LENGTH_PREFIX_SIZE: usize = core::mem::size_of::<u32>();
+
+/// Build associated data that starts with the length of the encrypted payload.
+///
+/// The framed associated data is the payload length as a 32-bit big-endian integer followed by
+/// `aad`. It is written to `framed_aad` and the used part is returned.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidBufferSize`: `framed_aad` is too small or `payload_size` does not fit into the prefix.
+pub fn build_length_prefixed_aad<'a>(
+    payload_size: usize,
+    aad: &[u8],
+    framed_aad: &'a mut [u8],
+) -> Result<&'a [u8], Error> {
+    let prefix = u32::try_from(payload_size).map_err(|_| Error::InvalidBufferSize)?;
+    let framed_aad = framed_aad
+        .get_mut(..AAD_LENGTH_PREFIX_SIZE + aad.len())
+        .ok_or(Error::InvalidBufferSize)?;
+    framed_aad[..AAD_LENGTH_PREFIX_SIZE].copy_from_slice(&prefix.to_be_bytes());
+    framed_aad[AAD_LENGTH_PREFIX_SIZE..].copy_from_slice(aad);
+    Ok(framed_aad)
+}
+
+/// Check that length-prefixed associated data describes a payload of `payload_size` bytes.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `LengthMismatch`: The prefix is missing or does not match `payload_size`.
+pub fn check_length_prefixed_aad(framed_aad: &[u8], payload_size: usize) -> Result<(), Error> {
+    let prefix = framed_aad
+        .get(..AAD_LENGTH_PREFIX_SIZE)
+        .ok_or(Error::LengthMismatch)?;
+    let prefix = u32::from_be_bytes(prefix.try_into().expect("unexpected prefix size"));
+    if usize::try_from(prefix) != Ok(payload_size) {
+        return Err(Error::LengthMismatch);
+    }
+    Ok(())
+}
+
 macro_rules! define_aes_gcm_impl {
     (
         $encryptor:ident,
         $decryptor:ident,
-        $core:tt
+        $decryptor_length_prefixed:ident,
+        $encryptor_aad_segments:ident,
+        $decryptor_aad_segments:ident,
+        $core:tt,
+        $cipher:tt
     ) => {
         pub fn $encryptor(
             key: &[u8],
@@ -89,25 +203,331 @@ macro_rules! define_aes_gcm_impl {
         ) -> Result<(), Error> {
             decrypt_in_place_detached::<$core>(key, iv, aad, buffer, tag)
         }
+
+        /// Decrypt with length-prefixed associated data as built by
+        /// [build_length_prefixed_aad]. The prefix is checked against the length of `buffer`
+        /// before decrypting and a mismatch is reported as `LengthMismatch`.
+        pub fn $decryptor_length_prefixed(
+            key: &[u8],
+            iv: &[u8],
+            framed_aad: &[u8],
+            buffer: &mut [u8],
+            tag: &[u8],
+        ) -> Result<(), Error> {
+            check_length_prefixed_aad(framed_aad, buffer.len())?;
+            decrypt_in_place_detached::<$core>(key, iv, framed_aad, buffer, tag)
+        }
+
+        /// Encrypt with the associated data given as segments that are authenticated in order,
+        /// without concatenating them first. The result equals the encryption with the
+        /// concatenated associated data.
+        pub fn $encryptor_aad_segments<T: AsRef<[u8]>>(
+            key: &[u8],
+            iv: &[u8],
+            aad_segments: &[T],
+            buffer: &mut [u8],
+            tag: &mut [u8],
+        ) -> Result<(), Error> {
+            encrypt_in_place_detached_aad_segments::<$cipher, T>(key, iv, aad_segments, buffer, tag)
+        }
+
+        /// Decrypt with the associated data given as segments that are authenticated in order,
+        /// without concatenating them first.
+        pub fn $decryptor_aad_segments<T: AsRef<[u8]>>(
+            key: &[u8],
+            iv: &[u8],
+            aad_segments: &[T],
+            buffer: &mut [u8],
+            tag: &[u8],
+        ) -> Result<(), Error> {
+            decrypt_in_place_detached_aad_segments::<$cipher, T>(key, iv, aad_segments, buffer, tag)
+        }
     };
 }
 
 define_aes_gcm_impl!(
     aes128gcm_encrypt_in_place_detached,
     aes128gcm_decrypt_in_place_detached,
-    Aes128Gcm
+    aes128gcm_decrypt_in_place_detached_length_prefixed,
+    aes128gcm_encrypt_in_place_detached_aad_segments,
+    aes128gcm_decrypt_in_place_detached_aad_segments,
+    Aes128Gcm,
+    Aes128
 );
 define_aes_gcm_impl!(
     aes256gcm_encrypt_in_place_detached,
     aes256gcm_decrypt_in_place_detached,
-    Aes256Gcm
+    aes256gcm_decrypt_in_place_detached_length_prefixed,
+    aes256gcm_encrypt_in_place_detached_aad_segments,
+    aes256gcm_decrypt_in_place_detached_aad_segments,
+    Aes256Gcm,
+    Aes256
 );
 
+/// Derive a deterministic AES-GCM IV from the plaintext and a per-key salt.
+///
+/// The IV is the HMAC-SHA-256 of the plaintext keyed with the salt, truncated to
+/// `SupportedIvSize` bytes. Encrypting the same plaintext under the same key and salt therefore
+/// yields the same ciphertext, which allows deduplication of encrypted content.
+///
+/// Tradeoffs compared to random or counter-based IVs:
+/// * Equal plaintexts produce equal ciphertexts, so an observer learns when content repeats.
+/// * Distinct plaintexts only get distinct IVs with high probability. The IV has 96 bits, so IV
+///   collisions under one key become likely after about 2^48 distinct plaintexts. An IV collision
+///   breaks the confidentiality of both messages and the integrity of the key.
+/// * The associated data does not influence the IV.
+/// * The whole plaintext has to be known before encryption starts.
+///
+/// Use a different salt for every key, ideally a secret one, to prevent precomputing IVs for
+/// guessed plaintexts and to avoid correlating ciphertexts across keys. AES-GCM-SIV should be
+/// preferred where it is available.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidIvSize`: The `iv` slice is not `SupportedIvSize` bytes long.
+pub fn gcm_derive_deterministic_iv(
+    salt: &[u8],
+    plaintext: &[u8],
+    iv: &mut [u8],
+) -> Result<(), Error> {
+    if iv.len() != SupportedIvSize::USIZE {
+        return Err(Error::InvalidIvSize);
+    }
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(salt).map_err(|_| Error::InvalidIvSize)?;
+    mac.update(plaintext);
+    let mut digest = mac.finalize().into_bytes();
+    iv.copy_from_slice(&digest[..SupportedIvSize::USIZE]);
+    digest.zeroize();
+    Ok(())
+}
+
+/// Maximum number of plaintext bytes processed by a single AES-GCM stream (2^39 - 256 bits).
+pub const GCM_STREAM_MAX_SIZE: u64 = (1 << 36) - 32;
+
+/// State shared by [GcmEncryptStream] and [GcmDecryptStream].
+struct GcmStream<C>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    ctr: Ctr32BE<C>,
+    ghash: GHash,
+    /// Encrypted initial counter block that masks the GHASH output.
+    tag_mask: [u8; BLOCK_SIZE],
+    /// Ciphertext bytes not yet processed by GHASH.
+    partial_block: [u8; BLOCK_SIZE],
+    partial_block_size: usize,
+    aad_size: u64,
+    data_size: u64,
+}
+
+impl<C> GcmStream<C>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    fn new<T: AsRef<[u8]>>(key: &[u8], iv: &[u8], aad_segments: &[T]) -> Result<Self, Error> {
+        check_sizes(key, iv, C::key_size(), SupportedIvSize::USIZE)?;
+        let cipher = C::new(key.into());
+        let mut hash_key = [0u8; BLOCK_SIZE];
+        cipher.encrypt_block((&mut hash_key).into());
+        let mut counter_block = [0u8; BLOCK_SIZE];
+        counter_block[..SupportedIvSize::USIZE].copy_from_slice(iv);
+        counter_block[BLOCK_SIZE - 1] = 1;
+        let mut tag_mask = counter_block;
+        cipher.encrypt_block((&mut tag_mask).into());
+        // Data is encrypted starting with the second counter block
+        counter_block[BLOCK_SIZE - 1] = 2;
+
+        let mut ghash = GHash::new(&hash_key.into());
+        hash_key.zeroize();
+        let aad_size = update_padded_segments(&mut ghash, aad_segments);
+        Ok(Self {
+            ctr: Ctr32BE::<C>::from_core(CtrCore::inner_iv_init(cipher, &counter_block.into())),
+            ghash,
+            tag_mask,
+            partial_block: [0u8; BLOCK_SIZE],
+            partial_block_size: 0,
+            aad_size,
+            data_size: 0,
+        })
+    }
+
+    fn check_size(&mut self, input: &[u8], output: &[u8]) -> Result<(), Error> {
+        if input.len() != output.len() {
+            return Err(Error::InvalidBufferSize);
+        }
+        let data_size = self
+            .data_size
+            .checked_add(input.len() as u64)
+            .filter(|size| *size <= GCM_STREAM_MAX_SIZE)
+            .ok_or(Error::InvalidBufferSize)?;
+        self.data_size = data_size;
+        Ok(())
+    }
+
+    /// Feed ciphertext to GHASH, buffering incomplete blocks.
+    fn authenticate(&mut self, mut ciphertext: &[u8]) {
+        if self.partial_block_size > 0 {
+            let size = ciphertext.len().min(BLOCK_SIZE - self.partial_block_size);
+            self.partial_block[self.partial_block_size..self.partial_block_size + size]
+                .copy_from_slice(&ciphertext[..size]);
+            self.partial_block_size += size;
+            ciphertext = &ciphertext[size..];
+            if self.partial_block_size < BLOCK_SIZE {
+                return;
+            }
+            self.ghash.update(&[self.partial_block.into()]);
+            self.partial_block_size = 0;
+        }
+        let full_blocks_size = ciphertext.len() - ciphertext.len() % BLOCK_SIZE;
+        self.ghash.update_padded(&ciphertext[..full_blocks_size]);
+        let rest = &ciphertext[full_blocks_size..];
+        self.partial_block[..rest.len()].copy_from_slice(rest);
+        self.partial_block_size = rest.len();
+    }
+
+    fn tag(&mut self) -> [u8; BLOCK_SIZE] {
+        self.ghash
+            .update_padded(&self.partial_block[..self.partial_block_size]);
+        let mut lengths = [0u8; BLOCK_SIZE];
+        lengths[..8].copy_from_slice(&(self.aad_size * 8).to_be_bytes());
+        lengths[8..].copy_from_slice(&(self.data_size * 8).to_be_bytes());
+        self.ghash.update(&[lengths.into()]);
+        let mut tag: [u8; BLOCK_SIZE] = self.ghash.clone().finalize().into();
+        tag.iter_mut()
+            .zip(self.tag_mask.iter())
+            .for_each(|(t, m)| *t ^= m);
+        tag
+    }
+}
+
+impl<C> Drop for GcmStream<C>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    fn drop(&mut self) {
+        self.tag_mask.zeroize();
+        self.partial_block.zeroize();
+    }
+}
+
+/// Incremental AES-GCM encryption for payloads that do not fit into a single buffer.
+///
+/// Produces the same ciphertext and tag as the one-shot encryption of the concatenated chunks.
+pub struct GcmEncryptStream<C>(GcmStream<C>)
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit;
+
+impl<C> GcmEncryptStream<C>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    /// Start encrypting a payload.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidSymmetricKeySize`: `key` has an invalid size.
+    /// * `InvalidIvSize`: `iv` is not `SupportedIvSize` bytes long.
+    pub fn new(key: &[u8], iv: &[u8], aad: &[u8]) -> Result<Self, Error> {
+        Ok(Self(GcmStream::new(key, iv, &[aad])?))
+    }
+
+    /// Encrypt the next `plaintext` chunk into `ciphertext`. Chunks can have any size.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidBufferSize`: `ciphertext` is not as long as `plaintext` or the stream exceeds
+    ///   `GCM_STREAM_MAX_SIZE` bytes.
+    pub fn update(&mut self, plaintext: &[u8], ciphertext: &mut [u8]) -> Result<(), Error> {
+        self.0.check_size(plaintext, ciphertext)?;
+        ciphertext.copy_from_slice(plaintext);
+        self.0.ctr.apply_keystream(ciphertext);
+        self.0.authenticate(ciphertext);
+        Ok(())
+    }
+
+    /// Finish encryption and return the authentication tag.
+    pub fn finalize(mut self) -> [u8; BLOCK_SIZE] {
+        self.0.tag()
+    }
+}
+
+/// Incremental AES-GCM decryption for payloads that do not fit into a single buffer.
+///
+/// The tag is only verified in [GcmDecryptStream::finalize]. Plaintext chunks returned before are
+/// unauthenticated and must be discarded if verification fails.
+pub struct GcmDecryptStream<C>(GcmStream<C>)
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit;
+
+impl<C> GcmDecryptStream<C>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    /// Start decrypting a payload.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidSymmetricKeySize`: `key` has an invalid size.
+    /// * `InvalidIvSize`: `iv` is not `SupportedIvSize` bytes long.
+    pub fn new(key: &[u8], iv: &[u8], aad: &[u8]) -> Result<Self, Error> {
+        Ok(Self(GcmStream::new(key, iv, &[aad])?))
+    }
+
+    /// Decrypt the next `ciphertext` chunk into `plaintext`. Chunks can have any size.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidBufferSize`: `plaintext` is not as long as `ciphertext` or the stream exceeds
+    ///   `GCM_STREAM_MAX_SIZE` bytes.
+    pub fn update(&mut self, ciphertext: &[u8], plaintext: &mut [u8]) -> Result<(), Error> {
+        self.0.check_size(ciphertext, plaintext)?;
+        self.0.authenticate(ciphertext);
+        plaintext.copy_from_slice(ciphertext);
+        self.0.ctr.apply_keystream(plaintext);
+        Ok(())
+    }
+
+    /// Finish decryption and verify the authentication tag.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidTagSize`: `tag` is not `SupportedTagSize` bytes long.
+    /// * `Decrypt`: The tag does not match the received data.
+    pub fn finalize(mut self, tag: &[u8]) -> Result<(), Error> {
+        if tag.len() != SupportedTagSize::USIZE {
+            return Err(Error::InvalidTagSize);
+        }
+        let mut computed_tag = self.0.tag();
+        let matches: bool = computed_tag.ct_eq(tag).into();
+        computed_tag.zeroize();
+        if matches {
+            Ok(())
+        } else {
+            Err(Error::Decrypt)
+        }
+    }
+}
+
+/// Incremental AES-128-GCM encryption.
+pub type Aes128GcmEncryptStream = GcmEncryptStream<Aes128>;
+/// Incremental AES-128-GCM decryption.
+pub type Aes128GcmDecryptStream = GcmDecryptStream<Aes128>;
+/// Incremental AES-256-GCM encryption.
+pub type Aes256GcmEncryptStream = GcmEncryptStream<Aes256>;
+/// Incremental AES-256-GCM decryption.
+pub type Aes256GcmDecryptStream = GcmDecryptStream<Aes256>;
+
 #[cfg(test)]
 mod test {
     extern crate alloc;
     use super::*;
-    use crate::crypto::aes::{test::*, GCM_TAG_SIZE};
+    use crate::crypto::aes::{test::*, GCM_IV_SIZE, GCM_TAG_SIZE};
     use alloc::borrow::ToOwned;
     use heapless::Vec;
 
@@ -345,4 +765,265 @@ mod test {
         PLAINTEXT,
         [0, 1, 8, 16, 24, 256]
     );
+
+    #[test]
+    fn test_aes128gcm_deterministic_iv() {
+        const SALT: &[u8] = b"dedup salt";
+
+        fn encrypt(key: &[u8], plaintext: &[u8]) -> (Vec<u8, 32>, [u8; GCM_TAG_SIZE]) {
+            let mut iv = [0u8; SupportedIvSize::USIZE];
+            gcm_derive_deterministic_iv(SALT, plaintext, &mut iv).expect("IV derivation error");
+            let mut buffer: Vec<u8, 32> = Vec::from_slice(plaintext).expect("buffer too small");
+            let mut tag = [0u8; GCM_TAG_SIZE];
+            aes128gcm_encrypt_in_place_detached(key, &iv, &[], &mut buffer, &mut tag)
+                .expect("encryption error");
+            (buffer, tag)
+        }
+
+        let other_key = b"Close sesame....";
+        assert_eq!(encrypt(KEY128, PLAINTEXT), encrypt(KEY128, PLAINTEXT));
+        assert_ne!(encrypt(KEY128, PLAINTEXT), encrypt(other_key, PLAINTEXT));
+        assert_ne!(
+            encrypt(KEY128, PLAINTEXT),
+            encrypt(KEY128, PLAINTEXT_PADDED)
+        );
+
+        let mut short_iv = [0u8; SupportedIvSize::USIZE - 1];
+        assert_eq!(
+            gcm_derive_deterministic_iv(SALT, PLAINTEXT, &mut short_iv),
+            Err(Error::InvalidIvSize)
+        );
+    }
+
+    #[test]
+    fn test_aes128gcm_length_prefixed_aad() {
+        let mut framed_aad = [0u8; AAD_LENGTH_PREFIX_SIZE + AAD.len()];
+        let framed_aad = build_length_prefixed_aad(PLAINTEXT.len(), AAD, &mut framed_aad)
+            .expect("failed to build associated data");
+        assert_eq!(
+            framed_aad[..AAD_LENGTH_PREFIX_SIZE],
+            (PLAINTEXT.len() as u32).to_be_bytes()
+        );
+        let mut buffer: Vec<u8, 32> = Vec::from_slice(PLAINTEXT).expect("buffer too small");
+        let mut tag = [0u8; GCM_TAG_SIZE];
+        aes128gcm_encrypt_in_place_detached(KEY128, GCM_IV, framed_aad, &mut buffer, &mut tag)
+            .expect("encryption error");
+
+        let mut corrupted_aad: Vec<u8, 64> = Vec::from_slice(framed_aad).expect("buffer too small");
+        corrupted_aad[AAD_LENGTH_PREFIX_SIZE - 1] ^= 1;
+        let ciphertext = buffer.clone();
+        assert_eq!(
+            aes128gcm_decrypt_in_place_detached_length_prefixed(
+                KEY128,
+                GCM_IV,
+                &corrupted_aad,
+                &mut buffer,
+                &tag
+            ),
+            Err(Error::LengthMismatch)
+        );
+        assert_eq!(buffer, ciphertext);
+        assert_eq!(
+            check_length_prefixed_aad(&framed_aad[..AAD_LENGTH_PREFIX_SIZE - 1], 0),
+            Err(Error::LengthMismatch)
+        );
+
+        aes128gcm_decrypt_in_place_detached_length_prefixed(
+            KEY128,
+            GCM_IV,
+            framed_aad,
+            &mut buffer,
+            &tag,
+        )
+        .expect("decryption error");
+        assert_eq!(buffer, PLAINTEXT);
+
+        assert_eq!(
+            build_length_prefixed_aad(PLAINTEXT.len(), AAD, &mut [0u8; 8]),
+            Err(Error::InvalidBufferSize)
+        );
+    }
+
+    #[test]
+    fn test_aes_gcm_aad_segments() {
+        // Segments cross GHASH block boundaries and include an empty one
+        let aad_segments = [&AAD[..0], &AAD[..5], &AAD[5..25], &AAD[25..36], &AAD[36..]];
+
+        let mut expected_buffer: Vec<u8, 32> =
+            Vec::from_slice(PLAINTEXT).expect("buffer too small");
+        let mut expected_tag = [0u8; GCM_TAG_SIZE];
+        aes128gcm_encrypt_in_place_detached(
+            KEY128,
+            GCM_IV,
+            AAD,
+            &mut expected_buffer,
+            &mut expected_tag,
+        )
+        .expect("encryption error");
+        let mut buffer: Vec<u8, 32> = Vec::from_slice(PLAINTEXT).expect("buffer too small");
+        let mut tag = [0u8; GCM_TAG_SIZE];
+        aes128gcm_encrypt_in_place_detached_aad_segments(
+            KEY128,
+            GCM_IV,
+            &aad_segments,
+            &mut buffer,
+            &mut tag,
+        )
+        .expect("encryption error");
+        assert_eq!(buffer, expected_buffer, "ciphertext mismatch");
+        assert_eq!(tag, expected_tag, "tag mismatch");
+
+        let ciphertext = buffer.clone();
+        assert_eq!(
+            aes128gcm_decrypt_in_place_detached_aad_segments(
+                KEY128,
+                GCM_IV,
+                &aad_segments[2..],
+                &mut buffer,
+                &tag
+            ),
+            Err(Error::Decrypt)
+        );
+        assert_eq!(buffer, ciphertext);
+        aes128gcm_decrypt_in_place_detached_aad_segments(
+            KEY128,
+            GCM_IV,
+            &aad_segments,
+            &mut buffer,
+            &tag,
+        )
+        .expect("decryption error");
+        assert_eq!(buffer, PLAINTEXT);
+
+        let mut expected_buffer: Vec<u8, 32> =
+            Vec::from_slice(PLAINTEXT).expect("buffer too small");
+        aes256gcm_encrypt_in_place_detached(
+            KEY256,
+            GCM_IV,
+            AAD,
+            &mut expected_buffer,
+            &mut expected_tag,
+        )
+        .expect("encryption error");
+        aes256gcm_encrypt_in_place_detached_aad_segments(
+            KEY256,
+            GCM_IV,
+            &aad_segments,
+            &mut buffer,
+            &mut tag,
+        )
+        .expect("encryption error");
+        assert_eq!(buffer, expected_buffer, "ciphertext mismatch");
+        assert_eq!(tag, expected_tag, "tag mismatch");
+        aes256gcm_decrypt_in_place_detached_aad_segments(
+            KEY256,
+            GCM_IV,
+            &aad_segments,
+            &mut buffer,
+            &tag,
+        )
+        .expect("decryption error");
+        assert_eq!(buffer, PLAINTEXT);
+
+        assert_eq!(
+            aes128gcm_encrypt_in_place_detached_aad_segments(
+                KEY128,
+                GCM_IV,
+                &aad_segments,
+                &mut buffer,
+                &mut tag[..GCM_TAG_SIZE - 1]
+            ),
+            Err(Error::InvalidTagSize)
+        );
+        assert_eq!(
+            aes128gcm_decrypt_in_place_detached_aad_segments(
+                KEY256,
+                GCM_IV,
+                &aad_segments,
+                &mut buffer,
+                &tag
+            ),
+            Err(Error::InvalidSymmetricKeySize)
+        );
+    }
+
+    #[test]
+    fn test_aes256gcm_stream() {
+        const SIZE: usize = 1000;
+        let key = [0x42u8; 32];
+        let iv = [0x24u8; GCM_IV_SIZE];
+        let aad = b"flash region 3";
+        let mut plaintext = [0u8; SIZE];
+        for (i, byte) in plaintext.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut expected_ciphertext = plaintext;
+        let mut expected_tag = [0u8; GCM_TAG_SIZE];
+        aes256gcm_encrypt_in_place_detached(
+            &key,
+            &iv,
+            aad,
+            &mut expected_ciphertext,
+            &mut expected_tag,
+        )
+        .expect("failed to encrypt");
+
+        for chunk_size in [1, 7, 16, 33, SIZE] {
+            let mut ciphertext = [0u8; SIZE];
+            let mut stream =
+                Aes256GcmEncryptStream::new(&key, &iv, aad).expect("failed to start encryption");
+            for (input, output) in plaintext
+                .chunks(chunk_size)
+                .zip(ciphertext.chunks_mut(chunk_size))
+            {
+                stream.update(input, output).expect("failed to encrypt");
+            }
+            assert_eq!(stream.finalize(), expected_tag);
+            assert_eq!(ciphertext, expected_ciphertext);
+
+            let mut decrypted = [0u8; SIZE];
+            let mut stream =
+                Aes256GcmDecryptStream::new(&key, &iv, aad).expect("failed to start decryption");
+            for (input, output) in ciphertext
+                .chunks(chunk_size)
+                .zip(decrypted.chunks_mut(chunk_size))
+            {
+                stream.update(input, output).expect("failed to decrypt");
+            }
+            stream.finalize(&expected_tag).expect("failed to verify");
+            assert_eq!(decrypted, plaintext);
+        }
+    }
+
+    #[test]
+    fn test_aes128gcm_stream_errors() {
+        let key = [0x42u8; 16];
+        let iv = [0x24u8; GCM_IV_SIZE];
+        let mut ciphertext = [0u8; 64];
+        let mut tag = [0u8; GCM_TAG_SIZE];
+        aes128gcm_encrypt_in_place_detached(&key, &iv, &[], &mut ciphertext, &mut tag)
+            .expect("failed to encrypt");
+        ciphertext[40] ^= 1;
+
+        let mut plaintext = [0u8; 64];
+        let mut stream =
+            Aes128GcmDecryptStream::new(&key, &iv, &[]).expect("failed to start decryption");
+        assert_eq!(
+            stream.update(&ciphertext, &mut plaintext[1..]),
+            Err(Error::InvalidBufferSize)
+        );
+        stream
+            .update(&ciphertext, &mut plaintext)
+            .expect("failed to decrypt");
+        assert_eq!(stream.finalize(&tag), Err(Error::Decrypt));
+
+        assert!(matches!(
+            Aes128GcmEncryptStream::new(&key[1..], &iv, &[]),
+            Err(Error::InvalidSymmetricKeySize)
+        ));
+        assert!(matches!(
+            Aes128GcmEncryptStream::new(&key, &iv[1..], &[]),
+            Err(Error::InvalidIvSize)
+        ));
+    }
 }
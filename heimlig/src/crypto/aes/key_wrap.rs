@@ -0,0 +1,243 @@
+use crate::crypto::Error;
+use aes::{
+    cipher::{
+        consts::U16, generic_array::GenericArray, BlockCipher, BlockDecrypt, BlockEncrypt,
+        BlockSizeUser, KeyInit,
+    },
+    Aes128, Aes192, Aes256,
+};
+use zeroize::Zeroize;
+
+/// Size of the semiblocks the key data is processed in.
+pub const SEMIBLOCK_SIZE: usize = 8;
+
+/// Default initial value of RFC 3394, section 2.2.3.1.
+const DEFAULT_IV: [u8; SEMIBLOCK_SIZE] = [0xa6; SEMIBLOCK_SIZE];
+
+/// Key data has to consist of at least two semiblocks.
+const MIN_KEY_DATA_SIZE: usize = 2 * SEMIBLOCK_SIZE;
+
+fn check_key_data_size(size: usize) -> Result<(), Error> {
+    if size < MIN_KEY_DATA_SIZE || !size.is_multiple_of(SEMIBLOCK_SIZE) {
+        return Err(Error::InvalidBufferSize);
+    }
+    Ok(())
+}
+
+/// AES key wrap: generic over an underlying AES implementation.
+fn key_wrap<C>(kek: &[u8], key_data: &[u8], wrapped: &mut [u8]) -> Result<(), Error>
+where
+    C: BlockCipher + BlockEncrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    let cipher = C::new_from_slice(kek).map_err(|_| Error::InvalidSymmetricKeySize)?;
+    check_key_data_size(key_data.len())?;
+    if wrapped.len() != key_data.len() + SEMIBLOCK_SIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+
+    let (a, r) = wrapped.split_at_mut(SEMIBLOCK_SIZE);
+    a.copy_from_slice(&DEFAULT_IV);
+    r.copy_from_slice(key_data);
+    let n = r.len() / SEMIBLOCK_SIZE;
+    let mut block = GenericArray::<u8, U16>::default();
+    for j in 0..6 {
+        for (i, r_i) in r.chunks_exact_mut(SEMIBLOCK_SIZE).enumerate() {
+            block[..SEMIBLOCK_SIZE].copy_from_slice(a);
+            block[SEMIBLOCK_SIZE..].copy_from_slice(r_i);
+            cipher.encrypt_block(&mut block);
+            let t = (n * j + i + 1) as u64;
+            for (a, (b, t)) in a.iter_mut().zip(block.iter().zip(t.to_be_bytes())) {
+                *a = b ^ t;
+            }
+            r_i.copy_from_slice(&block[SEMIBLOCK_SIZE..]);
+        }
+    }
+    block.zeroize();
+    Ok(())
+}
+
+/// AES key unwrap: generic over an underlying AES implementation.
+fn key_unwrap<C>(kek: &[u8], wrapped: &[u8], key_data: &mut [u8]) -> Result<(), Error>
+where
+    C: BlockCipher + BlockDecrypt + BlockSizeUser<BlockSize = U16> + KeyInit,
+{
+    let cipher = C::new_from_slice(kek).map_err(|_| Error::InvalidSymmetricKeySize)?;
+    if wrapped.len() < SEMIBLOCK_SIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+    check_key_data_size(wrapped.len() - SEMIBLOCK_SIZE)?;
+    if key_data.len() != wrapped.len() - SEMIBLOCK_SIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+
+    let mut a = [0u8; SEMIBLOCK_SIZE];
+    a.copy_from_slice(&wrapped[..SEMIBLOCK_SIZE]);
+    key_data.copy_from_slice(&wrapped[SEMIBLOCK_SIZE..]);
+    let n = key_data.len() / SEMIBLOCK_SIZE;
+    let mut block = GenericArray::<u8, U16>::default();
+    for j in (0..6).rev() {
+        for (i, r_i) in key_data.chunks_exact_mut(SEMIBLOCK_SIZE).enumerate().rev() {
+            let t = (n * j + i + 1) as u64;
+            for (b, (a, t)) in block.iter_mut().zip(a.iter().zip(t.to_be_bytes())) {
+                *b = a ^ t;
+            }
+            block[SEMIBLOCK_SIZE..].copy_from_slice(r_i);
+            cipher.decrypt_block(&mut block);
+            a.copy_from_slice(&block[..SEMIBLOCK_SIZE]);
+            r_i.copy_from_slice(&block[SEMIBLOCK_SIZE..]);
+        }
+    }
+    block.zeroize();
+    if a != DEFAULT_IV {
+        key_data.zeroize();
+        return Err(Error::IntegrityCheckFailed);
+    }
+    Ok(())
+}
+
+/// AES key wrap as specified in RFC 3394.
+///
+/// The key size of `kek` selects AES128, AES192 or AES256.
+///
+/// # Arguments
+///
+/// * `kek`: A slice containing the key encryption key bytes.
+///   The key slice has to be `KEY128_SIZE`, `KEY192_SIZE` or `KEY256_SIZE` bytes long.
+/// * `key_data`: A slice containing the key data to wrap.
+/// * `wrapped`: A mutable slice where the wrapped key data will be stored.
+///   The slice has to be `SEMIBLOCK_SIZE` bytes longer than `key_data`.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidSymmetricKeySize`: The length of the `kek` is not a valid AES key size.
+/// * `InvalidBufferSize`: `key_data` is not a multiple of `SEMIBLOCK_SIZE` bytes, shorter than
+///   two semiblocks, or `wrapped` has the wrong size.
+pub fn aes_key_wrap(kek: &[u8], key_data: &[u8], wrapped: &mut [u8]) -> Result<(), Error> {
+    match kek.len() {
+        super::KEY128_SIZE => key_wrap::<Aes128>(kek, key_data, wrapped),
+        super::KEY192_SIZE => key_wrap::<Aes192>(kek, key_data, wrapped),
+        super::KEY256_SIZE => key_wrap::<Aes256>(kek, key_data, wrapped),
+        _ => Err(Error::InvalidSymmetricKeySize),
+    }
+}
+
+/// AES key unwrap as specified in RFC 3394.
+///
+/// The key size of `kek` selects AES128, AES192 or AES256.
+///
+/// # Arguments
+///
+/// * `kek`: A slice containing the key encryption key bytes.
+///   The key slice has to be `KEY128_SIZE`, `KEY192_SIZE` or `KEY256_SIZE` bytes long.
+/// * `wrapped`: A slice containing the wrapped key data.
+/// * `key_data`: A mutable slice where the unwrapped key data will be stored.
+///   The slice has to be `SEMIBLOCK_SIZE` bytes shorter than `wrapped`.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidSymmetricKeySize`: The length of the `kek` is not a valid AES key size.
+/// * `InvalidBufferSize`: `wrapped` is not a multiple of `SEMIBLOCK_SIZE` bytes, shorter than
+///   three semiblocks, or `key_data` has the wrong size.
+/// * `IntegrityCheckFailed`: The integrity check value does not match. `key_data` is zeroized.
+pub fn aes_key_unwrap(kek: &[u8], wrapped: &[u8], key_data: &mut [u8]) -> Result<(), Error> {
+    match kek.len() {
+        super::KEY128_SIZE => key_unwrap::<Aes128>(kek, wrapped, key_data),
+        super::KEY192_SIZE => key_unwrap::<Aes192>(kek, wrapped, key_data),
+        super::KEY256_SIZE => key_unwrap::<Aes256>(kek, wrapped, key_data),
+        _ => Err(Error::InvalidSymmetricKeySize),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // RFC 3394, section 4
+    const KEK: [u8; 32] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d,
+        0x1e, 0x1f,
+    ];
+    const KEY_DATA: [u8; 32] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee,
+        0xff, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+        0x0e, 0x0f,
+    ];
+
+    fn wrap_unwrap(kek: &[u8], key_data: &[u8], expected: &str) {
+        let expected = hex::decode(expected).expect("Failed to decode hex string");
+        let mut wrapped = [0u8; 48];
+        let wrapped = &mut wrapped[..key_data.len() + SEMIBLOCK_SIZE];
+        aes_key_wrap(kek, key_data, wrapped).expect("failed to wrap key");
+        assert_eq!(wrapped, expected.as_slice());
+
+        let mut unwrapped = [0u8; 40];
+        let unwrapped = &mut unwrapped[..key_data.len()];
+        aes_key_unwrap(kek, wrapped, unwrapped).expect("failed to unwrap key");
+        assert_eq!(unwrapped, key_data);
+    }
+
+    #[test]
+    fn test_aes128_key_wrap_128_bit_key() {
+        wrap_unwrap(
+            &KEK[..16],
+            &KEY_DATA[..16],
+            "1fa68b0a8112b447aef34bd8fb5a7b829d3e862371d2cfe5",
+        );
+    }
+
+    #[test]
+    fn test_aes256_key_wrap_128_bit_key() {
+        wrap_unwrap(
+            &KEK,
+            &KEY_DATA[..16],
+            "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7",
+        );
+    }
+
+    #[test]
+    fn test_aes256_key_wrap_256_bit_key() {
+        wrap_unwrap(
+            &KEK,
+            &KEY_DATA,
+            concat!(
+                "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326",
+                "cbc7f0e71a99f43bfb988b9b7a02dd21"
+            ),
+        );
+    }
+
+    #[test]
+    fn test_aes_key_wrap_errors() {
+        let mut wrapped = [0u8; 48];
+        let mut key_data = [0u8; 40];
+        assert_eq!(
+            aes_key_wrap(&KEK[..15], &KEY_DATA[..16], &mut wrapped[..24]),
+            Err(Error::InvalidSymmetricKeySize)
+        );
+        for len in [0, 8, 15, 17, 20] {
+            assert_eq!(
+                aes_key_wrap(&KEK[..16], &KEY_DATA[..len], &mut wrapped[..len + 8]),
+                Err(Error::InvalidBufferSize)
+            );
+            assert_eq!(
+                aes_key_unwrap(&KEK[..16], &wrapped[..len + 8], &mut key_data[..len]),
+                Err(Error::InvalidBufferSize)
+            );
+        }
+        assert_eq!(
+            aes_key_wrap(&KEK[..16], &KEY_DATA[..16], &mut wrapped[..16]),
+            Err(Error::InvalidBufferSize)
+        );
+
+        aes_key_wrap(&KEK[..16], &KEY_DATA[..16], &mut wrapped[..24]).expect("failed to wrap");
+        wrapped[0] ^= 1;
+        assert_eq!(
+            aes_key_unwrap(&KEK[..16], &wrapped[..24], &mut key_data[..16]),
+            Err(Error::IntegrityCheckFailed)
+        );
+        assert_eq!(key_data, [0u8; 40]);
+    }
+}
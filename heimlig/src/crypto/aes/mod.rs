@@ -1,7 +1,9 @@
 pub mod cbc;
 pub mod ccm;
 pub mod cmac;
+pub mod ctr;
 pub mod gcm;
+pub mod key_wrap;
 
 use aes::{
     cipher::{BlockSizeUser, KeySizeUser, Unsigned},
@@ -26,6 +28,8 @@ pub const GCM_TAG_SIZE: usize = gcm::SupportedTagSize::USIZE;
 pub const CCM_NONCE_SIZE: usize = ccm::SupportedNonceSize::USIZE;
 /// Size of the supported authentication tag in bytes for AES-CCM algorithms.
 pub const CCM_TAG_SIZE: usize = ccm::SupportedTagSize::USIZE;
+/// Size of the nonce in bytes for AES-CTR algorithms.
+pub const CTR_NONCE_SIZE: usize = ctr::NONCE_SIZE;
 /// Size of the supported authentication tag in bytes for AES-CMAC algorithms.
 pub const CMAC_TAG_SIZE: usize = <Aes128 as BlockSizeUser>::BlockSize::USIZE;
 
@@ -1,8 +1,14 @@
-use crate::crypto::{check_sizes_with_tag, Error};
+use crate::crypto::{check_sizes_with_tag, update_padded_segments, Error};
+use chacha20::{
+    cipher::{KeyIvInit, StreamCipher, StreamCipherSeek},
+    ChaCha20,
+};
 use chacha20poly1305::{
     aead::{generic_array::typenum::Unsigned, AeadCore},
     AeadInPlace, ChaCha20Poly1305, KeyInit, KeySizeUser,
 };
+use elliptic_curve::subtle::ConstantTimeEq;
+use poly1305::{universal_hash::UniversalHash, Poly1305};
 use zeroize::Zeroize;
 
 /// Size of the key in bytes for ChaCha20-Poly1305 algorithms
@@ -69,6 +75,101 @@ pub fn decrypt_in_place_detached(
     Ok(())
 }
 
+/// Size of a ChaCha20 keystream block in bytes.
+const CHACHA20_BLOCK_SIZE: u64 = 64;
+
+/// Maximum number of bytes that can be encrypted before the 32-bit block counter overflows. The
+/// first block is used for the Poly1305 key.
+const MAX_DATA_SIZE: u64 = (u32::MAX as u64 - 1) * CHACHA20_BLOCK_SIZE;
+
+/// ChaCha20 keystream positioned at the first data block and Poly1305 keyed from the first
+/// keystream block as described in RFC 8439.
+fn init_cipher_and_mac(key: &[u8], nonce: &[u8]) -> (ChaCha20, Poly1305) {
+    let mut cipher = ChaCha20::new(key.into(), nonce.into());
+    let mut mac_key = poly1305::Key::default();
+    cipher.apply_keystream(&mut mac_key);
+    let mac = Poly1305::new(&mac_key);
+    mac_key.zeroize();
+    cipher.seek(CHACHA20_BLOCK_SIZE);
+    (cipher, mac)
+}
+
+/// Authenticate `ciphertext` and the sizes of the associated data and the ciphertext and return
+/// the tag.
+fn compute_tag(mut mac: Poly1305, aad_size: u64, ciphertext: &[u8]) -> [u8; TAG_SIZE] {
+    mac.update_padded(ciphertext);
+    let mut lengths = poly1305::Block::default();
+    lengths[..8].copy_from_slice(&aad_size.to_le_bytes());
+    lengths[8..].copy_from_slice(&(ciphertext.len() as u64).to_le_bytes());
+    mac.update(&[lengths]);
+    mac.finalize().into()
+}
+
+/// Encrypt data with ChaCha20Poly1305 where the associated data is given as segments that are
+/// authenticated in order, without concatenating them first. The result equals
+/// [encrypt_in_place_detached] with the concatenated associated data.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidSymmetricKeySize`: `key` is not [KEY_SIZE] bytes long.
+/// * `InvalidIvSize`: `nonce` is not [NONCE_SIZE] bytes long.
+/// * `InvalidTagSize`: `tag` is not [TAG_SIZE] bytes long.
+/// * `Encrypt`: `buffer` is too large to be encrypted with a single nonce.
+pub fn encrypt_in_place_detached_aad_segments<T: AsRef<[u8]>>(
+    key: &[u8],
+    nonce: &[u8],
+    aad_segments: &[T],
+    buffer: &mut [u8],
+    tag: &mut [u8],
+) -> Result<(), Error> {
+    check_sizes_with_tag(key, nonce, tag, KEY_SIZE, NONCE_SIZE, TAG_SIZE)?;
+    if buffer.len() as u64 > MAX_DATA_SIZE {
+        return Err(Error::Encrypt);
+    }
+    let (mut cipher, mut mac) = init_cipher_and_mac(key, nonce);
+    let aad_size = update_padded_segments(&mut mac, aad_segments);
+    cipher.apply_keystream(buffer);
+    let mut computed_tag = compute_tag(mac, aad_size, buffer);
+    tag.copy_from_slice(&computed_tag);
+    computed_tag.zeroize();
+    Ok(())
+}
+
+/// Decrypt data with ChaCha20Poly1305 where the associated data is given as segments that are
+/// authenticated in order, without concatenating them first. `buffer` is only decrypted if the
+/// tag matches.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidSymmetricKeySize`: `key` is not [KEY_SIZE] bytes long.
+/// * `InvalidIvSize`: `nonce` is not [NONCE_SIZE] bytes long.
+/// * `InvalidTagSize`: `tag` is not [TAG_SIZE] bytes long.
+/// * `Decrypt`: The tag does not match or `buffer` is too large.
+pub fn decrypt_in_place_detached_aad_segments<T: AsRef<[u8]>>(
+    key: &[u8],
+    nonce: &[u8],
+    aad_segments: &[T],
+    buffer: &mut [u8],
+    tag: &[u8],
+) -> Result<(), Error> {
+    check_sizes_with_tag(key, nonce, tag, KEY_SIZE, NONCE_SIZE, TAG_SIZE)?;
+    if buffer.len() as u64 > MAX_DATA_SIZE {
+        return Err(Error::Decrypt);
+    }
+    let (mut cipher, mut mac) = init_cipher_and_mac(key, nonce);
+    let aad_size = update_padded_segments(&mut mac, aad_segments);
+    let mut computed_tag = compute_tag(mac, aad_size, buffer);
+    let matches: bool = computed_tag.ct_eq(tag).into();
+    computed_tag.zeroize();
+    if !matches {
+        return Err(Error::Decrypt);
+    }
+    cipher.apply_keystream(buffer);
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     extern crate alloc;
@@ -150,6 +251,60 @@ mod test {
         ]
     );
 
+    #[test]
+    fn test_chacha20poly1305_aad_segments() {
+        // Segments cross Poly1305 block boundaries and include an empty one
+        let aad_segments = [&AAD[..0], &AAD[..5], &AAD[5..20], &AAD[20..21], &AAD[21..]];
+
+        let mut expected_buffer = PLAINTEXT.to_owned();
+        let mut expected_tag = [0u8; TAG_SIZE];
+        encrypt_in_place_detached(KEY, NONCE, AAD, &mut expected_buffer, &mut expected_tag)
+            .expect("encryption error");
+        let mut buffer = PLAINTEXT.to_owned();
+        let mut tag = [0u8; TAG_SIZE];
+        encrypt_in_place_detached_aad_segments(KEY, NONCE, &aad_segments, &mut buffer, &mut tag)
+            .expect("encryption error");
+        assert_eq!(buffer, expected_buffer, "ciphertext mismatch");
+        assert_eq!(tag, expected_tag, "tag mismatch");
+
+        let ciphertext = buffer.clone();
+        assert_eq!(
+            decrypt_in_place_detached_aad_segments(
+                KEY,
+                NONCE,
+                &aad_segments[1..4],
+                &mut buffer,
+                &tag
+            ),
+            Err(Error::Decrypt)
+        );
+        assert_eq!(buffer, ciphertext);
+        decrypt_in_place_detached_aad_segments(KEY, NONCE, &aad_segments, &mut buffer, &tag)
+            .expect("decryption error");
+        assert_eq!(buffer, PLAINTEXT);
+
+        assert_eq!(
+            encrypt_in_place_detached_aad_segments(
+                KEY,
+                NONCE,
+                &aad_segments,
+                &mut buffer,
+                &mut tag[..TAG_SIZE - 1]
+            ),
+            Err(Error::InvalidTagSize)
+        );
+        assert_eq!(
+            decrypt_in_place_detached_aad_segments(
+                KEY,
+                &NONCE[1..],
+                &aad_segments,
+                &mut buffer,
+                &tag
+            ),
+            Err(Error::InvalidIvSize)
+        );
+    }
+
     #[test]
     fn test_chacha20poly1305_errors() {
         for size in [0, 1, 8, 16, 24, 256] {
@@ -1,6 +1,11 @@
 pub use crate::crypto::ecc::generate_key_pair;
+use crate::crypto::Error;
 use elliptic_curve::ecdh::{diffie_hellman, SharedSecret};
-use elliptic_curve::{Curve, CurveArithmetic, PublicKey, SecretKey};
+use elliptic_curve::generic_array::typenum::Unsigned;
+use elliptic_curve::sec1::{EncodedPoint, FromEncodedPoint, ModulusSize, ToEncodedPoint};
+use elliptic_curve::{AffinePoint, Curve, CurveArithmetic, FieldBytesSize, PublicKey, SecretKey};
+use p256::NistP256;
+use p384::NistP384;
 
 /// Derive a shared secret from a private key and a public key. If another peer wants to derive the
 /// same secret, he has to switch out the keys with their respective partner keys.
@@ -18,11 +23,75 @@ where
     diffie_hellman(private.to_nonzero_scalar(), public.as_affine())
 }
 
+fn derive_shared_secret_bytes<C>(
+    private_key: &[u8],
+    public_key: &[u8],
+    shared_secret: &mut [u8],
+) -> Result<(), Error>
+where
+    C: Curve + CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    if private_key.len() != FieldBytesSize::<C>::USIZE {
+        return Err(Error::InvalidPrivateKey);
+    }
+    if public_key.len() != <FieldBytesSize<C> as ModulusSize>::UntaggedPointSize::USIZE {
+        return Err(Error::InvalidPublicKey);
+    }
+    if shared_secret.len() != FieldBytesSize::<C>::USIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+    let private_key =
+        SecretKey::<C>::from_slice(private_key).map_err(|_| Error::InvalidPrivateKey)?;
+    let public_key: Option<PublicKey<C>> = PublicKey::<C>::from_encoded_point(
+        &EncodedPoint::<C>::from_untagged_bytes(public_key.into()),
+    )
+    .into();
+    let public_key = public_key.ok_or(Error::InvalidPublicKey)?;
+    shared_secret
+        .copy_from_slice(derive_shared_secret(&private_key, &public_key).raw_secret_bytes());
+    Ok(())
+}
+
+macro_rules! define_nist_ecdh_impl {
+    (
+        $curve:tt,
+        $derive:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!("Derive a ", $doc, " shared secret from raw key bytes.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `private_key`: The private key of this peer.
+        /// * `public_key`: The uncompressed public key of the other peer without the SEC1 tag byte
+        /// (concatenated X and Y coordinates).
+        /// * `shared_secret`: Output buffer for the X coordinate of the shared point. Must have the
+        /// size of the private key.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidPrivateKey`: `private_key` has an invalid size or value.
+        /// * `InvalidPublicKey`: `public_key` has an invalid size or is not a point on the curve.
+        /// * `InvalidBufferSize`: `shared_secret` has an invalid size.
+        pub fn $derive(
+            private_key: &[u8],
+            public_key: &[u8],
+            shared_secret: &mut [u8],
+        ) -> Result<(), Error> {
+            derive_shared_secret_bytes::<$curve>(private_key, public_key, shared_secret)
+        }
+    };
+}
+
+define_nist_ecdh_impl!(NistP256, nist_p256_derive_shared_secret, "NIST P-256");
+define_nist_ecdh_impl!(NistP384, nist_p384_derive_shared_secret, "NIST P-384");
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use p256::NistP256;
-    use p384::NistP384;
     use rand_chacha::rand_core::SeedableRng;
     use rand_chacha::ChaCha20Rng;
 
@@ -51,4 +120,40 @@ mod test {
             remote_secret.raw_secret_bytes()
         );
     }
+
+    #[test]
+    fn test_p256_bytes() {
+        let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+        let (local_public, local_private) = generate_key_pair::<_, NistP256>(&mut rng);
+        let (remote_public, remote_private) = generate_key_pair::<_, NistP256>(&mut rng);
+        let mut local_secret = [0u8; 32];
+        let mut remote_secret = [0u8; 32];
+        nist_p256_derive_shared_secret(
+            &local_private.to_bytes(),
+            &remote_public.to_encoded_point(false).as_bytes()[1..],
+            &mut local_secret,
+        )
+        .expect("failed to derive shared secret");
+        nist_p256_derive_shared_secret(
+            &remote_private.to_bytes(),
+            &local_public.to_encoded_point(false).as_bytes()[1..],
+            &mut remote_secret,
+        )
+        .expect("failed to derive shared secret");
+        assert_eq!(local_secret, remote_secret);
+        assert_eq!(
+            local_secret.as_slice(),
+            derive_shared_secret(&local_private, &remote_public)
+                .raw_secret_bytes()
+                .as_slice()
+        );
+        assert_eq!(
+            nist_p256_derive_shared_secret(
+                &local_private.to_bytes(),
+                &[0u8; 64],
+                &mut local_secret
+            ),
+            Err(Error::InvalidPublicKey)
+        );
+    }
 }
@@ -6,7 +6,7 @@ use ecdsa::{
         ops::Invert,
         sec1::{FromEncodedPoint, ModulusSize, ToEncodedPoint},
         subtle::CtOption,
-        AffinePoint, Curve, CurveArithmetic, FieldBytesSize, Scalar, SecretKey,
+        AffinePoint, Curve, CurveArithmetic, FieldBytesSize, PublicKey, Scalar, SecretKey,
     },
     hazmat::{DigestPrimitive, SignPrimitive},
     signature::{
@@ -24,6 +24,7 @@ type PrivateKeySize<C> = FieldBytesSize<C>;
 type PrivateKeyBytes<C> = GenericArray<u8, PrivateKeySize<C>>;
 type PublicKeySize<C> = <FieldBytesSize<C> as ModulusSize>::UntaggedPointSize;
 type PublicKeyBytes<C> = GenericArray<u8, PublicKeySize<C>>;
+type CompressedPublicKeySize<C> = <FieldBytesSize<C> as ModulusSize>::CompressedPointSize;
 type DigestSize<C> = FieldBytesSize<C>;
 
 fn check_digest_and_signature_sizes<C>(digest: &[u8], signature: &[u8]) -> Result<(), Error>
@@ -120,6 +121,74 @@ where
         .map_err(|_| Error::InvalidSignature)
 }
 
+fn compress_public_key<C>(public_key: &[u8], compressed_public_key: &mut [u8]) -> Result<(), Error>
+where
+    C: Curve + CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    if public_key.len() != PublicKeySize::<C>::USIZE {
+        return Err(Error::InvalidPublicKey);
+    }
+    if compressed_public_key.len() != CompressedPublicKeySize::<C>::USIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+    let public_key: Option<PublicKey<C>> = PublicKey::<C>::from_encoded_point(
+        &EncodedPoint::<C>::from_untagged_bytes(public_key.into()),
+    )
+    .into();
+    let public_key = public_key.ok_or(Error::InvalidPublicKey)?;
+    compressed_public_key.copy_from_slice(public_key.to_encoded_point(true).as_bytes());
+    Ok(())
+}
+
+fn public_key_from_private_key<C>(private_key: &[u8], public_key: &mut [u8]) -> Result<(), Error>
+where
+    C: Curve + CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    if private_key.len() != PrivateKeySize::<C>::USIZE {
+        return Err(Error::InvalidPrivateKey);
+    }
+    if public_key.len() != PublicKeySize::<C>::USIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+    let secret_key =
+        SecretKey::<C>::from_slice(private_key).map_err(|_| Error::InvalidPrivateKey)?;
+    // Skip the SEC1 tag of the uncompressed point
+    public_key.copy_from_slice(&secret_key.public_key().to_encoded_point(false).as_bytes()[1..]);
+    Ok(())
+}
+
+fn decompress_public_key<C>(
+    compressed_public_key: &[u8],
+    public_key: &mut [u8],
+) -> Result<(), Error>
+where
+    C: Curve + CurveArithmetic,
+    AffinePoint<C>: FromEncodedPoint<C> + ToEncodedPoint<C>,
+    FieldBytesSize<C>: ModulusSize,
+{
+    if compressed_public_key.len() != CompressedPublicKeySize::<C>::USIZE {
+        return Err(Error::InvalidPublicKey);
+    }
+    if public_key.len() != PublicKeySize::<C>::USIZE {
+        return Err(Error::InvalidBufferSize);
+    }
+    let encoded_point = EncodedPoint::<C>::from_bytes(compressed_public_key)
+        .map_err(|_| Error::InvalidPublicKey)?;
+    if !encoded_point.is_compressed() {
+        return Err(Error::InvalidPublicKey);
+    }
+    let decompressed: Option<PublicKey<C>> =
+        PublicKey::<C>::from_encoded_point(&encoded_point).into();
+    let decompressed = decompressed.ok_or(Error::InvalidPublicKey)?;
+    // Skip the SEC1 tag of the uncompressed point
+    public_key.copy_from_slice(&decompressed.to_encoded_point(false).as_bytes()[1..]);
+    Ok(())
+}
+
 fn generate_key_pair<R, C>(rng: &mut R) -> (PrivateKeyBytes<C>, PublicKeyBytes<C>)
 where
     R: CryptoRng + RngCore,
@@ -409,6 +478,180 @@ define_nist_impl!(
     "NIST P-384"
 );
 
+macro_rules! define_nist_der_impl {
+    (
+        $curve:tt,
+        $from_der:ident,
+        $signature_size:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!("Convert a DER-encoded ", $doc, " ECDSA signature to its raw form.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `der_signature`: ASN.1 DER-encoded `ECDSA-Sig-Value` as specified in RFC 3279.
+        /// * `signature`: Output buffer for the raw signature, the concatenated `r` and `s` values.
+        ///   The signature slice length has to be `
+        #[doc = stringify!($signature_size)]
+        /// ` bytes long.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidSignatureSize`: The `signature` slice has the wrong size.
+        /// * `InvalidSignatureEncoding`: `der_signature` is not a valid DER-encoded signature.
+        pub fn $from_der(der_signature: &[u8], signature: &mut [u8]) -> Result<(), Error> {
+            if signature.len() != $signature_size {
+                return Err(Error::InvalidSignatureSize);
+            }
+            let parsed = Signature::<$curve>::from_der(der_signature)
+                .map_err(|_| Error::InvalidSignatureEncoding)?;
+            signature.copy_from_slice(&parsed.to_bytes());
+            Ok(())
+        }
+    };
+}
+
+define_nist_der_impl!(
+    NistP256,
+    nist_p256_signature_from_der,
+    NIST_P256_SIGNATURE_SIZE,
+    "NIST P-256"
+);
+define_nist_der_impl!(
+    NistP384,
+    nist_p384_signature_from_der,
+    NIST_P384_SIGNATURE_SIZE,
+    "NIST P-384"
+);
+
+macro_rules! define_nist_point_compression_impl {
+    (
+        $curve:tt,
+        $compress:ident,
+        $decompress:ident,
+        $compressed_public_key_size:ident,
+        $public_key_size:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!("Size of a compressed ", $doc, " public key in bytes.")]
+        pub const $compressed_public_key_size: usize = CompressedPublicKeySize::<$curve>::USIZE;
+
+        #[doc = concat!("Compress a ", $doc, " public key to its SEC1 compressed point form.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `public_key`: Uncompressed public key, the concatenated X and Y coordinates.
+        ///   The public key slice length has to be `
+        #[doc = stringify!($public_key_size)]
+        /// ` bytes long.
+        /// * `compressed_public_key`: Output buffer for the tag byte followed by the X coordinate.
+        ///   The slice length has to be `
+        #[doc = stringify!($compressed_public_key_size)]
+        /// ` bytes long.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidPublicKey`: The `public_key` has the wrong size or is not a point on the curve.
+        /// * `InvalidBufferSize`: The `compressed_public_key` slice has the wrong size.
+        pub fn $compress(public_key: &[u8], compressed_public_key: &mut [u8]) -> Result<(), Error> {
+            compress_public_key::<$curve>(public_key, compressed_public_key)
+        }
+
+        #[doc = concat!("Decompress a ", $doc, " public key given in SEC1 compressed point form.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `compressed_public_key`: Compressed public key. The slice length has to be `
+        #[doc = stringify!($compressed_public_key_size)]
+        /// ` bytes long.
+        /// * `public_key`: Output buffer for the concatenated X and Y coordinates.
+        ///   The public key slice length has to be `
+        #[doc = stringify!($public_key_size)]
+        /// ` bytes long.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidPublicKey`: The `compressed_public_key` has the wrong size or is not a point
+        ///   on the curve.
+        /// * `InvalidBufferSize`: The `public_key` slice has the wrong size.
+        pub fn $decompress(
+            compressed_public_key: &[u8],
+            public_key: &mut [u8],
+        ) -> Result<(), Error> {
+            decompress_public_key::<$curve>(compressed_public_key, public_key)
+        }
+    };
+}
+
+macro_rules! define_nist_public_key_impl {
+    (
+        $curve:tt,
+        $public_key_from_private_key:ident,
+        $private_key_size:ident,
+        $public_key_size:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!("Derive the ", $doc, " public key belonging to a private key.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `private_key`: The private key. The private key slice length has to be `
+        #[doc = stringify!($private_key_size)]
+        /// ` bytes long.
+        /// * `public_key`: Output buffer for the concatenated X and Y coordinates.
+        ///   The public key slice length has to be `
+        #[doc = stringify!($public_key_size)]
+        /// ` bytes long.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidPrivateKey`: The `private_key` has the wrong size or is not a valid scalar.
+        /// * `InvalidBufferSize`: The `public_key` slice has the wrong size.
+        pub fn $public_key_from_private_key(
+            private_key: &[u8],
+            public_key: &mut [u8],
+        ) -> Result<(), Error> {
+            public_key_from_private_key::<$curve>(private_key, public_key)
+        }
+    };
+}
+
+define_nist_public_key_impl!(
+    NistP256,
+    nist_p256_public_key_from_private_key,
+    NIST_P256_PRIVATE_KEY_SIZE,
+    NIST_P256_PUBLIC_KEY_SIZE,
+    "NIST P-256"
+);
+define_nist_public_key_impl!(
+    NistP384,
+    nist_p384_public_key_from_private_key,
+    NIST_P384_PRIVATE_KEY_SIZE,
+    NIST_P384_PUBLIC_KEY_SIZE,
+    "NIST P-384"
+);
+
+define_nist_point_compression_impl!(
+    NistP256,
+    nist_p256_compress_public_key,
+    nist_p256_decompress_public_key,
+    NIST_P256_COMPRESSED_PUBLIC_KEY_SIZE,
+    NIST_P256_PUBLIC_KEY_SIZE,
+    "NIST P-256"
+);
+define_nist_point_compression_impl!(
+    NistP384,
+    nist_p384_compress_public_key,
+    nist_p384_decompress_public_key,
+    NIST_P384_COMPRESSED_PUBLIC_KEY_SIZE,
+    NIST_P384_PUBLIC_KEY_SIZE,
+    "NIST P-384"
+);
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -616,6 +859,119 @@ mod test {
         NIST_P256_PUBLIC_KEY_SIZE
     );
 
+    #[test]
+    fn nist_p256_der_signature() {
+        let mut rng = rand_chacha::ChaCha20Rng::from_seed([0u8; 32]);
+        let (private_key, public_key) = nist_p256_generate_key_pair(&mut rng);
+        let mut signature = [0u8; NIST_P256_SIGNATURE_SIZE];
+        let mut converted_signature = [0u8; NIST_P256_SIGNATURE_SIZE];
+        nist_p256_sign(&private_key, MESSAGE, &mut signature).expect("signing failed");
+        let der_signature = Signature::<NistP256>::from_slice(&signature)
+            .expect("failed to parse signature")
+            .to_der();
+
+        nist_p256_signature_from_der(der_signature.as_bytes(), &mut converted_signature)
+            .expect("failed to convert signature");
+        assert_eq!(signature, converted_signature);
+        nist_p256_verify(&public_key, MESSAGE, &converted_signature).expect("verifying failed");
+
+        assert_eq!(
+            nist_p256_signature_from_der(&signature, &mut converted_signature),
+            Err(Error::InvalidSignatureEncoding)
+        );
+        assert_eq!(
+            nist_p256_signature_from_der(der_signature.as_bytes(), &mut converted_signature[1..]),
+            Err(Error::InvalidSignatureSize)
+        );
+    }
+
+    // RFC 6979, Appendix A.2.5, ECDSA with P-256 and SHA-256
+    #[test]
+    fn nist_p256_rfc6979_vectors() {
+        let private_key =
+            hex::decode("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721")
+                .expect("Failed to decode hex string");
+        let public_key = hex::decode(concat!(
+            "60fed4ba255a9d31c961eb74c6356d68c049b8923b61fa6ce669622e60f29fb6",
+            "7903fe1008b8bc99a41ae9e95628bc64f2f1b20c2d7e9f5177a3c294d4462299"
+        ))
+        .expect("Failed to decode hex string");
+        let vectors: [(&[u8], &str); 2] = [
+            (
+                b"sample",
+                concat!(
+                    "efd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716",
+                    "f7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8"
+                ),
+            ),
+            (
+                b"test",
+                concat!(
+                    "f1abb023518351cd71d881567b1ea663ed3efcf6c5132b354f28d3b0b7d38367",
+                    "019f4113742a2b14bd25926b49c649155f267e60d3814b4c0cc84250e46f0083"
+                ),
+            ),
+        ];
+
+        let mut derived_public_key = [0u8; NIST_P256_PUBLIC_KEY_SIZE];
+        nist_p256_public_key_from_private_key(&private_key, &mut derived_public_key)
+            .expect("failed to derive public key");
+        assert_eq!(derived_public_key, public_key.as_slice());
+
+        for (message, expected_signature) in vectors {
+            let expected_signature =
+                hex::decode(expected_signature).expect("Failed to decode hex string");
+            let digest = <NistP256 as DigestPrimitive>::Digest::new_with_prefix(message).finalize();
+            let mut signature = [0u8; NIST_P256_SIGNATURE_SIZE];
+            nist_p256_sign(&private_key, message, &mut signature).expect("signing failed");
+            assert_eq!(signature, expected_signature.as_slice());
+            nist_p256_sign_prehashed(&private_key, digest.as_slice(), &mut signature)
+                .expect("signing of prehashed message failed");
+            assert_eq!(signature, expected_signature.as_slice());
+            nist_p256_verify_prehashed(&public_key, digest.as_slice(), &signature)
+                .expect("verification failed");
+        }
+    }
+
+    #[test]
+    fn nist_p256_derive_public_key() {
+        let mut rng = rand_chacha::ChaCha20Rng::from_seed([0u8; 32]);
+        let (private_key, public_key) = nist_p256_generate_key_pair(&mut rng);
+        let mut derived_public_key = [0u8; NIST_P256_PUBLIC_KEY_SIZE];
+        nist_p256_public_key_from_private_key(&private_key, &mut derived_public_key)
+            .expect("failed to derive public key");
+        assert_eq!(public_key, derived_public_key);
+        assert_eq!(
+            nist_p256_public_key_from_private_key(&private_key[1..], &mut derived_public_key),
+            Err(Error::InvalidPrivateKey)
+        );
+    }
+
+    #[test]
+    fn nist_p256_point_compression() {
+        let mut rng = rand_chacha::ChaCha20Rng::from_seed([0u8; 32]);
+        let (_, public_key) = nist_p256_generate_key_pair(&mut rng);
+        let mut compressed_public_key = [0u8; NIST_P256_COMPRESSED_PUBLIC_KEY_SIZE];
+        let mut decompressed_public_key = [0u8; NIST_P256_PUBLIC_KEY_SIZE];
+        nist_p256_compress_public_key(&public_key, &mut compressed_public_key)
+            .expect("failed to compress public key");
+        assert_eq!(compressed_public_key.len(), 33);
+        assert_eq!(compressed_public_key[1..], public_key[..32]);
+        nist_p256_decompress_public_key(&compressed_public_key, &mut decompressed_public_key)
+            .expect("failed to decompress public key");
+        assert_eq!(public_key, decompressed_public_key);
+
+        compressed_public_key[0] = 0x05;
+        assert_eq!(
+            nist_p256_decompress_public_key(&compressed_public_key, &mut decompressed_public_key),
+            Err(Error::InvalidPublicKey)
+        );
+        assert_eq!(
+            nist_p256_compress_public_key(&public_key, &mut compressed_public_key[1..]),
+            Err(Error::InvalidBufferSize)
+        );
+    }
+
     define_nist_error_test!(
         nist_p384_error_test,
         NistP384,
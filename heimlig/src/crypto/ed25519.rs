@@ -120,6 +120,72 @@ mod test {
         ed25519_verify(&public_key, MESSAGE, &signature).expect("verifying error");
     }
 
+    // RFC 8032, Section 7.1, Tests 1 to 3
+    const RFC8032_VECTORS: [(&str, &str, &str, &str); 3] = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            concat!(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e06522490155",
+                "5fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b"
+            ),
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            concat!(
+                "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da",
+                "085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00"
+            ),
+        ),
+        (
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            concat!(
+                "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac",
+                "18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a"
+            ),
+        ),
+    ];
+
+    #[test]
+    fn test_ed25519_rfc8032_vectors() {
+        for (private_key, expected_public_key, message, expected_signature) in RFC8032_VECTORS {
+            let private_key = hex::decode(private_key).expect("Failed to decode hex string");
+            let expected_public_key =
+                hex::decode(expected_public_key).expect("Failed to decode hex string");
+            let message = hex::decode(message).expect("Failed to decode hex string");
+            let expected_signature =
+                hex::decode(expected_signature).expect("Failed to decode hex string");
+
+            let mut public_key = [0u8; PUBLIC_KEY_SIZE];
+            ed25519_calculate_public_key(&private_key, &mut public_key)
+                .expect("public key calculation error");
+            assert_eq!(public_key, expected_public_key.as_slice());
+
+            let mut signature = [0u8; SIGNATURE_SIZE];
+            ed25519_sign(&private_key, &message, &mut signature).expect("signing error");
+            assert_eq!(signature, expected_signature.as_slice());
+
+            ed25519_verify(&public_key, &message, &signature).expect("verifying error");
+        }
+    }
+
+    #[test]
+    fn test_ed25519_malformed_public_key() {
+        // Y coordinate without a matching X coordinate on the curve
+        let mut public_key = [0u8; PUBLIC_KEY_SIZE];
+        public_key[0] = 0x02;
+        let signature = [0u8; SIGNATURE_SIZE];
+        assert_eq!(
+            ed25519_verify(&public_key, MESSAGE, &signature),
+            Err(Error::InvalidPublicKey)
+        );
+    }
+
     #[test]
     fn test_ed25519_size_errors() {
         const BUFF_SIZE: usize = 128;
@@ -1,5 +1,7 @@
+use sha2::digest::{core_api::BlockSizeUser, generic_array::GenericArray, Output};
 use sha2::{Digest, Sha256, Sha384, Sha512};
 use sha3::{Sha3_256, Sha3_384, Sha3_512};
+use zeroize::Zeroize;
 
 /// Digest size of SHA-256
 pub const SHA256_SIZE: usize = 32;
@@ -38,6 +40,69 @@ pub fn blake3<T: AsRef<[u8]>>(input: T) -> [u8; BLAKE3_SIZE] {
     blake3::hash(input.as_ref()).into()
 }
 
+/// Streaming hasher that passes input to the digest algorithm in whole blocks.
+///
+/// Chunks of arbitrary size can be fed with [BlockAlignedHasher::update]. Bytes that do not fill a
+/// complete block are buffered until the next chunk arrives. Only [BlockAlignedHasher::finalize]
+/// passes a partial block, the buffered tail of the input. This matches hash accelerators that
+/// accept block-aligned input, with a shorter last block. The buffer is zeroized on drop.
+pub struct BlockAlignedHasher<D: Digest + BlockSizeUser> {
+    hasher: D,
+    buffer: GenericArray<u8, D::BlockSize>,
+    buffered: usize,
+}
+
+impl<D: Digest + BlockSizeUser> Default for BlockAlignedHasher<D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest + BlockSizeUser> BlockAlignedHasher<D> {
+    pub fn new() -> Self {
+        Self {
+            hasher: D::new(),
+            buffer: GenericArray::default(),
+            buffered: 0,
+        }
+    }
+
+    /// Feed the next chunk of input.
+    pub fn update(&mut self, mut chunk: &[u8]) {
+        let block_size = self.buffer.len();
+        if self.buffered > 0 {
+            let missing = (block_size - self.buffered).min(chunk.len());
+            self.buffer[self.buffered..self.buffered + missing].copy_from_slice(&chunk[..missing]);
+            self.buffered += missing;
+            chunk = &chunk[missing..];
+            if self.buffered < block_size {
+                return;
+            }
+            self.hasher.update(&self.buffer);
+            self.buffered = 0;
+        }
+        let aligned_size = chunk.len() - chunk.len() % block_size;
+        let (blocks, tail) = chunk.split_at(aligned_size);
+        if !blocks.is_empty() {
+            self.hasher.update(blocks);
+        }
+        self.buffer[..tail.len()].copy_from_slice(tail);
+        self.buffered = tail.len();
+    }
+
+    /// Process the buffered partial block and return the digest.
+    pub fn finalize(mut self) -> Output<D> {
+        self.hasher.update(&self.buffer[..self.buffered]);
+        core::mem::replace(&mut self.hasher, D::new()).finalize()
+    }
+}
+
+impl<D: Digest + BlockSizeUser> Drop for BlockAlignedHasher<D> {
+    fn drop(&mut self) {
+        self.buffer.as_mut_slice().zeroize();
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -53,6 +118,19 @@ mod test {
         assert_eq!(output, expected.as_slice());
     }
 
+    // NIST CSRC example values for SHA-256
+    #[test]
+    fn test_sha256_nist() {
+        let expected =
+            hex::decode("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+                .expect("Failed to decode hex string");
+        assert_eq!(sha256(b""), expected.as_slice());
+        let expected =
+            hex::decode("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+                .expect("Failed to decode hex string");
+        assert_eq!(sha256(b"abc"), expected.as_slice());
+    }
+
     #[test]
     fn test_sha384() {
         let output = sha384(HELLO_WORLD);
@@ -67,6 +145,23 @@ mod test {
         assert_eq!(output, expected.as_slice());
     }
 
+    // NIST CSRC example values for SHA-384 and SHA-512
+    #[test]
+    fn test_sha384_nist() {
+        let expected = hex::decode("38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b").expect("Failed to decode hex string");
+        assert_eq!(sha384(b""), expected.as_slice());
+        let expected = hex::decode("cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7").expect("Failed to decode hex string");
+        assert_eq!(sha384(b"abc"), expected.as_slice());
+    }
+
+    #[test]
+    fn test_sha512_nist() {
+        let expected = hex::decode("cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e").expect("Failed to decode hex string");
+        assert_eq!(sha512(b""), expected.as_slice());
+        let expected = hex::decode("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f").expect("Failed to decode hex string");
+        assert_eq!(sha512(b"abc"), expected.as_slice());
+    }
+
     #[test]
     fn test_sha3_256() {
         let output = sha3_256(HELLO_WORLD);
@@ -98,4 +193,27 @@ mod test {
                 .expect("Failed to decode hex string");
         assert_eq!(output, expected.as_slice());
     }
+
+    #[test]
+    fn test_block_aligned_hasher() {
+        let input: [u8; 300] = core::array::from_fn(|i| i as u8);
+        for chunk_size in [1, 7, 63, 64, 65, 129, 300] {
+            let mut hasher = BlockAlignedHasher::<Sha256>::new();
+            for chunk in input.chunks(chunk_size) {
+                hasher.update(chunk);
+                hasher.update(&[]);
+            }
+            assert_eq!(hasher.finalize().as_slice(), sha256(input));
+
+            let mut hasher = BlockAlignedHasher::<Sha512>::new();
+            for chunk in input.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize().as_slice(), sha512(input));
+        }
+        assert_eq!(
+            BlockAlignedHasher::<Sha256>::new().finalize().as_slice(),
+            sha256([])
+        );
+    }
 }
@@ -0,0 +1,158 @@
+use crate::crypto::Error;
+use hkdf::{
+    hmac::digest::{
+        block_buffer::Eager,
+        core_api::{BlockSizeUser, BufferKindUser, CoreProxy, FixedOutputCore, UpdateCore},
+        typenum::{IsLess, Le, NonZero, U256},
+        HashMarker, OutputSizeUser,
+    },
+    Hkdf,
+};
+use sha2::{Sha256, Sha384, Sha512};
+
+fn hkdf_derive<D>(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Error>
+where
+    D: CoreProxy + OutputSizeUser,
+    D::Core: HashMarker
+        + UpdateCore
+        + FixedOutputCore
+        + BufferKindUser<BufferKind = Eager>
+        + Default
+        + Clone,
+    <D::Core as BlockSizeUser>::BlockSize: IsLess<U256>,
+    Le<<D::Core as BlockSizeUser>::BlockSize, U256>: NonZero,
+{
+    let salt = if salt.is_empty() { None } else { Some(salt) };
+    Hkdf::<D>::new(salt, ikm)
+        .expand(info, okm)
+        .map_err(|_| Error::InvalidBufferSize)
+}
+
+macro_rules! define_hkdf_impl {
+    (
+        $digest:tt,
+        $derive:ident,
+        $doc:expr
+    ) => {
+        #[doc = concat!("HKDF-",$doc, " key derivation (extract and expand) as specified in RFC 5869.")]
+        ///
+        /// # Arguments
+        ///
+        /// * `salt`: Optional salt. An empty slice is treated as no salt.
+        /// * `ikm`: Input keying material.
+        /// * `info`: Context and application specific information.
+        /// * `okm`: Output buffer that is completely filled with the derived key material.
+        ///
+        /// # Errors
+        ///
+        /// The function returns an error if:
+        /// * `InvalidBufferSize`: The `okm` slice is longer than 255 times the digest size.
+        pub fn $derive(salt: &[u8], ikm: &[u8], info: &[u8], okm: &mut [u8]) -> Result<(), Error> {
+            hkdf_derive::<$digest>(salt, ikm, info, okm)
+        }
+    };
+}
+
+define_hkdf_impl!(Sha256, hkdf_sha2_256, "SHA-256");
+define_hkdf_impl!(Sha384, hkdf_sha2_384, "SHA-384");
+define_hkdf_impl!(Sha512, hkdf_sha2_512, "SHA-512");
+
+/// Label prepended to the message type when deriving MAC keys.
+const MAC_KEY_LABEL: &[u8] = b"heimlig mac key";
+
+/// Replace a base key with a MAC key of the same size that is bound to a message type.
+/// The MAC key is derived using HKDF-SHA-256.
+///
+/// Using a different derived key per message type prevents a MAC calculated for one message type
+/// from being accepted for another one.
+///
+/// # Arguments
+///
+/// * `key`: The base key. Overwritten with the derived MAC key.
+/// * `message_type`: Label identifying the message type.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidBufferSize`: The `key` slice is longer than 255 times the SHA-256 digest size.
+pub fn derive_mac_key(key: &mut [u8], message_type: &[u8]) -> Result<(), Error> {
+    let hkdf = Hkdf::<Sha256>::new(None, key);
+    hkdf.expand_multi_info(&[MAC_KEY_LABEL, message_type], key)
+        .map_err(|_| Error::InvalidBufferSize)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate alloc;
+    use super::*;
+    use alloc::vec::Vec;
+
+    // RFC 5869, Test Case 1
+    #[test]
+    fn test_hkdf_sha2_256() {
+        let ikm = hex::decode("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b")
+            .expect("Failed to decode hex string");
+        let salt = hex::decode("000102030405060708090a0b0c").expect("Failed to decode hex string");
+        let info = hex::decode("f0f1f2f3f4f5f6f7f8f9").expect("Failed to decode hex string");
+        let expected = hex::decode(
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865",
+        )
+        .expect("Failed to decode hex string");
+        let mut okm = [0u8; 42];
+        hkdf_sha2_256(&salt, &ikm, &info, &mut okm).expect("failed to derive key");
+        assert_eq!(okm, expected.as_slice());
+    }
+
+    // RFC 5869, Test Case 2: longer inputs and outputs
+    #[test]
+    fn test_hkdf_sha2_256_long_inputs() {
+        let ikm: Vec<u8> = (0x00..=0x4f).collect();
+        let salt: Vec<u8> = (0x60..=0xaf).collect();
+        let info: Vec<u8> = (0xb0..=0xff).collect();
+        let expected = hex::decode(concat!(
+            "b11e398dc80327a1c8e7f78c596a49344f012eda2d4efad8a050cc4c19afa97c",
+            "59045a99cac7827271cb41c65e590e09da3275600c2f09b8367793a9aca3db71",
+            "cc30c58179ec3e87c14c01d5c1f3434f1d87"
+        ))
+        .expect("Failed to decode hex string");
+        let mut okm = [0u8; 82];
+        hkdf_sha2_256(&salt, &ikm, &info, &mut okm).expect("failed to derive key");
+        assert_eq!(okm, expected.as_slice());
+    }
+
+    // RFC 5869, Test Case 3: zero-length salt and info
+    #[test]
+    fn test_hkdf_sha2_256_no_salt_and_info() {
+        let ikm = [0x0bu8; 22];
+        let expected = hex::decode(
+            "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d9d201395faa4b61a96c8",
+        )
+        .expect("Failed to decode hex string");
+        let mut okm = [0u8; 42];
+        hkdf_sha2_256(&[], &ikm, &[], &mut okm).expect("failed to derive key");
+        assert_eq!(okm, expected.as_slice());
+    }
+
+    #[test]
+    fn test_derive_mac_key() {
+        let base_key = [1u8; 32];
+        let mut key_a = base_key;
+        let mut key_a_again = base_key;
+        let mut key_b = base_key;
+        derive_mac_key(&mut key_a, b"a").expect("failed to derive key");
+        derive_mac_key(&mut key_a_again, b"a").expect("failed to derive key");
+        derive_mac_key(&mut key_b, b"b").expect("failed to derive key");
+        assert_eq!(key_a, key_a_again);
+        assert_ne!(key_a, key_b);
+        assert_ne!(key_a, base_key);
+    }
+
+    #[test]
+    fn test_hkdf_output_too_long() {
+        let mut okm = [0u8; 255 * 32 + 1];
+        assert_eq!(
+            hkdf_sha2_256(&[], b"ikm", b"info", &mut okm),
+            Err(Error::InvalidBufferSize)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::crypto::Error;
+use core::num::NonZeroU32;
 use hmac::{
     digest::{
         block_buffer::Eager,
@@ -162,6 +163,18 @@ define_hmac_impl!(
     "SHA3-512"
 );
 
+/// PBKDF2 key derivation with HMAC-SHA-256 as pseudorandom function as specified in RFC 8018.
+///
+/// # Arguments
+///
+/// * `password`: Password to derive the key from.
+/// * `salt`: Salt. Should be at least 8 bytes long.
+/// * `iterations`: Number of iterations.
+/// * `okm`: Output buffer that is completely filled with the derived key material.
+pub fn pbkdf2_hmac_sha2_256(password: &[u8], salt: &[u8], iterations: NonZeroU32, okm: &mut [u8]) {
+    pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, iterations.get(), okm);
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -169,6 +182,17 @@ mod test {
     pub const KEY: &[u8; 7] = b"Gandalf";
     pub const MESSAGE: &[u8; 67] =
         b"All we have to decide is what to do with the time that is given us.";
+    pub const RFC4231_KEY_1: &[u8; 20] = &[0x0b; 20];
+    pub const RFC4231_KEY_3: &[u8; 20] = &[0xaa; 20];
+    pub const RFC4231_DATA_3: &[u8; 50] = &[0xdd; 50];
+    pub const RFC4231_KEY_4: &[u8; 25] = &[
+        0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19,
+    ];
+    pub const RFC4231_DATA_4: &[u8; 50] = &[0xcd; 50];
+    pub const RFC4231_KEY_5: &[u8; 20] = &[0x0c; 20];
+    /// Larger than the SHA-256 block size.
+    pub const RFC4231_KEY_6: &[u8; 131] = &[0xaa; 131];
 
     macro_rules! define_hmac_calculate_verify_test {
         (
@@ -210,6 +234,105 @@ mod test {
             0x6b, 0xa9, 0x21, 0x11,
         ]
     );
+    // RFC 4231, Test Case 1
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_1,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        RFC4231_KEY_1,
+        b"Hi There",
+        [
+            0xb0, 0x34, 0x4c, 0x61, 0xd8, 0xdb, 0x38, 0x53, 0x5c, 0xa8, 0xaf, 0xce, 0xaf, 0x0b,
+            0xf1, 0x2b, 0x88, 0x1d, 0xc2, 0x00, 0xc9, 0x83, 0x3d, 0xa7, 0x26, 0xe9, 0x37, 0x6c,
+            0x2e, 0x32, 0xcf, 0xf7,
+        ]
+    );
+    // RFC 4231, Test Case 2
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_2,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        b"Jefe",
+        b"what do ya want for nothing?",
+        [
+            0x5b, 0xdc, 0xc1, 0x46, 0xbf, 0x60, 0x75, 0x4e, 0x6a, 0x04, 0x24, 0x26, 0x08, 0x95,
+            0x75, 0xc7, 0x5a, 0x00, 0x3f, 0x08, 0x9d, 0x27, 0x39, 0x83, 0x9d, 0xec, 0x58, 0xb9,
+            0x64, 0xec, 0x38, 0x43,
+        ]
+    );
+    // RFC 4231, Test Case 3
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_3,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        RFC4231_KEY_3,
+        RFC4231_DATA_3,
+        [
+            0x77, 0x3e, 0xa9, 0x1e, 0x36, 0x80, 0x0e, 0x46, 0x85, 0x4d, 0xb8, 0xeb, 0xd0, 0x91,
+            0x81, 0xa7, 0x29, 0x59, 0x09, 0x8b, 0x3e, 0xf8, 0xc1, 0x22, 0xd9, 0x63, 0x55, 0x14,
+            0xce, 0xd5, 0x65, 0xfe,
+        ]
+    );
+    // RFC 4231, Test Case 4
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_4,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        RFC4231_KEY_4,
+        RFC4231_DATA_4,
+        [
+            0x82, 0x55, 0x8a, 0x38, 0x9a, 0x44, 0x3c, 0x0e, 0xa4, 0xcc, 0x81, 0x98, 0x99, 0xf2,
+            0x08, 0x3a, 0x85, 0xf0, 0xfa, 0xa3, 0xe5, 0x78, 0xf8, 0x07, 0x7a, 0x2e, 0x3f, 0xf4,
+            0x67, 0x29, 0x66, 0x5b,
+        ]
+    );
+    // RFC 4231, Test Case 5, truncated to 128 bits
+    #[test]
+    fn hmac_sha256_rfc4231_test_case_5() {
+        let mut tag = [0u8; HMAC_SHA2_256_SIZE];
+        hmac_sha2_256_calculate(RFC4231_KEY_5, b"Test With Truncation", &mut tag)
+            .expect("failed to calculate the tag");
+        assert_eq!(
+            tag[..16],
+            [
+                0xa3, 0xb6, 0x16, 0x74, 0x73, 0x10, 0x0e, 0xe0, 0x6e, 0x0c, 0x79, 0x6c, 0x29, 0x55,
+                0x55, 0x2b,
+            ],
+            "unexpected tag value"
+        );
+    }
+    // RFC 4231, Test Case 6
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_6,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        RFC4231_KEY_6,
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+        [
+            0x60, 0xe4, 0x31, 0x59, 0x1e, 0xe0, 0xb6, 0x7f, 0x0d, 0x8a, 0x26, 0xaa, 0xcb, 0xf5,
+            0xb7, 0x7f, 0x8e, 0x0b, 0xc6, 0x21, 0x37, 0x28, 0xc5, 0x14, 0x05, 0x46, 0x04, 0x0f,
+            0x0e, 0xe3, 0x7f, 0x54,
+        ]
+    );
+    // RFC 4231, Test Case 7
+    define_hmac_calculate_verify_test!(
+        hmac_sha256_rfc4231_test_case_7,
+        hmac_sha2_256_calculate,
+        hmac_sha2_256_verify,
+        HMAC_SHA2_256_SIZE,
+        RFC4231_KEY_6,
+        b"This is a test using a larger than block-size key and a larger than block-size data. The key needs to be hashed before being used by the HMAC algorithm.",
+        [
+            0x9b, 0x09, 0xff, 0xa7, 0x1b, 0x94, 0x2f, 0xcb, 0x27, 0x63, 0x5f, 0xbc, 0xd5, 0xb0,
+            0xe9, 0x44, 0xbf, 0xdc, 0x63, 0x64, 0x4f, 0x07, 0x13, 0x93, 0x8a, 0x7f, 0x51, 0x53,
+            0x5c, 0x3a, 0x35, 0xe2,
+        ]
+    );
     define_hmac_calculate_verify_test!(
         hmac_sha384_calculate_verify_test,
         hmac_sha2_384_calculate,
@@ -350,4 +473,36 @@ mod test {
         hmac_sha3_512_verify,
         HMAC_SHA3_512_SIZE
     );
+
+    // RFC 7914, section 11
+    #[test]
+    fn test_pbkdf2_hmac_sha2_256() {
+        let mut okm = [0u8; 64];
+        pbkdf2_hmac_sha2_256(
+            b"passwd",
+            b"salt",
+            NonZeroU32::new(1).expect("zero iterations"),
+            &mut okm,
+        );
+        let expected = hex::decode(concat!(
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc",
+            "49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783"
+        ))
+        .expect("Failed to decode hex string");
+        assert_eq!(okm, expected.as_slice());
+
+        let mut okm = [0u8; 64];
+        pbkdf2_hmac_sha2_256(
+            b"Password",
+            b"NaCl",
+            NonZeroU32::new(80000).expect("zero iterations"),
+            &mut okm,
+        );
+        let expected = hex::decode(concat!(
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56",
+            "a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d"
+        ))
+        .expect("Failed to decode hex string");
+        assert_eq!(okm, expected.as_slice());
+    }
 }
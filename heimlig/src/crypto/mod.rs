@@ -5,12 +5,17 @@ pub mod ecdh;
 pub mod ecdsa;
 pub mod ed25519;
 pub mod hash;
+pub mod hkdf;
 pub mod hmac;
+pub mod pkcs8;
 pub mod rng;
 pub mod x25519;
 
+use ghash::universal_hash::{consts::U16, UniversalHash};
+use zeroize::Zeroize;
+
 /// Common errors.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::EnumCount)]
 pub enum Error {
     /// Error during encryption.
     Encrypt,
@@ -40,6 +45,54 @@ pub enum Error {
     InvalidSignature,
     /// Invalid size of the digest.
     InvalidDigestSize,
+    /// Malformed signature encoding, e.g. invalid DER.
+    InvalidSignatureEncoding,
+    /// Public key of small order that would lead to a predictable shared secret.
+    WeakPublicKey,
+    /// Integrity check of unwrapped key data failed.
+    IntegrityCheckFailed,
+    /// Length prefix does not match the length of the data it describes.
+    LengthMismatch,
+    /// The random number generator has to be reseeded before generating more output.
+    ReseedRequired,
+    /// Entropy source failed a health test.
+    EntropyFailure,
+}
+
+/// Feed the concatenation of `segments` to `mac` and zero-pad the last incomplete block.
+///
+/// Returns the combined size of all segments in bytes.
+fn update_padded_segments<M, T>(mac: &mut M, segments: &[T]) -> u64
+where
+    M: UniversalHash<BlockSize = U16>,
+    T: AsRef<[u8]>,
+{
+    const BLOCK_SIZE: usize = 16;
+    let mut block = [0u8; BLOCK_SIZE];
+    let mut block_size = 0;
+    let mut total_size = 0u64;
+    for segment in segments {
+        let mut segment = segment.as_ref();
+        total_size += segment.len() as u64;
+        if block_size > 0 {
+            let size = segment.len().min(BLOCK_SIZE - block_size);
+            block[block_size..block_size + size].copy_from_slice(&segment[..size]);
+            block_size += size;
+            segment = &segment[size..];
+            if block_size < BLOCK_SIZE {
+                continue;
+            }
+            mac.update(&[block.into()]);
+        }
+        let full_blocks_size = segment.len() - segment.len() % BLOCK_SIZE;
+        mac.update_padded(&segment[..full_blocks_size]);
+        let rest = &segment[full_blocks_size..];
+        block[..rest.len()].copy_from_slice(rest);
+        block_size = rest.len();
+    }
+    mac.update_padded(&block[..block_size]);
+    block.zeroize();
+    total_size
 }
 
 /// Validation of key and initialization vector/nonce sizes.
@@ -0,0 +1,276 @@
+use crate::crypto::ecdsa::NIST_P256_PRIVATE_KEY_SIZE;
+use crate::crypto::Error;
+use core::num::NonZeroU32;
+use elliptic_curve::SecretKey;
+use p256::NistP256;
+use pkcs8::{
+    der::{asn1::ObjectIdentifier, Encode},
+    pkcs5::{pbes2, EncryptionScheme},
+    AlgorithmIdentifierRef, EncryptedPrivateKeyInfo, PrivateKeyInfo,
+};
+use sec1::EcPrivateKey;
+use zeroize::Zeroizing;
+
+/// Maximum size of the PBKDF2 salt.
+pub const PKCS8_MAX_SALT_SIZE: usize = 64;
+
+/// Maximum PBKDF2 iteration count.
+pub const PKCS8_MAX_ITERATIONS: u32 = pbes2::Pbkdf2Params::MAX_ITERATION_COUNT;
+
+/// Size of the AES-256-CBC initialization vector.
+pub const PKCS8_IV_SIZE: usize = 16;
+
+/// Maximum size of a NIST P-256 EncryptedPrivateKeyInfo structure, reached with a salt of
+/// `PKCS8_MAX_SALT_SIZE` bytes.
+pub const NIST_P256_ENCRYPTED_PKCS8_MAX_SIZE: usize = 234;
+
+/// id-ecPublicKey (RFC 5480).
+const OID_EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+/// prime256v1 (RFC 5480).
+const OID_PRIME256V1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
+/// Size of the DER encoded ECPrivateKey (RFC 5915) of a NIST P-256 private key.
+const NIST_P256_EC_PRIVATE_KEY_SIZE: usize = 39;
+
+/// Size of the DER encoded PrivateKeyInfo of a NIST P-256 private key after PKCS#7 padding.
+const NIST_P256_ENCRYPTED_DATA_SIZE: usize = 80;
+
+/// Encode a NIST P-256 private key as PKCS#8 EncryptedPrivateKeyInfo (RFC 5958).
+///
+/// The key is encrypted with PBES2 (RFC 8018) using PBKDF2 with HMAC-SHA-256 and AES-256-CBC,
+/// which is the default of `openssl pkcs8 -topk8`. Salt, iteration count and IV are included in
+/// the structure so that any PKCS#8 implementation can decrypt it with the password.
+///
+/// # Arguments
+///
+/// * `private_key`: NIST P-256 private key.
+/// * `password`: Password to derive the wrapping key from.
+/// * `salt`: Random PBKDF2 salt of 8 to `PKCS8_MAX_SALT_SIZE` bytes.
+/// * `iterations`: PBKDF2 iteration count of at most `PKCS8_MAX_ITERATIONS`.
+/// * `iv`: Random AES-256-CBC initialization vector.
+/// * `dest`: Output buffer. `NIST_P256_ENCRYPTED_PKCS8_MAX_SIZE` bytes are always sufficient.
+///
+/// returns: DER encoded EncryptedPrivateKeyInfo in the beginning of `dest`.
+///
+/// # Errors
+///
+/// The function returns an error if:
+/// * `InvalidPrivateKey`: `private_key` is not a valid NIST P-256 private key.
+/// * `InvalidIvSize`: `iv` is not `PKCS8_IV_SIZE` bytes long.
+/// * `InvalidBufferSize`: `salt` is too short or too long or `dest` is too small.
+/// * `Encrypt`: `iterations` is larger than `PKCS8_MAX_ITERATIONS`.
+pub fn nist_p256_encrypted_pkcs8<'data>(
+    private_key: &[u8],
+    password: &[u8],
+    salt: &[u8],
+    iterations: NonZeroU32,
+    iv: &[u8],
+    dest: &'data mut [u8],
+) -> Result<&'data [u8], Error> {
+    if private_key.len() != NIST_P256_PRIVATE_KEY_SIZE
+        || SecretKey::<NistP256>::from_slice(private_key).is_err()
+    {
+        return Err(Error::InvalidPrivateKey);
+    }
+    let iv: &[u8; PKCS8_IV_SIZE] = iv.try_into().map_err(|_| Error::InvalidIvSize)?;
+    if !(8..=PKCS8_MAX_SALT_SIZE).contains(&salt.len()) {
+        return Err(Error::InvalidBufferSize);
+    }
+
+    let mut ec_private_key_buffer = Zeroizing::new([0u8; NIST_P256_EC_PRIVATE_KEY_SIZE]);
+    let ec_private_key = EcPrivateKey {
+        private_key,
+        parameters: None,
+        public_key: None,
+    }
+    .encode_to_slice(ec_private_key_buffer.as_mut_slice())
+    .map_err(|_| Error::InvalidBufferSize)?;
+    let algorithm = AlgorithmIdentifierRef {
+        oid: OID_EC_PUBLIC_KEY,
+        parameters: Some((&OID_PRIME256V1).into()),
+    };
+    let mut encrypted_data_buffer = Zeroizing::new([0u8; NIST_P256_ENCRYPTED_DATA_SIZE]);
+    let private_key_info_size = PrivateKeyInfo::new(algorithm, ec_private_key)
+        .encode_to_slice(encrypted_data_buffer.as_mut_slice())
+        .map_err(|_| Error::InvalidBufferSize)?
+        .len();
+
+    let parameters = pbes2::Parameters::pbkdf2_sha256_aes256cbc(iterations.get(), salt, iv)
+        .map_err(|_| Error::Encrypt)?;
+    let encrypted_data = parameters
+        .encrypt_in_place(
+            password,
+            encrypted_data_buffer.as_mut_slice(),
+            private_key_info_size,
+        )
+        .map_err(|_| Error::Encrypt)?;
+    let encoded = EncryptedPrivateKeyInfo {
+        encryption_algorithm: EncryptionScheme::from(parameters),
+        encrypted_data,
+    }
+    .encode_to_slice(dest)
+    .map_err(|_| Error::InvalidBufferSize)?;
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pkcs8::der::Decode;
+
+    const PRIVATE_KEY: [u8; NIST_P256_PRIVATE_KEY_SIZE] = [
+        0xc9, 0xaf, 0xa9, 0xd8, 0x45, 0xba, 0x75, 0x16, 0x6b, 0x5c, 0x21, 0x57, 0x67, 0xb1, 0xd6,
+        0x93, 0x4e, 0x50, 0xc3, 0xdb, 0x36, 0xe8, 0x9b, 0x12, 0x7b, 0x8a, 0x62, 0x2b, 0x12, 0x0f,
+        0x67, 0x21,
+    ];
+    const PASSWORD: &[u8] = b"speak friend and enter";
+    const SALT: [u8; 8] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+    const IV: [u8; PKCS8_IV_SIZE] = [0xa5; PKCS8_IV_SIZE];
+    const ITERATIONS: NonZeroU32 = match NonZeroU32::new(2048) {
+        Some(iterations) => iterations,
+        None => panic!("zero iterations"),
+    };
+
+    /// Decrypt an EncryptedPrivateKeyInfo and return the NIST P-256 private key it contains.
+    fn decrypt_nist_p256_private_key(encoded: &[u8]) -> [u8; NIST_P256_PRIVATE_KEY_SIZE] {
+        let encrypted_private_key_info =
+            EncryptedPrivateKeyInfo::from_der(encoded).expect("failed to decode");
+        let mut buffer = [0u8; 256];
+        let encrypted_data = encrypted_private_key_info.encrypted_data;
+        buffer[..encrypted_data.len()].copy_from_slice(encrypted_data);
+        let private_key_info = encrypted_private_key_info
+            .encryption_algorithm
+            .decrypt_in_place(PASSWORD, &mut buffer[..encrypted_data.len()])
+            .expect("failed to decrypt");
+        let private_key_info =
+            PrivateKeyInfo::from_der(private_key_info).expect("failed to decode");
+        assert_eq!(private_key_info.algorithm.oid, OID_EC_PUBLIC_KEY);
+        assert_eq!(
+            private_key_info
+                .algorithm
+                .parameters_oid()
+                .expect("missing curve"),
+            OID_PRIME256V1
+        );
+        let ec_private_key =
+            EcPrivateKey::from_der(private_key_info.private_key).expect("failed to decode");
+        ec_private_key
+            .private_key
+            .try_into()
+            .expect("invalid private key size")
+    }
+
+    #[test]
+    fn test_nist_p256_encrypted_pkcs8() {
+        let mut dest = [0u8; NIST_P256_ENCRYPTED_PKCS8_MAX_SIZE];
+        let encoded =
+            nist_p256_encrypted_pkcs8(&PRIVATE_KEY, PASSWORD, &SALT, ITERATIONS, &IV, &mut dest)
+                .expect("failed to encode private key");
+        let expected_header = hex::decode(concat!(
+            "3081ab",                               // EncryptedPrivateKeyInfo
+            "3057",                                 // encryptionAlgorithm
+            "06092a864886f70d01050d",               // PBES2
+            "304a",                                 // PBES2-params
+            "3029",                                 // keyDerivationFunc
+            "06092a864886f70d01050c",               // PBKDF2
+            "301c",                                 // PBKDF2-params
+            "04080102030405060708",                 // salt
+            "02020800",                             // iterationCount
+            "300c06082a864886f70d02090500",         // hmacWithSHA256
+            "301d",                                 // encryptionScheme
+            "060960864801650304012a",               // aes256-CBC
+            "0410a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5", // IV
+            "0450",                                 // encryptedData
+        ))
+        .expect("Failed to decode hex string");
+        assert_eq!(encoded[..expected_header.len()], expected_header);
+        assert_eq!(decrypt_nist_p256_private_key(encoded), PRIVATE_KEY);
+
+        // Largest encoding, skipping the expensive key derivation
+        let parameters = pbes2::Parameters::pbkdf2_sha256_aes256cbc(
+            PKCS8_MAX_ITERATIONS,
+            &[0xff; PKCS8_MAX_SALT_SIZE],
+            &IV,
+        )
+        .expect("invalid parameters");
+        let max_size = EncryptedPrivateKeyInfo {
+            encryption_algorithm: parameters.into(),
+            encrypted_data: &[0u8; NIST_P256_ENCRYPTED_DATA_SIZE],
+        }
+        .encoded_len()
+        .expect("failed to calculate size");
+        assert_eq!(
+            usize::try_from(max_size).expect("invalid size"),
+            NIST_P256_ENCRYPTED_PKCS8_MAX_SIZE
+        );
+    }
+
+    #[test]
+    fn test_nist_p256_encrypted_pkcs8_openssl() {
+        // `openssl pkcs8 -topk8 -v2 aes-256-cbc -v2prf hmacWithSHA256 -iter 2048 -outform DER`
+        // of PRIVATE_KEY with PASSWORD. OpenSSL also includes the public key.
+        let encoded = hex::decode(concat!(
+            "3081f4305f06092a864886f70d01050d3052303106092a864886f70d01050c3024041004b63ea52a8787e2",
+            "9aa6fcf9c8b9c98602020800300c06082a864886f70d02090500301d060960864801650304012a04103c57",
+            "ac2020b6fbfc758dc034e58bb836048190ca4de1bd3d3ce25adeaa454d0fc923d82f6a7d602d100c2a679e",
+            "1f568aea05e0e4232b2cd1f601d98b1cdce2ca170566c8b3599709da05ad016e0e93bc51dce964eb04ffb6",
+            "4ed451efa17a96d576e429ec47e7e2de220ab6efefa2a41b95dd5323ddd757457e04ed672e544bc7b724f7",
+            "0d6ffc3842056ea095437c3effa7587c26bb9ca62e18801bc3be6f7b3ca4e311",
+        ))
+        .expect("Failed to decode hex string");
+        assert_eq!(decrypt_nist_p256_private_key(&encoded), PRIVATE_KEY);
+    }
+
+    #[test]
+    fn test_nist_p256_encrypted_pkcs8_errors() {
+        let mut dest = [0u8; NIST_P256_ENCRYPTED_PKCS8_MAX_SIZE];
+        assert_eq!(
+            nist_p256_encrypted_pkcs8(&[0u8; 32], PASSWORD, &SALT, ITERATIONS, &IV, &mut dest),
+            Err(Error::InvalidPrivateKey)
+        );
+        assert_eq!(
+            nist_p256_encrypted_pkcs8(
+                &PRIVATE_KEY,
+                PASSWORD,
+                &SALT,
+                ITERATIONS,
+                &IV[1..],
+                &mut dest
+            ),
+            Err(Error::InvalidIvSize)
+        );
+        assert_eq!(
+            nist_p256_encrypted_pkcs8(
+                &PRIVATE_KEY,
+                PASSWORD,
+                &SALT[1..],
+                ITERATIONS,
+                &IV,
+                &mut dest
+            ),
+            Err(Error::InvalidBufferSize)
+        );
+        assert_eq!(
+            nist_p256_encrypted_pkcs8(
+                &PRIVATE_KEY,
+                PASSWORD,
+                &SALT,
+                ITERATIONS,
+                &IV,
+                &mut dest[..100]
+            ),
+            Err(Error::InvalidBufferSize)
+        );
+        assert_eq!(
+            nist_p256_encrypted_pkcs8(
+                &PRIVATE_KEY,
+                PASSWORD,
+                &SALT,
+                NonZeroU32::new(PKCS8_MAX_ITERATIONS + 1).expect("zero iterations"),
+                &IV,
+                &mut dest
+            ),
+            Err(Error::Encrypt)
+        );
+    }
+}
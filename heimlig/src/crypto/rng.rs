@@ -1 +1,533 @@
+use crate::crypto::Error;
+use core::num::NonZeroU32;
+use hmac::{Hmac, Mac};
+use rand_chacha::rand_core::{self, CryptoRng, RngCore};
+use sha2::Sha256;
+use zeroize::Zeroize;
 
+/// Generate a fresh random nonce of `N` bytes, e.g. an AES-GCM IV.
+///
+/// Random 96-bit GCM nonces should not be used for more than 2^32 encryptions under the same key.
+pub fn generate_nonce<const N: usize, R: CryptoRng + RngCore>(rng: &mut R) -> [u8; N] {
+    let mut nonce = [0u8; N];
+    rng.fill_bytes(&mut nonce);
+    nonce
+}
+
+/// Output size of the HMAC used by [HmacDrbg].
+const OUTLEN: usize = 32;
+
+/// Maximum number of bytes returned by a single [HmacDrbg::generate] call (2^19 bits).
+pub const HMAC_DRBG_MAX_REQUEST_SIZE: usize = 1 << 16;
+
+/// Maximum number of [HmacDrbg::generate] calls between reseeds.
+pub const HMAC_DRBG_RESEED_INTERVAL: u64 = 1 << 48;
+
+/// HMAC-DRBG with SHA-256 as specified in NIST SP 800-90A Rev. 1, section 10.1.2.
+///
+/// The output only depends on the entropy input, nonce, personalization string and additional
+/// inputs, so identical inputs reproduce the output stream byte for byte. Prediction resistance
+/// is not supported, callers reseed explicitly with [HmacDrbg::reseed].
+///
+/// Implements [RngCore] and [CryptoRng] so that it can back the workers. Filling through
+/// [RngCore] panics if a reseed is required.
+pub struct HmacDrbg {
+    key: [u8; OUTLEN],
+    value: [u8; OUTLEN],
+    reseed_counter: u64,
+}
+
+impl HmacDrbg {
+    /// Instantiate the DRBG. `entropy` should hold at least 32 bytes of full entropy, `nonce` at
+    /// least 16 bytes.
+    pub fn instantiate(entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> Self {
+        let mut drbg = Self {
+            key: [0x00; OUTLEN],
+            value: [0x01; OUTLEN],
+            reseed_counter: 1,
+        };
+        drbg.update(&[entropy, nonce, personalization]);
+        drbg
+    }
+
+    /// Instantiate the DRBG like [HmacDrbg::instantiate] after running `health_tests` on
+    /// `entropy`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `EntropyFailure`: The health tests detected a failure of the noise source.
+    pub fn instantiate_checked(
+        health_tests: &mut EntropyHealthTests,
+        entropy: &[u8],
+        nonce: &[u8],
+        personalization: &[u8],
+    ) -> Result<Self, Error> {
+        health_tests.check(entropy)?;
+        Ok(Self::instantiate(entropy, nonce, personalization))
+    }
+
+    /// Reseed the DRBG with fresh entropy.
+    pub fn reseed(&mut self, entropy: &[u8], additional_input: &[u8]) {
+        self.update(&[entropy, additional_input]);
+        self.reseed_counter = 1;
+    }
+
+    /// Reseed the DRBG like [HmacDrbg::reseed] after running `health_tests` on `entropy`. The
+    /// state is left untouched if the tests fail.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `EntropyFailure`: The health tests detected a failure of the noise source.
+    pub fn reseed_checked(
+        &mut self,
+        health_tests: &mut EntropyHealthTests,
+        entropy: &[u8],
+        additional_input: &[u8],
+    ) -> Result<(), Error> {
+        health_tests.check(entropy)?;
+        self.reseed(entropy, additional_input);
+        Ok(())
+    }
+
+    /// Fill `output` with pseudorandom bytes.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `InvalidBufferSize`: `output` is longer than `HMAC_DRBG_MAX_REQUEST_SIZE` bytes.
+    /// * `ReseedRequired`: `HMAC_DRBG_RESEED_INTERVAL` requests were served since the last
+    ///   (re)seed.
+    pub fn generate(&mut self, output: &mut [u8], additional_input: &[u8]) -> Result<(), Error> {
+        if output.len() > HMAC_DRBG_MAX_REQUEST_SIZE {
+            return Err(Error::InvalidBufferSize);
+        }
+        if self.reseed_counter > HMAC_DRBG_RESEED_INTERVAL {
+            return Err(Error::ReseedRequired);
+        }
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+        for chunk in output.chunks_mut(OUTLEN) {
+            self.value = self.hmac([self.value.as_slice()]);
+            chunk.copy_from_slice(&self.value[..chunk.len()]);
+        }
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+
+    /// Derive an independent generator for the stream identified by `label`.
+    ///
+    /// The child is seeded from output of this generator, generated with `label` as additional
+    /// input, and uses `label` as personalization string. Knowing the state of the child reveals
+    /// nothing about this generator or its other children. Forking advances this generator, so
+    /// generators in the same state produce the same child for the same label.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `ReseedRequired`: This generator has to be reseeded first.
+    pub fn fork(&mut self, label: &[u8]) -> Result<Self, Error> {
+        let mut seed = [0u8; OUTLEN];
+        self.generate(&mut seed, label)?;
+        let child = Self::instantiate(&seed, &[], label);
+        seed.zeroize();
+        Ok(child)
+    }
+
+    fn hmac<'a>(&self, data: impl IntoIterator<Item = &'a [u8]>) -> [u8; OUTLEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key)
+            .expect("HMAC accepts keys of any size");
+        for segment in data {
+            mac.update(segment);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    /// HMAC_DRBG_Update. The provided data is the concatenation of all `data` segments.
+    fn update(&mut self, data: &[&[u8]]) {
+        let provided = data.iter().any(|segment| !segment.is_empty());
+        for round in [[0x00u8], [0x01]] {
+            let prefix: [&[u8]; 2] = [&self.value, &round];
+            self.key = self.hmac(prefix.into_iter().chain(data.iter().copied()));
+            self.value = self.hmac([self.value.as_slice()]);
+            if !provided {
+                break;
+            }
+        }
+    }
+}
+
+impl Drop for HmacDrbg {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.value.zeroize();
+    }
+}
+
+impl RngCore for HmacDrbg {
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .expect("HMAC-DRBG has to be reseeded")
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        for chunk in dest.chunks_mut(HMAC_DRBG_MAX_REQUEST_SIZE) {
+            self.generate(chunk, &[]).map_err(|_| {
+                rand_core::Error::from(
+                    NonZeroU32::new(rand_core::Error::CUSTOM_START).expect("non-zero error code"),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl CryptoRng for HmacDrbg {}
+
+/// Window size of the adaptive proportion test for non-binary samples.
+pub const ADAPTIVE_PROPORTION_WINDOW_SIZE: usize = 512;
+
+/// Continuous health tests for a noise source delivering one sample per byte, as specified in
+/// NIST SP 800-90B, section 4.4.
+///
+/// Seed material should be passed through [EntropyHealthTests::check] before it is used to
+/// (re)seed a random number generator, e.g. with [HmacDrbg::instantiate_checked] and
+/// [HmacDrbg::reseed_checked]. The state is kept across calls, so the tests also trip when a source
+/// gets stuck over several seeds.
+pub struct EntropyHealthTests {
+    repetition_count_cutoff: usize,
+    adaptive_proportion_cutoff: usize,
+    last_sample: Option<u8>,
+    repetition_count: usize,
+    window_first_sample: u8,
+    window_matches: usize,
+    window_samples: usize,
+}
+
+impl EntropyHealthTests {
+    /// Repetition count cutoff for a min-entropy of one bit per sample (`1 + ceil(20 / H)`).
+    pub const DEFAULT_REPETITION_COUNT_CUTOFF: usize = 21;
+    /// Adaptive proportion cutoff for a min-entropy of one bit per sample and a window of
+    /// [ADAPTIVE_PROPORTION_WINDOW_SIZE] samples.
+    pub const DEFAULT_ADAPTIVE_PROPORTION_CUTOFF: usize = 410;
+
+    /// Create health tests with the given cutoffs. Cutoffs are derived from the assessed min-entropy
+    /// of the noise source as described in SP 800-90B, sections 4.4.1 and 4.4.2.
+    pub fn new(repetition_count_cutoff: usize, adaptive_proportion_cutoff: usize) -> Self {
+        Self {
+            repetition_count_cutoff,
+            adaptive_proportion_cutoff,
+            last_sample: None,
+            repetition_count: 0,
+            window_first_sample: 0,
+            window_matches: 0,
+            window_samples: 0,
+        }
+    }
+
+    /// Run the repetition count and the adaptive proportion test on `samples`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if:
+    /// * `EntropyFailure`: One of the tests detected a failure of the noise source.
+    pub fn check(&mut self, samples: &[u8]) -> Result<(), Error> {
+        for &sample in samples {
+            self.repetition_count_test(sample)?;
+            self.adaptive_proportion_test(sample)?;
+        }
+        Ok(())
+    }
+
+    fn repetition_count_test(&mut self, sample: u8) -> Result<(), Error> {
+        if self.last_sample == Some(sample) {
+            self.repetition_count += 1;
+            if self.repetition_count >= self.repetition_count_cutoff {
+                return Err(Error::EntropyFailure);
+            }
+        } else {
+            self.last_sample = Some(sample);
+            self.repetition_count = 1;
+        }
+        Ok(())
+    }
+
+    fn adaptive_proportion_test(&mut self, sample: u8) -> Result<(), Error> {
+        if self.window_samples == 0 {
+            self.window_first_sample = sample;
+            self.window_matches = 1;
+        } else if sample == self.window_first_sample {
+            self.window_matches += 1;
+            if self.window_matches >= self.adaptive_proportion_cutoff {
+                return Err(Error::EntropyFailure);
+            }
+        }
+        self.window_samples = (self.window_samples + 1) % ADAPTIVE_PROPORTION_WINDOW_SIZE;
+        Ok(())
+    }
+}
+
+impl Default for EntropyHealthTests {
+    fn default() -> Self {
+        Self::new(
+            Self::DEFAULT_REPETITION_COUNT_CUTOFF,
+            Self::DEFAULT_ADAPTIVE_PROPORTION_CUTOFF,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::crypto::aes::GCM_IV_SIZE;
+    use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
+
+    #[test]
+    fn test_generate_nonce() {
+        let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+        let nonce1: [u8; GCM_IV_SIZE] = generate_nonce(&mut rng);
+        let nonce2: [u8; GCM_IV_SIZE] = generate_nonce(&mut rng);
+        assert_ne!(nonce1, nonce2);
+        assert_ne!(nonce1, [0u8; GCM_IV_SIZE]);
+    }
+
+    // NIST CAVP HMAC_DRBG.rsp, SHA-256, no prediction resistance, no reseed, COUNT = 0
+    #[test]
+    fn test_hmac_drbg_sha2_256_kat() {
+        let entropy =
+            hex::decode("ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488")
+                .expect("Failed to decode hex string");
+        let nonce =
+            hex::decode("659ba96c601dc69fc902940805ec0ca8").expect("Failed to decode hex string");
+        let expected = hex::decode(concat!(
+            "e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89",
+            "d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc1",
+            "07694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668",
+            "961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8"
+        ))
+        .expect("Failed to decode hex string");
+
+        let mut drbg = HmacDrbg::instantiate(&entropy, &nonce, &[]);
+        let mut output = [0u8; 128];
+        drbg.generate(&mut output, &[]).expect("failed to generate");
+        drbg.generate(&mut output, &[]).expect("failed to generate");
+        assert_eq!(output, expected.as_slice());
+    }
+
+    // NIST CAVP HMAC_DRBG.rsp, SHA-256, no prediction resistance, no reseed,
+    // PersonalizationStringLen = 256, AdditionalInputLen = 256, COUNT = 0
+    #[test]
+    fn test_hmac_drbg_sha2_256_kat_personalization_additional_input() {
+        let entropy =
+            hex::decode("5d3286bc53a258a53ba781e2c4dcd79a790e43bbe0e89fb3eed39086be34174b")
+                .expect("Failed to decode hex string");
+        let nonce =
+            hex::decode("c5422294b7318952ace7055ab7570abf").expect("Failed to decode hex string");
+        let personalization =
+            hex::decode("2dba094d008e150d51c4135bb2f03dcde9cbf3468a12908a1b025c120c985b9d")
+                .expect("Failed to decode hex string");
+        let additional_input1 =
+            hex::decode("793a7ef8f6f0482beac542bb785c10f8b7b406a4de92667ab168ecc2cf7573c6")
+                .expect("Failed to decode hex string");
+        let additional_input2 =
+            hex::decode("2238cdb4e23d629fe0c2a83dd8d5144ce1a6229ef41dabe2a99ff722e510b530")
+                .expect("Failed to decode hex string");
+        let expected = hex::decode(concat!(
+            "d04678198ae7e1aeb435b45291458ffde0891560748b43330eaf866b5a6385e7",
+            "4c6fa5a5a44bdb284d436e98d244018d6acedcdfa2e9f499d8089e4db86ae89a",
+            "6ab2d19cb705e2f048f97fb597f04106a1fa6a1416ad3d859118e079a0c319eb",
+            "95686f4cbcce3b5101c7a0b010ef029c4ef6d06cdfac97efb9773891688c37cf",
+        ))
+        .expect("Failed to decode hex string");
+
+        let mut drbg = HmacDrbg::instantiate(&entropy, &nonce, &personalization);
+        let mut output = [0u8; 128];
+        drbg.generate(&mut output, &additional_input1)
+            .expect("failed to generate");
+        drbg.generate(&mut output, &additional_input2)
+            .expect("failed to generate");
+        assert_eq!(output, expected.as_slice());
+    }
+
+    // NIST CAVP HMAC_DRBG.rsp, SHA-256, no prediction resistance, with reseed,
+    // PersonalizationStringLen = 256, AdditionalInputLen = 256, COUNT = 0
+    #[test]
+    fn test_hmac_drbg_sha2_256_kat_reseed() {
+        let entropy =
+            hex::decode("cdb0d9117cc6dbc9ef9dcb06a97579841d72dc18b2d46a1cb61e314012bdf416")
+                .expect("Failed to decode hex string");
+        let nonce =
+            hex::decode("d0c0d01d156016d0eb6b7e9c7c3c8da8").expect("Failed to decode hex string");
+        let personalization =
+            hex::decode("6f0fb9eab3f9ea7ab0a719bfa879bf0aaed683307fda0c6d73ce018b6e34faaa")
+                .expect("Failed to decode hex string");
+        let entropy_reseed =
+            hex::decode("8ec6f7d5a8e2e88f43986f70b86e050d07c84b931bcf18e601c5a3eee3064c82")
+                .expect("Failed to decode hex string");
+        let additional_input_reseed =
+            hex::decode("1ab4ca9014fa98a55938316de8ba5a68c629b0741bdd058c4d70c91cda5099b3")
+                .expect("Failed to decode hex string");
+        let additional_input1 =
+            hex::decode("16e2d0721b58d839a122852abd3bf2c942a31c84d82fca74211871880d7162ff")
+                .expect("Failed to decode hex string");
+        let additional_input2 =
+            hex::decode("53686f042a7b087d5d2eca0d2a96de131f275ed7151189f7ca52deaa78b79fb2")
+                .expect("Failed to decode hex string");
+        let expected = hex::decode(concat!(
+            "dda04a2ca7b8147af1548f5d086591ca4fd951a345ce52b3cd49d47e84aa31a1",
+            "83e31fbc42a1ff1d95afec7143c8008c97bc2a9c091df0a763848391f68cb4a3",
+            "66ad89857ac725a53b303ddea767be8dc5f605b1b95f6d24c9f06be65a973a08",
+            "9320b3cc42569dcfd4b92b62a993785b0301b3fc452445656fce22664827b88f",
+        ))
+        .expect("Failed to decode hex string");
+
+        let mut drbg = HmacDrbg::instantiate(&entropy, &nonce, &personalization);
+        drbg.reseed(&entropy_reseed, &additional_input_reseed);
+        let mut output = [0u8; 128];
+        drbg.generate(&mut output, &additional_input1)
+            .expect("failed to generate");
+        drbg.generate(&mut output, &additional_input2)
+            .expect("failed to generate");
+        assert_eq!(output, expected.as_slice());
+    }
+
+    #[test]
+    fn test_hmac_drbg_reproducible() {
+        let mut drbg1 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], b"device 1");
+        let mut drbg2 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], b"device 1");
+        let mut drbg3 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], b"device 2");
+        let mut output1 = [0u8; 100];
+        let mut output2 = [0u8; 100];
+        let mut output3 = [0u8; 100];
+        drbg1.fill_bytes(&mut output1);
+        drbg2.fill_bytes(&mut output2);
+        drbg3.fill_bytes(&mut output3);
+        assert_eq!(output1, output2);
+        assert_ne!(output1, output3);
+
+        drbg1.reseed(&[3u8; 32], &[]);
+        drbg1.fill_bytes(&mut output1);
+        drbg2.fill_bytes(&mut output2);
+        assert_ne!(output1, output2);
+
+        let mut too_large = [0u8; HMAC_DRBG_MAX_REQUEST_SIZE + 1];
+        assert_eq!(
+            drbg1.generate(&mut too_large, &[]),
+            Err(Error::InvalidBufferSize)
+        );
+    }
+
+    #[test]
+    fn test_health_tests_constant_source() {
+        let mut health_tests = EntropyHealthTests::default();
+        assert_eq!(health_tests.check(&[0u8; 32]), Err(Error::EntropyFailure));
+
+        // A source getting stuck across several seeds is detected as well
+        let mut health_tests = EntropyHealthTests::default();
+        for _ in 0..2 {
+            health_tests
+                .check(&[0xAA; 10])
+                .expect("tests failed too early");
+        }
+        assert_eq!(health_tests.check(&[0xAA; 10]), Err(Error::EntropyFailure));
+    }
+
+    #[test]
+    fn test_health_tests_biased_source() {
+        // Never repeats a sample, but every other sample is the same
+        let mut health_tests = EntropyHealthTests::default();
+        let mut samples = [0u8; ADAPTIVE_PROPORTION_WINDOW_SIZE];
+        for (i, sample) in samples.iter_mut().enumerate() {
+            *sample = if i % 2 == 0 { 0 } else { i as u8 | 1 };
+        }
+        assert_eq!(health_tests.check(&samples), Ok(()));
+
+        let mut health_tests = EntropyHealthTests::new(
+            EntropyHealthTests::DEFAULT_REPETITION_COUNT_CUTOFF,
+            ADAPTIVE_PROPORTION_WINDOW_SIZE / 4,
+        );
+        assert_eq!(health_tests.check(&samples), Err(Error::EntropyFailure));
+    }
+
+    #[test]
+    fn test_health_tests_random_source() {
+        let mut health_tests = EntropyHealthTests::default();
+        let mut drbg = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], &[]);
+        let mut samples = [0u8; 4 * ADAPTIVE_PROPORTION_WINDOW_SIZE];
+        drbg.fill_bytes(&mut samples);
+        assert_eq!(health_tests.check(&samples), Ok(()));
+    }
+
+    #[test]
+    fn test_hmac_drbg_checked_seeding() {
+        let mut health_tests = EntropyHealthTests::default();
+        assert!(matches!(
+            HmacDrbg::instantiate_checked(&mut health_tests, &[0u8; 32], &[2u8; 16], &[]),
+            Err(Error::EntropyFailure)
+        ));
+
+        let mut seed = [0u8; 32];
+        HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], &[]).fill_bytes(&mut seed);
+        let mut health_tests = EntropyHealthTests::default();
+        let mut drbg = HmacDrbg::instantiate_checked(&mut health_tests, &seed, &[2u8; 16], &[])
+            .expect("failed to instantiate DRBG");
+        let mut reference = HmacDrbg::instantiate(&seed, &[2u8; 16], &[]);
+
+        // A failed reseed must not change the output stream
+        assert_eq!(
+            drbg.reseed_checked(&mut health_tests, &[0xAA; 32], &[]),
+            Err(Error::EntropyFailure)
+        );
+        let mut output = [0u8; 32];
+        let mut expected = [0u8; 32];
+        drbg.fill_bytes(&mut output);
+        reference.fill_bytes(&mut expected);
+        assert_eq!(output, expected);
+
+        let mut health_tests = EntropyHealthTests::default();
+        assert_eq!(drbg.reseed_checked(&mut health_tests, &seed, &[]), Ok(()));
+        reference.reseed(&seed, &[]);
+        drbg.fill_bytes(&mut output);
+        reference.fill_bytes(&mut expected);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_hmac_drbg_fork() {
+        let mut parent1 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], &[]);
+        let mut parent2 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], &[]);
+        let mut parent3 = HmacDrbg::instantiate(&[1u8; 32], &[2u8; 16], &[]);
+        let mut output1 = [0u8; 64];
+        let mut output2 = [0u8; 64];
+        let mut output3 = [0u8; 64];
+        parent1
+            .fork(b"nonces")
+            .expect("failed to fork")
+            .fill_bytes(&mut output1);
+        parent2
+            .fork(b"nonces")
+            .expect("failed to fork")
+            .fill_bytes(&mut output2);
+        parent3
+            .fork(b"keys")
+            .expect("failed to fork")
+            .fill_bytes(&mut output3);
+        assert_eq!(output1, output2);
+        assert_ne!(output1, output3);
+
+        // The parent stream differs from the forked stream
+        parent1.fill_bytes(&mut output3);
+        assert_ne!(output1, output3);
+    }
+}
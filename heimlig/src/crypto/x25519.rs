@@ -24,7 +24,7 @@ fn try_to_array(slice: &[u8]) -> Result<[u8; KEY_SIZE], ()> {
 /// # Arguments
 ///
 /// * `private_key`: A slice containing this peer private key bytes.
-///   The private key has to be `KEY_SIZE` bytes long.
+///   The private key has to be `KEY_SIZE` bytes long. Unclamped keys are clamped implicitly.
 /// * `public_key`: A slice containing the other peer public key bytes.
 ///   The public key has to be `KEY_SIZE` bytes long.
 /// * `shared_secret`: A mutable slice where the computed shared secret will be
@@ -36,6 +36,8 @@ fn try_to_array(slice: &[u8]) -> Result<[u8; KEY_SIZE], ()> {
 /// * `InvalidPrivateKey`: The length of the `private_key` is not `KEY_SIZE` bytes.
 /// * `InvalidPublicKey`: The length of the `public_key` is not `KEY_SIZE` bytes.
 /// * `InvalidBufferSize`: The length of the `shared_secret` is not `KEY_SIZE` bytes.
+/// * `WeakPublicKey`: The `public_key` is a point of small order and the resulting shared secret
+///   would be all zeros, independent of the `private_key`.
 pub fn x25519_calculate_shared_secret(
     private_key: &[u8],
     public_key: &[u8],
@@ -46,7 +48,11 @@ pub fn x25519_calculate_shared_secret(
     let public_key = PublicKey::from(try_to_array(public_key).or(Err(Error::InvalidPublicKey))?);
     check_size(shared_secret).or(Err(Error::InvalidBufferSize))?;
 
-    shared_secret.copy_from_slice(private_key.diffie_hellman(&public_key).as_bytes());
+    let result = private_key.diffie_hellman(&public_key);
+    if !result.was_contributory() {
+        return Err(Error::WeakPublicKey);
+    }
+    shared_secret.copy_from_slice(result.as_bytes());
 
     Ok(())
 }
@@ -56,7 +62,7 @@ pub fn x25519_calculate_shared_secret(
 /// # Arguments
 ///
 /// * `private_key`: A slice containing the private key bytes. The private key has to
-///   be `KEY_SIZE` bytes long.
+///   be `KEY_SIZE` bytes long. Unclamped keys are clamped implicitly.
 /// * `public_key`: A mutable slice where the computed public key will be stored. The
 ///   public key slice length has be `KEY_SIZE` bytes long.
 ///
@@ -118,6 +124,61 @@ mod test {
         assert_eq!(alice_shared_secret, bob_shared_secret);
     }
 
+    // RFC 7748, Section 6.1
+    #[test]
+    fn test_x25519_rfc7748_vectors() {
+        let alice_private_key =
+            hex::decode("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a")
+                .expect("Failed to decode hex string");
+        let alice_public_key =
+            hex::decode("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+                .expect("Failed to decode hex string");
+        let bob_private_key =
+            hex::decode("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb")
+                .expect("Failed to decode hex string");
+        let bob_public_key =
+            hex::decode("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+                .expect("Failed to decode hex string");
+        let expected_shared_secret =
+            hex::decode("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742")
+                .expect("Failed to decode hex string");
+
+        let mut public_key = [0u8; KEY_SIZE];
+        x25519_calculate_public_key(&alice_private_key, &mut public_key)
+            .expect("Alice public key error");
+        assert_eq!(public_key, alice_public_key.as_slice());
+        x25519_calculate_public_key(&bob_private_key, &mut public_key)
+            .expect("Bob public key error");
+        assert_eq!(public_key, bob_public_key.as_slice());
+
+        let mut shared_secret = [0u8; KEY_SIZE];
+        x25519_calculate_shared_secret(&alice_private_key, &bob_public_key, &mut shared_secret)
+            .expect("Alice shared secret error");
+        assert_eq!(shared_secret, expected_shared_secret.as_slice());
+        x25519_calculate_shared_secret(&bob_private_key, &alice_public_key, &mut shared_secret)
+            .expect("Bob shared secret error");
+        assert_eq!(shared_secret, expected_shared_secret.as_slice());
+    }
+
+    #[test]
+    fn test_x25519_weak_public_key() {
+        let mut rng = ChaCha20Rng::from_seed([0u8; 32]);
+        let private_key = StaticSecret::random_from_rng(&mut rng);
+        let mut one = [0u8; KEY_SIZE];
+        one[0] = 1;
+        let mut shared_secret = [0u8; KEY_SIZE];
+        for low_order_point in [[0u8; KEY_SIZE], one] {
+            assert_eq!(
+                x25519_calculate_shared_secret(
+                    private_key.as_bytes(),
+                    &low_order_point,
+                    &mut shared_secret
+                ),
+                Err(Error::WeakPublicKey)
+            );
+        }
+    }
+
     #[test]
     fn test_x25519_errors() {
         const BUFF_SIZE: usize = 64;
@@ -1,14 +1,23 @@
 use crate::common::jobs;
-use crate::common::jobs::{ClientId, Request, RequestId, RequestType, Response};
+use crate::common::jobs::{ClientId, PublicKeyEncoding, Request, RequestId, RequestType, Response};
+use crate::common::version;
+use crate::crypto;
+use crate::crypto::ecdsa::{
+    nist_p256_compress_public_key, nist_p256_decompress_public_key, nist_p384_compress_public_key,
+    nist_p384_decompress_public_key,
+};
 use crate::hsm::keystore;
+use crate::hsm::keystore::{Curve, KeyId, KeyType};
 use core::future::poll_fn;
 use core::ops::DerefMut;
 use core::pin::Pin;
+use core::task::{Context, Poll};
 use embassy_futures::select::select_slice;
 use embassy_sync::blocking_mutex::raw::RawMutex;
 use embassy_sync::mutex::Mutex;
-use futures::{FutureExt, Sink, SinkExt, Stream, StreamExt};
+use futures::{task::noop_waker_ref, FutureExt, Sink, SinkExt, Stream, StreamExt};
 use heapless::Vec;
+use strum::EnumCount;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Error {
@@ -78,6 +87,10 @@ enum Job {
     ProcessOnCore(ClientId),
     /// The incoming request has no worker to handle it
     RespondNoWorkerForRequest(ClientId),
+    /// The core is locked and rejects the incoming request
+    RespondLocked(ClientId),
+    /// The incoming request type is not on the allow-list
+    RespondOperationNotPermitted(ClientId),
 }
 
 // TODO: Can be made configurable once `generic_const_exprs` is stable
@@ -107,6 +120,23 @@ pub struct Core<
     workers: Vec<WorkerChannel<'data, ReqSink, RespSrc, M>, MAX_WORKERS>,
     last_client_id: usize,
     last_worker_id: usize,
+    /// Set after an emergency erase. A locked core rejects all further requests.
+    locked: bool,
+    /// Request types that clients are allowed to send. `None` allows all request types.
+    allowed_req_types: Option<Vec<RequestType, { RequestType::COUNT }>>,
+    /// How decryption failures reported by workers are forwarded to clients.
+    decryption_error_policy: DecryptionErrorPolicy,
+}
+
+/// Determines how decryption and verification failures are reported to clients.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum DecryptionErrorPolicy {
+    /// Report all decryption failures as [jobs::Error::AuthenticationFailed]. Clients can not tell
+    /// whether e.g. the tag or the padding check failed, which prevents padding oracle attacks.
+    #[default]
+    Generic,
+    /// Report the specific cryptographic error.
+    Specific,
 }
 
 struct ClientChannel<
@@ -146,6 +176,8 @@ pub struct Builder<
     key_store: Option<&'keystore Mutex<M, &'keystore mut KeyStore>>,
     clients: Vec<ClientChannel<'data, ReqSrc, RespSink, M>, MAX_CLIENTS>,
     workers: Vec<WorkerChannel<'data, ReqSink, RespSrc, M>, MAX_WORKERS>,
+    allowed_req_types: Option<Vec<RequestType, { RequestType::COUNT }>>,
+    decryption_error_policy: DecryptionErrorPolicy,
 }
 
 impl<
@@ -180,6 +212,8 @@ impl<
             key_store: None,
             clients: Default::default(),
             workers: Default::default(),
+            allowed_req_types: None,
+            decryption_error_policy: DecryptionErrorPolicy::default(),
         }
     }
 
@@ -191,6 +225,29 @@ impl<
         self
     }
 
+    /// Restrict the request types that clients are allowed to send.
+    /// All other requests are rejected with `OperationNotPermitted`. Without an allow-list, all
+    /// request types are allowed.
+    pub fn with_allowed_request_types(mut self, req_types: &[RequestType]) -> Result<Self, Error> {
+        let mut allowed = Vec::new();
+        for req_type in req_types {
+            if !allowed.contains(req_type) {
+                allowed
+                    .push(*req_type)
+                    .map_err(|_| Error::TooManyRequestTypes)?;
+            }
+        }
+        self.allowed_req_types = Some(allowed);
+        Ok(self)
+    }
+
+    /// Set how decryption failures are reported to clients.
+    /// Defaults to [DecryptionErrorPolicy::Generic].
+    pub fn with_decryption_error_policy(mut self, policy: DecryptionErrorPolicy) -> Self {
+        self.decryption_error_policy = policy;
+        self
+    }
+
     pub fn with_client(mut self, requests: ReqSrc, responses: RespSink) -> Result<Self, Error> {
         self.clients
             .push(ClientChannel {
@@ -236,6 +293,9 @@ impl<
             workers: self.workers,
             last_client_id: 0,
             last_worker_id: 0,
+            locked: false,
+            allowed_req_types: self.allowed_req_types,
+            decryption_error_policy: self.decryption_error_policy,
         }
     }
 }
@@ -251,10 +311,36 @@ impl<
         KeyStore: keystore::KeyStore,
     > Core<'data, 'keystore, M, ReqSrc, RespSink, ReqSink, RespSrc, KeyStore>
 {
+    /// Check without waiting whether a client request or a worker response is queued.
+    /// Integrators polling the core can use this to avoid sleeping while work is pending.
+    /// Channels that are currently locked by another task are not considered.
+    pub fn has_pending(&self) -> bool {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let requests_pending = self.clients.iter().any(|client| {
+            client.requests.try_lock().is_ok_and(|mut requests| {
+                matches!(
+                    Pin::new(requests.deref_mut()).poll_peek(&mut cx),
+                    Poll::Ready(Some(_))
+                )
+            })
+        });
+        let responses_pending = self.workers.iter().any(|worker| {
+            worker.responses.try_lock().is_ok_and(|mut responses| {
+                matches!(
+                    Pin::new(responses.deref_mut()).poll_peek(&mut cx),
+                    Poll::Ready(Some(_))
+                )
+            })
+        });
+        requests_pending || responses_pending
+    }
+
     /// Drive the core to process the next client request or forward the next worker response.
     /// This method is supposed to be called by a system task that owns the core.
     pub async fn execute(&mut self) -> Result<(), Error> {
-        match self.next_job().await? {
+        let job = self.next_job().await?;
+        self.rotate_channels(&job);
+        match job {
             Job::ForwardRequest(client_id, worker_id) => {
                 self.forward_request(client_id, worker_id).await
             }
@@ -263,7 +349,33 @@ impl<
             }
             Job::ProcessOnCore(client_id) => self.process_on_core(client_id).await,
             Job::RespondNoWorkerForRequest(client_id) => {
-                self.respond_no_worker_for_request(client_id).await
+                self.respond_with_error(client_id, jobs::Error::NoWorkerForRequest)
+                    .await
+            }
+            Job::RespondLocked(client_id) => {
+                self.respond_with_error(client_id, jobs::Error::Locked)
+                    .await
+            }
+            Job::RespondOperationNotPermitted(client_id) => {
+                self.respond_with_error(client_id, jobs::Error::OperationNotPermitted)
+                    .await
+            }
+        }
+    }
+
+    /// Start with the channels after the ones of `job` when looking for the next job. This serves
+    /// all clients and workers in turn so that a busy channel cannot starve the others.
+    fn rotate_channels(&mut self, job: &Job) {
+        match *job {
+            Job::ForwardResponse(_, worker_id) => {
+                self.last_worker_id = (worker_id.idx() + 1) % self.workers.len();
+            }
+            Job::ForwardRequest(client_id, _)
+            | Job::ProcessOnCore(client_id)
+            | Job::RespondNoWorkerForRequest(client_id)
+            | Job::RespondLocked(client_id)
+            | Job::RespondOperationNotPermitted(client_id) => {
+                self.last_client_id = (client_id.idx() + 1) % self.clients.len();
             }
         }
     }
@@ -308,7 +420,15 @@ impl<
                 .peek()
                 .await
                 .ok_or(Error::StreamTerminated)?;
+            if self.locked {
+                return Ok(Job::RespondLocked(client.id));
+            }
             let request_type = request.get_type();
+            if let Some(allowed_req_types) = &self.allowed_req_types {
+                if !allowed_req_types.contains(&request_type) {
+                    return Ok(Job::RespondOperationNotPermitted(client.id));
+                }
+            }
             if request_type.is_handled_by_core() {
                 return Ok(Job::ProcessOnCore(client.id));
             }
@@ -344,7 +464,7 @@ impl<
         client_id: ClientId,
         worker_id: WorkerId,
     ) -> Result<(), Error> {
-        let response = self
+        let mut response = self
             .workers
             .get(worker_id.idx())
             .ok_or(Error::Internal(InternalError::InvalidWorkerId(worker_id)))?
@@ -364,6 +484,16 @@ impl<
                 response.get_client_id(),
             )));
         }
+        if self.decryption_error_policy == DecryptionErrorPolicy::Generic {
+            if let Response::Error { error, .. } = &mut response {
+                if matches!(
+                    error,
+                    jobs::Error::Crypto(crypto::Error::Decrypt | crypto::Error::InvalidPadding)
+                ) {
+                    *error = jobs::Error::AuthenticationFailed;
+                }
+            }
+        }
         self.send_to_client(response).await
     }
 
@@ -387,6 +517,7 @@ impl<
 
     async fn process_on_core(&mut self, client_id: ClientId) -> Result<(), Error> {
         let request = self.recv_from_client(client_id).await?;
+        let expects_response = request.expects_response();
         let response = match request {
             Request::IsKeyAvailable {
                 client_id,
@@ -436,18 +567,37 @@ impl<
             } => match self.key_store {
                 None => Ok(Self::no_key_store_response(client_id, request_id)),
                 Some(key_store) => {
-                    let result = key_store.lock().await.deref_mut().import_key_pair(
+                    let mut locked_key_store = key_store.lock().await;
+                    let mut decompressed_public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+                    let public_key = decompress_imported_public_key(
+                        *locked_key_store,
                         key_id,
                         public_key,
-                        private_key,
-                        overwrite,
+                        &mut decompressed_public_key,
                     );
-                    match result {
-                        Ok(()) => Ok(Response::ImportKeyPair {
+                    match public_key {
+                        Ok(public_key) => {
+                            let result = locked_key_store.deref_mut().import_key_pair(
+                                key_id,
+                                public_key,
+                                private_key,
+                                overwrite,
+                            );
+                            match result {
+                                Ok(()) => Ok(Response::ImportKeyPair {
+                                    client_id,
+                                    request_id,
+                                }),
+                                Err(e) => {
+                                    Ok(Self::key_store_error_response(client_id, request_id, e))
+                                }
+                            }
+                        }
+                        Err(e) => Ok(Response::Error {
                             client_id,
                             request_id,
+                            error: jobs::Error::Crypto(e),
                         }),
-                        Err(e) => Ok(Self::key_store_error_response(client_id, request_id, e)),
                     }
                 }
             },
@@ -482,26 +632,58 @@ impl<
                 request_id,
                 key_id,
                 public_key,
+                encoding,
             } => match self.key_store {
                 None => Ok(Self::no_key_store_response(client_id, request_id)),
-                Some(key_store) => {
-                    let exported_key = key_store
-                        .lock()
-                        .await
-                        .deref_mut()
-                        .export_public_key(key_id, public_key);
-                    match exported_key {
-                        Ok(written) => {
-                            let exported_key_len = written.len();
-                            Ok(Response::ExportPublicKey {
-                                client_id,
-                                request_id,
-                                public_key: &mut public_key[..exported_key_len],
-                            })
+                Some(key_store) => match encoding {
+                    PublicKeyEncoding::Uncompressed => {
+                        let exported_key = key_store
+                            .lock()
+                            .await
+                            .deref_mut()
+                            .export_public_key(key_id, public_key);
+                        match exported_key {
+                            Ok(written) => {
+                                let exported_key_len = written.len();
+                                Ok(Response::ExportPublicKey {
+                                    client_id,
+                                    request_id,
+                                    public_key: &mut public_key[..exported_key_len],
+                                })
+                            }
+                            Err(e) => Ok(Self::key_store_error_response(client_id, request_id, e)),
                         }
-                        Err(e) => Ok(Self::key_store_error_response(client_id, request_id, e)),
                     }
-                }
+                    PublicKeyEncoding::Compressed => {
+                        let mut uncompressed_public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+                        let exported_key = key_store
+                            .lock()
+                            .await
+                            .deref_mut()
+                            .export_public_key(key_id, &mut uncompressed_public_key)
+                            .map(|written| written.len());
+                        match exported_key {
+                            Ok(exported_key_len) => {
+                                match compress_public_key(
+                                    &uncompressed_public_key[..exported_key_len],
+                                    public_key,
+                                ) {
+                                    Ok(compressed_key_len) => Ok(Response::ExportPublicKey {
+                                        client_id,
+                                        request_id,
+                                        public_key: &mut public_key[..compressed_key_len],
+                                    }),
+                                    Err(e) => Ok(Response::Error {
+                                        client_id,
+                                        request_id,
+                                        error: jobs::Error::Crypto(e),
+                                    }),
+                                }
+                            }
+                            Err(e) => Ok(Self::key_store_error_response(client_id, request_id, e)),
+                        }
+                    }
+                },
             },
             Request::ExportPrivateKey {
                 client_id,
@@ -529,20 +711,98 @@ impl<
                     }
                 }
             },
+            Request::ListKeys {
+                client_id,
+                request_id,
+                start,
+                ids,
+            } => match self.key_store {
+                None => Ok(Self::no_key_store_response(client_id, request_id)),
+                // An empty page could never make progress
+                Some(_) if ids.is_empty() => Ok(Self::key_store_error_response(
+                    client_id,
+                    request_id,
+                    keystore::Error::InvalidBufferSize,
+                )),
+                Some(key_store) => {
+                    let key_store = key_store.lock().await;
+                    let mut count = 0;
+                    let mut next = None;
+                    for info in key_store.iter_metadata() {
+                        if info.id >= start && key_store.is_key_available(info.id) {
+                            if count == ids.len() {
+                                // Page is full, continue with this key in the next request
+                                next = Some(info.id);
+                                break;
+                            }
+                            ids[count] = info.id;
+                            count += 1;
+                        }
+                    }
+                    Ok(Response::KeyList {
+                        client_id,
+                        request_id,
+                        ids: &mut ids[..count],
+                        next,
+                    })
+                }
+            },
+            Request::EmergencyErase {
+                client_id,
+                request_id,
+            } => {
+                if let Some(key_store) = self.key_store {
+                    key_store.lock().await.deref_mut().erase_all();
+                }
+                self.locked = true;
+                Ok(Response::EmergencyErase {
+                    client_id,
+                    request_id,
+                })
+            }
+            Request::Version {
+                client_id,
+                request_id,
+            } => Ok(Response::Version {
+                client_id,
+                request_id,
+                crate_version: version::CRATE_VERSION,
+                protocol_version: version::PROTOCOL_VERSION,
+                features: version::FEATURES,
+            }),
+            Request::AeadOutputLen {
+                client_id,
+                request_id,
+                algorithm,
+                plaintext_len,
+            } => Ok(Response::AeadOutputLen {
+                client_id,
+                request_id,
+                // Encryption is done in place, the tag is returned separately
+                ciphertext_len: plaintext_len,
+                tag_len: algorithm.tag_size(),
+            }),
             _ => Err(Error::Internal(InternalError::UnexpectedCoreRequest(
                 request.get_type(),
             ))),
         }?;
+        if !response.is_sent(expects_response) {
+            return Ok(());
+        }
         self.send_to_client(response).await
     }
 
-    async fn respond_no_worker_for_request(&mut self, client_id: ClientId) -> Result<(), Error> {
+    async fn respond_with_error(
+        &mut self,
+        client_id: ClientId,
+        error: jobs::Error,
+    ) -> Result<(), Error> {
         // Remove request from queue even though we cannot handle it
         let request = self.recv_from_client(client_id).await?;
         let response = Response::Error {
             client_id,
             request_id: request.get_request_id(),
-            error: jobs::Error::NoWorkerForRequest,
+            error,
         };
         self.send_to_client(response).await
     }
@@ -602,3 +862,47 @@ impl<
         }
     }
 }
+
+/// Compress an uncompressed public key into `dest`. The curve is determined by the size of
+/// `public_key`. Returns the size of the compressed public key.
+fn compress_public_key(public_key: &[u8], dest: &mut [u8]) -> Result<usize, crypto::Error> {
+    let curve = if public_key.len() == KeyType::Asymmetric(Curve::NistP256).public_key_size() {
+        Curve::NistP256
+    } else {
+        Curve::NistP384
+    };
+    let size = KeyType::Asymmetric(curve).compressed_public_key_size();
+    if dest.len() < size {
+        return Err(crypto::Error::InvalidBufferSize);
+    }
+    match curve {
+        Curve::NistP256 => nist_p256_compress_public_key(public_key, &mut dest[..size]),
+        Curve::NistP384 => nist_p384_compress_public_key(public_key, &mut dest[..size]),
+    }?;
+    Ok(size)
+}
+
+/// Public keys to import may be given in compressed form. In that case, the public key is
+/// decompressed into `buffer`. Otherwise, `public_key` is returned unchanged and checked by the key
+/// store on import.
+fn decompress_imported_public_key<'a>(
+    key_store: &impl keystore::KeyStore,
+    key_id: KeyId,
+    public_key: &'a [u8],
+    buffer: &'a mut [u8; KeyType::MAX_PUBLIC_KEY_SIZE],
+) -> Result<&'a [u8], crypto::Error> {
+    match keystore::KeyStore::get_key_info(key_store, key_id) {
+        Ok(key_info) if public_key.len() == key_info.ty.compressed_public_key_size() => {
+            let KeyType::Asymmetric(curve) = key_info.ty else {
+                return Ok(public_key);
+            };
+            let buffer = &mut buffer[..key_info.ty.public_key_size()];
+            match curve {
+                Curve::NistP256 => nist_p256_decompress_public_key(public_key, buffer),
+                Curve::NistP384 => nist_p384_decompress_public_key(public_key, buffer),
+            }?;
+            Ok(buffer)
+        }
+        _ => Ok(public_key),
+    }
+}
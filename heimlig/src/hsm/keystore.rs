@@ -1,8 +1,9 @@
 /// Identifier to reference HSM keys
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[repr(transparent)]
 pub struct KeyId(pub u32);
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, strum::EnumCount)]
 pub enum Error {
     /// The operation is not permitted
     NotAllowed,
@@ -85,6 +86,16 @@ impl KeyType {
         }
     }
 
+    /// Size of a public key in SEC1 compressed point form.
+    pub const fn compressed_public_key_size(&self) -> usize {
+        match self {
+            KeyType::Asymmetric(c) => match c {
+                Curve::NistP256 | Curve::NistP384 => c.size() + 1,
+            },
+            _ => 0,
+        }
+    }
+
     pub const fn private_key_size(&self) -> usize {
         match self {
             KeyType::Asymmetric(c) => match c {
@@ -116,6 +127,11 @@ impl KeyType {
 pub trait InsecureKeyStore {
     fn get_key_info(&self, id: KeyId) -> Result<KeyInfo, Error>;
 
+    /// Get the info of the key slot with the next larger ID than `id`, or of the key slot with the
+    /// smallest ID if `id` is `None`. Used to iterate over all key slots, whether a key is stored
+    /// in them or not.
+    fn next_key_info(&self, id: Option<KeyId>) -> Option<KeyInfo>;
+
     /// Write a symmetric key to storage.
     ///
     /// Unlike `import_symmetric_key()`, this function imports keys even if their permissions do not
@@ -172,6 +188,9 @@ pub trait InsecureKeyStore {
     /// return: An error, if the key could not be found.
     fn delete_insecure(&mut self, id: KeyId) -> Result<(), Error>;
 
+    /// Zeroize all keys in the store regardless of their permissions.
+    fn erase_all_insecure(&mut self);
+
     /// Returns whether a key for the given 'id' is present in the store.
     fn is_key_available(&self, id: KeyId) -> bool;
 
@@ -182,6 +201,11 @@ pub trait InsecureKeyStore {
 pub trait KeyStore {
     fn get_key_info(&self, id: KeyId) -> Result<KeyInfo, Error>;
 
+    /// Get the info of the key slot with the next larger ID than `id`, or of the key slot with the
+    /// smallest ID if `id` is `None`. Used to iterate over all key slots, whether a key is stored
+    /// in them or not.
+    fn next_key_info(&self, id: Option<KeyId>) -> Option<KeyInfo>;
+
     /// Write a symmetric key to storage.
     fn import_symmetric_key(
         &mut self,
@@ -231,11 +255,44 @@ pub trait KeyStore {
     /// return: An error, if the key could not be found.
     fn delete(&mut self, id: KeyId) -> Result<(), Error>;
 
+    /// Zeroize all keys in the store.
+    ///
+    /// This is a last-resort security control and ignores the `delete` permission of the keys.
+    fn erase_all(&mut self);
+
     /// Returns whether a key for the given 'id' is present in the store.
     fn is_key_available(&self, id: KeyId) -> bool;
 
     /// Get the size of a key.
     fn size(&self, id: KeyId) -> Result<usize, Error>;
+
+    /// Iterate over the infos of all key slots in ascending ID order, whether a key is stored in
+    /// them or not. Only metadata is yielded, never key material.
+    fn iter_metadata(&self) -> KeyInfoIter<'_, Self>
+    where
+        Self: Sized,
+    {
+        KeyInfoIter {
+            key_store: self,
+            next: self.next_key_info(None),
+        }
+    }
+}
+
+/// Iterator over the key infos of a [KeyStore]. Created by [KeyStore::iter_metadata].
+pub struct KeyInfoIter<'a, K: KeyStore> {
+    key_store: &'a K,
+    next: Option<KeyInfo>,
+}
+
+impl<K: KeyStore> Iterator for KeyInfoIter<'_, K> {
+    type Item = KeyInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let key_info = self.next?;
+        self.next = self.key_store.next_key_info(Some(key_info.id));
+        Some(key_info)
+    }
 }
 
 /// Blanket implementation for `InsecureKeyStore` to be used as `KeyStore`, by applying permission
@@ -245,6 +302,10 @@ impl<T: InsecureKeyStore> KeyStore for T {
         self.get_key_info(id)
     }
 
+    fn next_key_info(&self, id: Option<KeyId>) -> Option<KeyInfo> {
+        self.next_key_info(id)
+    }
+
     fn import_symmetric_key(
         &mut self,
         id: KeyId,
@@ -338,6 +399,11 @@ impl<T: InsecureKeyStore> KeyStore for T {
         }
         self.delete_insecure(id)
     }
+
+    fn erase_all(&mut self) {
+        self.erase_all_insecure()
+    }
+
     fn is_key_available(&self, id: KeyId) -> bool {
         self.is_key_available(id)
     }
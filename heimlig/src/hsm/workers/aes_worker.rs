@@ -1,5 +1,5 @@
 use crate::{
-    common::jobs::{ClientId, Error, Request, RequestId, Response},
+    common::jobs::{CbcPadding, ClientId, Error, Request, RequestId, Response},
     crypto::{
         self,
         aes::{
@@ -15,24 +15,51 @@ use crate::{
                 aes128gcm_decrypt_in_place_detached, aes128gcm_encrypt_in_place_detached,
                 aes256gcm_decrypt_in_place_detached, aes256gcm_encrypt_in_place_detached,
             },
-            KEY128_SIZE, KEY192_SIZE, KEY256_SIZE,
+            GCM_IV_SIZE, GCM_TAG_SIZE, KEY128_SIZE, KEY192_SIZE, KEY256_SIZE,
         },
+        ecdsa::{nist_p256_public_key_from_private_key, nist_p384_public_key_from_private_key},
+        rng::generate_nonce,
+    },
+    hsm::{
+        keystore::{self, Curve, KeyId, KeyInfo, KeyType},
+        workers::export_mac_key_and_key_info,
     },
-    hsm::keystore::{self, KeyId, KeyInfo, KeyType},
 };
-use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::block_padding::{Iso7816, NoPadding, Pkcs7};
 use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
 use futures::{Sink, SinkExt, Stream, StreamExt};
-use zeroize::Zeroizing;
+use rand_chacha::rand_core::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Call an AES-CBC function with the padding type selected by a `CbcPadding` value.
+macro_rules! with_padding {
+    ($padding:expr, $f:ident($($arg:expr),*)) => {
+        match $padding {
+            CbcPadding::Pkcs7 => $f::<Pkcs7>($($arg),*),
+            CbcPadding::Iso7816 => $f::<Iso7816>($($arg),*),
+            CbcPadding::NoPadding => $f::<NoPadding>($($arg),*),
+        }
+    };
+}
+
+/// Associated data authenticated with every key store backup.
+const BACKUP_AAD: &[u8] = b"heimlig keystore backup";
+
+/// Size of the key ID (`u32`) and key size (`u16`) preceding every key in a backup.
+const BACKUP_RECORD_HEADER_SIZE: usize = 6;
 
 pub struct AesWorker<
     'data,
+    'rng,
     'keystore,
     M: RawMutex,
+    R: CryptoRng + RngCore,
     ReqSrc: Stream<Item = Request<'data>>,
     RespSink: Sink<Response<'data>>,
     KeyStore: keystore::KeyStore + keystore::InsecureKeyStore + Send,
 > {
+    /// Source of the nonces generated by the HSM.
+    pub rng: &'rng Mutex<M, R>,
     pub key_store: &'keystore Mutex<M, &'keystore mut KeyStore>,
     pub requests: ReqSrc,
     pub responses: RespSink,
@@ -43,10 +70,11 @@ impl<
         'rng,
         'keystore,
         M: RawMutex,
+        R: CryptoRng + RngCore,
         ReqSrc: Stream<Item = Request<'data>> + Unpin,
         RespSink: Sink<Response<'data>> + Unpin,
         KeyStore: keystore::KeyStore + keystore::InsecureKeyStore + Send,
-    > AesWorker<'data, 'keystore, M, ReqSrc, RespSink, KeyStore>
+    > AesWorker<'data, 'rng, 'keystore, M, R, ReqSrc, RespSink, KeyStore>
 {
     /// Drive the worker to process the next request.
     /// This method is supposed to be called by a system task that owns this worker.
@@ -65,6 +93,20 @@ impl<
                 self.encrypt_aes_gcm(client_id, request_id, key_id, iv, buffer, aad, tag)
                     .await
             }
+            Request::EncryptAesGcmRandomNonce {
+                client_id,
+                request_id,
+                key_id,
+                iv,
+                buffer,
+                aad,
+                tag,
+            } => {
+                self.encrypt_aes_gcm_random_nonce(
+                    client_id, request_id, key_id, iv, buffer, aad, tag,
+                )
+                .await
+            }
             Request::EncryptAesGcmExternalKey {
                 client_id,
                 request_id,
@@ -107,10 +149,19 @@ impl<
                 key_id,
                 iv,
                 buffer,
+                padding,
                 plaintext_size,
             } => {
-                self.encrypt_aes_cbc(client_id, request_id, key_id, iv, buffer, plaintext_size)
-                    .await
+                self.encrypt_aes_cbc(
+                    client_id,
+                    request_id,
+                    key_id,
+                    iv,
+                    buffer,
+                    padding,
+                    plaintext_size,
+                )
+                .await
             }
             Request::EncryptAesCbcExternalKey {
                 client_id,
@@ -118,6 +169,7 @@ impl<
                 key,
                 iv,
                 buffer,
+                padding,
                 plaintext_size,
             } => {
                 self.encrypt_aes_cbc_external_key(
@@ -126,6 +178,7 @@ impl<
                     key,
                     iv,
                     buffer,
+                    padding,
                     plaintext_size,
                 )
                 .await
@@ -136,8 +189,9 @@ impl<
                 key_id,
                 iv,
                 buffer,
+                padding,
             } => {
-                self.decrypt_aes_cbc(client_id, request_id, key_id, iv, buffer)
+                self.decrypt_aes_cbc(client_id, request_id, key_id, iv, buffer, padding)
                     .await
             }
             Request::DecryptAesCbcExternalKey {
@@ -146,8 +200,9 @@ impl<
                 key,
                 iv,
                 buffer,
+                padding,
             } => {
-                self.decrypt_aes_cbc_external_key(client_id, request_id, key, iv, buffer)
+                self.decrypt_aes_cbc_external_key(client_id, request_id, key, iv, buffer, padding)
                     .await
             }
             Request::CalculateAesCmac {
@@ -155,9 +210,10 @@ impl<
                 request_id,
                 key_id,
                 message,
+                message_type,
                 tag,
             } => {
-                self.calculate_aes_cmac(client_id, request_id, key_id, message, tag)
+                self.calculate_aes_cmac(client_id, request_id, key_id, message, message_type, tag)
                     .await
             }
             Request::CalculateAesCmacExternalKey {
@@ -175,9 +231,10 @@ impl<
                 request_id,
                 key_id,
                 message,
+                message_type,
                 tag,
             } => {
-                self.verify_aes_cmac(client_id, request_id, key_id, message, tag)
+                self.verify_aes_cmac(client_id, request_id, key_id, message, message_type, tag)
                     .await
             }
             Request::VerifyAesCmacExternalKey {
@@ -190,6 +247,25 @@ impl<
                 self.verify_aes_cmac_external_key(client_id, request_id, key, message, tag)
                     .await
             }
+            Request::BackupKeystore {
+                client_id,
+                request_id,
+                kek_id,
+                iv,
+                blob,
+            } => {
+                self.backup_keystore(client_id, request_id, kek_id, iv, blob)
+                    .await
+            }
+            Request::RestoreKeystore {
+                client_id,
+                request_id,
+                kek_id,
+                blob,
+            } => {
+                self.restore_keystore(client_id, request_id, kek_id, blob)
+                    .await
+            }
             _ => Err(Error::UnexpectedRequestType)?,
         };
         self.responses
@@ -252,6 +328,47 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn encrypt_aes_gcm_random_nonce(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        iv: &'data mut [u8],
+        buffer: &'data mut [u8],
+        aad: &[u8],
+        tag: &'data mut [u8],
+    ) -> Response<'data> {
+        if iv.len() != GCM_IV_SIZE {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(crypto::Error::InvalidIvSize),
+            };
+        }
+        iv.copy_from_slice(&generate_nonce::<GCM_IV_SIZE, _>(
+            &mut *self.rng.lock().await,
+        ));
+        match self
+            .encrypt_aes_gcm(client_id, request_id, key_id, iv, buffer, aad, tag)
+            .await
+        {
+            Response::EncryptAesGcm {
+                client_id,
+                request_id,
+                buffer,
+                tag,
+            } => Response::EncryptAesGcmRandomNonce {
+                client_id,
+                request_id,
+                iv,
+                buffer,
+                tag,
+            },
+            response => response,
+        }
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn encrypt_aes_gcm_external_key(
         &mut self,
@@ -378,6 +495,7 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn encrypt_aes_cbc(
         &mut self,
         client_id: ClientId,
@@ -385,6 +503,7 @@ impl<
         key_id: KeyId,
         iv: &[u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
         plaintext_size: usize,
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
@@ -401,13 +520,13 @@ impl<
             }
             Ok((key, key_info)) => match key_info.ty {
                 KeyType::Symmetric(16) => {
-                    aes128cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size)
+                    with_padding!(padding, aes128cbc_encrypt(key, iv, buffer, plaintext_size))
                 }
                 KeyType::Symmetric(24) => {
-                    aes192cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size)
+                    with_padding!(padding, aes192cbc_encrypt(key, iv, buffer, plaintext_size))
                 }
                 KeyType::Symmetric(32) => {
-                    aes256cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size)
+                    with_padding!(padding, aes256cbc_encrypt(key, iv, buffer, plaintext_size))
                 }
                 _ => {
                     return Response::Error {
@@ -435,6 +554,7 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn encrypt_aes_cbc_external_key(
         &mut self,
         client_id: ClientId,
@@ -442,12 +562,19 @@ impl<
         key: &[u8],
         iv: &[u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
         plaintext_size: usize,
     ) -> Response<'data> {
         let result = match key.len() {
-            KEY128_SIZE => aes128cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size),
-            KEY192_SIZE => aes192cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size),
-            KEY256_SIZE => aes256cbc_encrypt::<Pkcs7>(key, iv, buffer, plaintext_size),
+            KEY128_SIZE => {
+                with_padding!(padding, aes128cbc_encrypt(key, iv, buffer, plaintext_size))
+            }
+            KEY192_SIZE => {
+                with_padding!(padding, aes192cbc_encrypt(key, iv, buffer, plaintext_size))
+            }
+            KEY256_SIZE => {
+                with_padding!(padding, aes256cbc_encrypt(key, iv, buffer, plaintext_size))
+            }
             _ => {
                 return Response::Error {
                     client_id,
@@ -480,6 +607,7 @@ impl<
         key_id: KeyId,
         iv: &[u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
         let key_and_info = self
@@ -494,9 +622,15 @@ impl<
                 }
             }
             Ok((key, key_info)) => match key_info.ty {
-                KeyType::Symmetric(16) => aes128cbc_decrypt::<Pkcs7>(key, iv, buffer),
-                KeyType::Symmetric(24) => aes192cbc_decrypt::<Pkcs7>(key, iv, buffer),
-                KeyType::Symmetric(32) => aes256cbc_decrypt::<Pkcs7>(key, iv, buffer),
+                KeyType::Symmetric(16) => {
+                    with_padding!(padding, aes128cbc_decrypt(key, iv, buffer))
+                }
+                KeyType::Symmetric(24) => {
+                    with_padding!(padding, aes192cbc_decrypt(key, iv, buffer))
+                }
+                KeyType::Symmetric(32) => {
+                    with_padding!(padding, aes256cbc_decrypt(key, iv, buffer))
+                }
                 _ => {
                     return Response::Error {
                         client_id,
@@ -530,11 +664,12 @@ impl<
         key: &[u8],
         iv: &[u8],
         buffer: &'data mut [u8],
+        padding: CbcPadding,
     ) -> Response<'data> {
         let result = match key.len() {
-            KEY128_SIZE => aes128cbc_decrypt::<Pkcs7>(key, iv, buffer),
-            KEY192_SIZE => aes192cbc_decrypt::<Pkcs7>(key, iv, buffer),
-            KEY256_SIZE => aes256cbc_decrypt::<Pkcs7>(key, iv, buffer),
+            KEY128_SIZE => with_padding!(padding, aes128cbc_decrypt(key, iv, buffer)),
+            KEY192_SIZE => with_padding!(padding, aes192cbc_decrypt(key, iv, buffer)),
+            KEY256_SIZE => with_padding!(padding, aes256cbc_decrypt(key, iv, buffer)),
             _ => {
                 return Response::Error {
                     client_id,
@@ -566,12 +701,17 @@ impl<
         request_id: RequestId,
         key_id: KeyId,
         message: &[u8],
+        message_type: &[u8],
         tag: &'data mut [u8],
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
-        let key_and_info = self
-            .export_key_and_key_info(key_id, key_buffer.as_mut_slice())
-            .await;
+        let key_and_info = export_mac_key_and_key_info(
+            self.key_store,
+            key_id,
+            message_type,
+            key_buffer.as_mut_slice(),
+        )
+        .await;
         let result = match key_and_info {
             Err(e) => {
                 return Response::Error {
@@ -647,12 +787,17 @@ impl<
         request_id: RequestId,
         key_id: KeyId,
         message: &[u8],
+        message_type: &[u8],
         tag: &[u8],
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
-        let key_and_info = self
-            .export_key_and_key_info(key_id, key_buffer.as_mut_slice())
-            .await;
+        let key_and_info = export_mac_key_and_key_info(
+            self.key_store,
+            key_id,
+            message_type,
+            key_buffer.as_mut_slice(),
+        )
+        .await;
         let result = match key_and_info {
             Err(e) => {
                 return Response::Error {
@@ -722,6 +867,237 @@ impl<
         }
     }
 
+    async fn backup_keystore(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        kek_id: KeyId,
+        iv: &[u8],
+        blob: &'data mut [u8],
+    ) -> Response<'data> {
+        match self.write_backup(kek_id, iv, blob).await {
+            Err(e) => {
+                blob.zeroize();
+                Response::Error {
+                    client_id,
+                    request_id,
+                    error: e,
+                }
+            }
+            Ok((size, skipped)) => Response::BackupKeystore {
+                client_id,
+                request_id,
+                blob: &mut blob[..size],
+                skipped,
+            },
+        }
+    }
+
+    /// Serialize and encrypt all exportable keys except the KEK into `blob`.
+    ///
+    /// returns: The number of bytes written to `blob` and the number of skipped keys.
+    async fn write_backup(
+        &mut self,
+        kek_id: KeyId,
+        iv: &[u8],
+        blob: &mut [u8],
+    ) -> Result<(usize, u32), Error> {
+        if iv.len() != GCM_IV_SIZE {
+            return Err(Error::Crypto(crypto::Error::InvalidIvSize));
+        }
+        if blob.len() < GCM_IV_SIZE + GCM_TAG_SIZE {
+            return Err(Error::Crypto(crypto::Error::InvalidBufferSize));
+        }
+        let mut kek_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
+        // Lock keystore for the whole backup to get a consistent snapshot
+        let locked_key_store = self.key_store.lock().await;
+        let kek = Self::export_kek(*locked_key_store, kek_id, kek_buffer.as_mut_slice())?;
+
+        let records_end = blob.len() - GCM_TAG_SIZE;
+        let mut offset = GCM_IV_SIZE;
+        let mut skipped = 0;
+        for key_info in keystore::KeyStore::iter_metadata(*locked_key_store) {
+            if key_info.id == kek_id
+                || !keystore::KeyStore::is_key_available(*locked_key_store, key_info.id)
+            {
+                continue;
+            }
+            if !key_info.permissions.export_private {
+                skipped += 1;
+                continue;
+            }
+            let key_size = key_info.ty.key_size();
+            let record_end = offset + BACKUP_RECORD_HEADER_SIZE + key_size;
+            if record_end > records_end {
+                return Err(Error::KeyStore(keystore::Error::InvalidBufferSize));
+            }
+            let record = &mut blob[offset..record_end];
+            record[..4].copy_from_slice(&key_info.id.0.to_le_bytes());
+            record[4..6].copy_from_slice(&(key_size as u16).to_le_bytes());
+            let key = &mut record[BACKUP_RECORD_HEADER_SIZE..];
+            match key_info.ty {
+                KeyType::Symmetric(_) => {
+                    locked_key_store.export_symmetric_key_insecure(key_info.id, key)?;
+                }
+                KeyType::Asymmetric(_) => {
+                    let (public_key, private_key) = key.split_at_mut(key_info.ty.public_key_size());
+                    locked_key_store.export_public_key_insecure(key_info.id, public_key)?;
+                    locked_key_store.export_private_key_insecure(key_info.id, private_key)?;
+                }
+            }
+            offset = record_end;
+        }
+        drop(locked_key_store);
+
+        let (head, tail) = blob.split_at_mut(offset);
+        let (blob_iv, records) = head.split_at_mut(GCM_IV_SIZE);
+        blob_iv.copy_from_slice(iv);
+        let tag = &mut tail[..GCM_TAG_SIZE];
+        match kek.len() {
+            KEY128_SIZE => aes128gcm_encrypt_in_place_detached(kek, iv, BACKUP_AAD, records, tag),
+            _ => aes256gcm_encrypt_in_place_detached(kek, iv, BACKUP_AAD, records, tag),
+        }?;
+        Ok((offset + GCM_TAG_SIZE, skipped))
+    }
+
+    async fn restore_keystore(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        kek_id: KeyId,
+        blob: &'data mut [u8],
+    ) -> Response<'data> {
+        let result = self.read_backup(kek_id, blob).await;
+        blob.zeroize();
+        match result {
+            Err(e) => Response::Error {
+                client_id,
+                request_id,
+                error: e,
+            },
+            Ok(restored) => Response::RestoreKeystore {
+                client_id,
+                request_id,
+                restored,
+            },
+        }
+    }
+
+    /// Decrypt a backup created by `write_backup()` in place and import all keys contained in it.
+    /// All records are validated before the first key is imported.
+    ///
+    /// returns: The number of restored keys.
+    async fn read_backup(&mut self, kek_id: KeyId, blob: &mut [u8]) -> Result<u32, Error> {
+        if blob.len() < GCM_IV_SIZE + GCM_TAG_SIZE {
+            return Err(Error::Crypto(crypto::Error::InvalidBufferSize));
+        }
+        let mut kek_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
+        let mut locked_key_store = self.key_store.lock().await;
+        let kek = Self::export_kek(*locked_key_store, kek_id, kek_buffer.as_mut_slice())?;
+
+        let (iv, rest) = blob.split_at_mut(GCM_IV_SIZE);
+        let (records, tag) = rest.split_at_mut(rest.len() - GCM_TAG_SIZE);
+        match kek.len() {
+            KEY128_SIZE => aes128gcm_decrypt_in_place_detached(kek, iv, BACKUP_AAD, records, tag),
+            _ => aes256gcm_decrypt_in_place_detached(kek, iv, BACKUP_AAD, records, tag),
+        }?;
+
+        // Validate all records first to avoid partially restored key stores
+        let mut offset = 0;
+        while offset < records.len() {
+            let (id, key) = Self::parse_backup_record(&records[offset..])?;
+            let key_info = keystore::KeyStore::get_key_info(*locked_key_store, id)?;
+            if id == kek_id || key.len() != key_info.ty.key_size() {
+                return Err(Error::KeyStore(keystore::Error::InvalidKeyId));
+            }
+            // The checks below use the state before the restore, so a later record for the same
+            // key would silently overwrite an earlier one
+            let mut previous_offset = 0;
+            while previous_offset < offset {
+                let (previous_id, previous_key) =
+                    Self::parse_backup_record(&records[previous_offset..])?;
+                if previous_id == id {
+                    return Err(Error::KeyStore(keystore::Error::DuplicateIds));
+                }
+                previous_offset += BACKUP_RECORD_HEADER_SIZE + previous_key.len();
+            }
+            // Same permissions as for importing a key without the overwrite flag
+            if !key_info.permissions.import {
+                return Err(Error::KeyStore(keystore::Error::NotAllowed));
+            }
+            if keystore::KeyStore::is_key_available(*locked_key_store, id)
+                && !key_info.permissions.overwrite
+            {
+                return Err(Error::KeyStore(keystore::Error::KeyAlreadyExists));
+            }
+            if let KeyType::Asymmetric(curve) = key_info.ty {
+                let (public_key, private_key) = key.split_at(key_info.ty.public_key_size());
+                Self::check_key_pair(curve, public_key, private_key)?;
+            }
+            offset += BACKUP_RECORD_HEADER_SIZE + key.len();
+        }
+
+        let mut offset = 0;
+        let mut restored = 0;
+        while offset < records.len() {
+            let (id, key) = Self::parse_backup_record(&records[offset..])?;
+            match keystore::KeyStore::get_key_info(*locked_key_store, id)?.ty {
+                KeyType::Symmetric(_) => locked_key_store.import_symmetric_key_insecure(id, key)?,
+                ty @ KeyType::Asymmetric(_) => {
+                    let (public_key, private_key) = key.split_at(ty.public_key_size());
+                    locked_key_store.import_key_pair_insecure(id, public_key, private_key)?
+                }
+            }
+            offset += BACKUP_RECORD_HEADER_SIZE + key.len();
+            restored += 1;
+        }
+        Ok(restored)
+    }
+
+    /// Check that `private_key` is valid and belongs to `public_key`.
+    fn check_key_pair(curve: Curve, public_key: &[u8], private_key: &[u8]) -> Result<(), Error> {
+        let mut derived_public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+        let derived_public_key = &mut derived_public_key[..public_key.len()];
+        match curve {
+            Curve::NistP256 => {
+                nist_p256_public_key_from_private_key(private_key, derived_public_key)
+            }
+            Curve::NistP384 => {
+                nist_p384_public_key_from_private_key(private_key, derived_public_key)
+            }
+        }?;
+        if derived_public_key != public_key {
+            return Err(Error::Crypto(crypto::Error::InvalidPublicKey));
+        }
+        Ok(())
+    }
+
+    fn parse_backup_record(record: &[u8]) -> Result<(KeyId, &[u8]), Error> {
+        if record.len() < BACKUP_RECORD_HEADER_SIZE {
+            return Err(Error::KeyStore(keystore::Error::InvalidBufferSize));
+        }
+        let id = u32::from_le_bytes(record[..4].try_into().expect("invalid slice size"));
+        let size = u16::from_le_bytes(record[4..6].try_into().expect("invalid slice size"));
+        let key = record[BACKUP_RECORD_HEADER_SIZE..]
+            .get(..size as usize)
+            .ok_or(Error::KeyStore(keystore::Error::InvalidBufferSize))?;
+        Ok((KeyId(id), key))
+    }
+
+    /// Export a key encryption key. Only AES-128 and AES-256 keys are supported.
+    fn export_kek<'a>(
+        key_store: &KeyStore,
+        kek_id: KeyId,
+        kek_buffer: &'a mut [u8],
+    ) -> Result<&'a [u8], Error> {
+        match keystore::KeyStore::get_key_info(key_store, kek_id)?.ty {
+            KeyType::Symmetric(KEY128_SIZE) | KeyType::Symmetric(KEY256_SIZE) => {
+                Ok(key_store.export_symmetric_key_insecure(kek_id, kek_buffer)?)
+            }
+            _ => Err(Error::KeyStore(keystore::Error::InvalidKeyType)),
+        }
+    }
+
     async fn export_key_and_key_info<'a>(
         &mut self,
         key_id: KeyId,
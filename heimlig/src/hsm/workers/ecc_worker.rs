@@ -1,10 +1,14 @@
-use crate::common::jobs::{ClientId, Error, Request, RequestId, Response};
+use crate::common::jobs::{ClientId, Error, Request, RequestId, Response, SignatureEncoding};
 use crate::crypto;
+use crate::crypto::ecdh::{nist_p256_derive_shared_secret, nist_p384_derive_shared_secret};
 use crate::crypto::ecdsa::{
-    nist_p256_generate_key_pair, nist_p256_sign, nist_p256_sign_prehashed, nist_p256_verify,
-    nist_p256_verify_prehashed, nist_p384_generate_key_pair, nist_p384_sign,
-    nist_p384_sign_prehashed, nist_p384_verify, nist_p384_verify_prehashed,
+    nist_p256_generate_key_pair, nist_p256_public_key_from_private_key, nist_p256_sign,
+    nist_p256_sign_prehashed, nist_p256_signature_from_der, nist_p256_verify,
+    nist_p256_verify_prehashed, nist_p384_generate_key_pair, nist_p384_public_key_from_private_key,
+    nist_p384_sign, nist_p384_sign_prehashed, nist_p384_signature_from_der, nist_p384_verify,
+    nist_p384_verify_prehashed,
 };
+use crate::crypto::hkdf::hkdf_sha2_256;
 use crate::hsm::keystore;
 use crate::hsm::keystore::{Curve, KeyId, KeyInfo, KeyType};
 use core::ops::DerefMut;
@@ -14,6 +18,9 @@ use futures::{Sink, SinkExt, Stream, StreamExt};
 use rand_chacha::rand_core::{CryptoRng, RngCore};
 use zeroize::{Zeroize, Zeroizing};
 
+/// Label at the start of every attestation statement to separate it from other signed data.
+pub const ATTESTATION_LABEL: &[u8] = b"heimlig attestation";
+
 pub struct EccWorker<
     'data,
     'rng,
@@ -28,6 +35,9 @@ pub struct EccWorker<
     pub key_store: &'keystore Mutex<M, &'keystore mut KeyStore>,
     pub requests: ReqSrc,
     pub responses: RespSink,
+    /// The only key allowed to sign attestation statements. Attestation is disabled if `None`.
+    /// The key is reserved for attestation and can not be used for plain signatures.
+    pub attestation_key_id: Option<KeyId>,
 }
 
 impl<
@@ -45,12 +55,14 @@ impl<
     /// This method is supposed to be called by a system task that owns this worker.
     pub async fn execute(&mut self) -> Result<(), Error> {
         let request = self.requests.next().await.ok_or(Error::StreamTerminated)?;
+        let expects_response = request.expects_response();
         let response = match request {
             Request::GenerateKeyPair {
                 client_id,
                 request_id,
                 key_id,
                 overwrite,
+                no_response: _,
             } => {
                 self.generate_key_pair(client_id, request_id, key_id, overwrite)
                     .await
@@ -91,9 +103,12 @@ impl<
                 message,
                 prehashed,
                 signature,
+                encoding,
             } => {
-                self.verify(client_id, request_id, key_id, message, prehashed, signature)
-                    .await
+                self.verify(
+                    client_id, request_id, key_id, message, prehashed, signature, encoding,
+                )
+                .await
             }
             Request::VerifyExternalKey {
                 client_id,
@@ -102,14 +117,60 @@ impl<
                 message,
                 prehashed,
                 signature,
+                encoding,
             } => {
                 self.verify_external_key(
-                    client_id, request_id, public_key, message, prehashed, signature,
+                    client_id, request_id, public_key, message, prehashed, signature, encoding,
                 )
                 .await
             }
+            Request::EcdhHkdf {
+                client_id,
+                request_id,
+                private_key_id,
+                public_key,
+                salt,
+                info,
+                key_id,
+                overwrite,
+            } => {
+                self.ecdh_hkdf(
+                    client_id,
+                    request_id,
+                    private_key_id,
+                    public_key,
+                    salt,
+                    info,
+                    key_id,
+                    overwrite,
+                )
+                .await
+            }
+            Request::Attest {
+                client_id,
+                request_id,
+                key_id,
+                nonce,
+                statement,
+                signature,
+            } => {
+                self.attest(client_id, request_id, key_id, nonce, statement, signature)
+                    .await
+            }
+            Request::VerifyKeyMatches {
+                client_id,
+                request_id,
+                private_key_id,
+                expected_public_key,
+            } => {
+                self.verify_key_matches(client_id, request_id, private_key_id, expected_public_key)
+                    .await
+            }
             _ => Err(Error::UnexpectedRequestType)?,
         };
+        if !response.is_sent(expects_response) {
+            return Ok(());
+        }
         self.responses
             .send(response)
             .await
@@ -206,6 +267,14 @@ impl<
         prehashed: bool,
         signature: &'data mut [u8],
     ) -> Response<'data> {
+        // Signing arbitrary messages with the attestation key would allow forging statements
+        if self.attestation_key_id == Some(key_id) {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::KeyStore(keystore::Error::NotAllowed),
+            };
+        }
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
         let private_key_and_info = self
             .export_private_key_and_key_info(key_id, key_buffer.as_mut_slice())
@@ -258,6 +327,62 @@ impl<
         }
     }
 
+    async fn verify_key_matches(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        private_key_id: KeyId,
+        expected_public_key: &[u8],
+    ) -> Response<'data> {
+        let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        let private_key_and_info = self
+            .export_private_key_and_key_info(private_key_id, key_buffer.as_mut_slice())
+            .await;
+
+        let mut public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+        let result = match private_key_and_info {
+            Err(e) => {
+                return Response::Error {
+                    client_id,
+                    request_id,
+                    error: Error::KeyStore(e),
+                };
+            }
+            Ok((private_key, key_info)) => {
+                let public_key = &mut public_key[..key_info.ty.public_key_size()];
+                match key_info.ty {
+                    KeyType::Asymmetric(Curve::NistP256) => {
+                        nist_p256_public_key_from_private_key(private_key, public_key)
+                    }
+                    KeyType::Asymmetric(Curve::NistP384) => {
+                        nist_p384_public_key_from_private_key(private_key, public_key)
+                    }
+                    _ => {
+                        return Response::Error {
+                            client_id,
+                            request_id,
+                            error: Error::KeyStore(keystore::Error::InvalidKeyType),
+                        };
+                    }
+                }
+                .map(|_| public_key == expected_public_key)
+            }
+        };
+
+        match result {
+            Err(e) => Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(e),
+            },
+            Ok(matches) => Response::VerifyKeyMatches {
+                client_id,
+                request_id,
+                matches,
+            },
+        }
+    }
+
     async fn sing_external_key(
         &mut self,
         client_id: ClientId,
@@ -305,6 +430,7 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn verify(
         &mut self,
         client_id: ClientId,
@@ -313,6 +439,7 @@ impl<
         message: &[u8],
         prehashed: bool,
         signature: &[u8],
+        encoding: SignatureEncoding,
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_PUBLIC_KEY_SIZE]);
         let public_key_and_info = self
@@ -328,19 +455,8 @@ impl<
                 };
             }
             Ok((public_key, key_info)) => match key_info.ty {
-                KeyType::Asymmetric(Curve::NistP256) => {
-                    if prehashed {
-                        nist_p256_verify_prehashed(public_key, message, signature)
-                    } else {
-                        nist_p256_verify(public_key, message, signature)
-                    }
-                }
-                KeyType::Asymmetric(Curve::NistP384) => {
-                    if prehashed {
-                        nist_p384_verify_prehashed(public_key, message, signature)
-                    } else {
-                        nist_p384_verify(public_key, message, signature)
-                    }
+                KeyType::Asymmetric(curve) => {
+                    verify_signature(curve, public_key, message, prehashed, signature, encoding)
                 }
                 _ => {
                     return Response::Error {
@@ -371,6 +487,7 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn verify_external_key(
         &mut self,
         client_id: ClientId,
@@ -379,22 +496,11 @@ impl<
         message: &[u8],
         prehashed: bool,
         signature: &[u8],
+        encoding: SignatureEncoding,
     ) -> Response<'data> {
-        let result = match public_key.len() {
-            crypto::ecdsa::NIST_P256_PUBLIC_KEY_SIZE => {
-                if prehashed {
-                    nist_p256_verify_prehashed(public_key, message, signature)
-                } else {
-                    nist_p256_verify(public_key, message, signature)
-                }
-            }
-            crypto::ecdsa::NIST_P384_PUBLIC_KEY_SIZE => {
-                if prehashed {
-                    nist_p384_verify_prehashed(public_key, message, signature)
-                } else {
-                    nist_p384_verify(public_key, message, signature)
-                }
-            }
+        let curve = match public_key.len() {
+            crypto::ecdsa::NIST_P256_PUBLIC_KEY_SIZE => Curve::NistP256,
+            crypto::ecdsa::NIST_P384_PUBLIC_KEY_SIZE => Curve::NistP384,
             _ => {
                 return Response::Error {
                     client_id,
@@ -403,6 +509,7 @@ impl<
                 };
             }
         };
+        let result = verify_signature(curve, public_key, message, prehashed, signature, encoding);
 
         match result {
             Err(crypto::Error::InvalidSignature) => Response::Verify {
@@ -423,6 +530,158 @@ impl<
         }
     }
 
+    /// Perform an ECDH key agreement and derive a symmetric key from the shared secret via
+    /// HKDF-SHA-256. The derived key is stored under `key_id` and fills the whole key slot.
+    /// The shared secret never leaves the worker.
+    #[allow(clippy::too_many_arguments)]
+    async fn ecdh_hkdf(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        private_key_id: KeyId,
+        public_key: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        key_id: KeyId,
+        overwrite: bool,
+    ) -> Response<'data> {
+        match self
+            .derive_ecdh_hkdf_key(private_key_id, public_key, salt, info, key_id, overwrite)
+            .await
+        {
+            Ok(()) => Response::EcdhHkdf {
+                client_id,
+                request_id,
+            },
+            Err(error) => Response::Error {
+                client_id,
+                request_id,
+                error,
+            },
+        }
+    }
+
+    async fn derive_ecdh_hkdf_key(
+        &mut self,
+        private_key_id: KeyId,
+        public_key: &[u8],
+        salt: &[u8],
+        info: &[u8],
+        key_id: KeyId,
+        overwrite: bool,
+    ) -> Result<(), Error> {
+        let mut private_key_buffer = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        let mut shared_secret_buffer = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
+        // Lock keystore only once
+        let mut locked_key_store = self.key_store.lock().await;
+
+        let private_key_info = keystore::KeyStore::get_key_info(*locked_key_store, private_key_id)?;
+        let key_info = keystore::KeyStore::get_key_info(*locked_key_store, key_id)?;
+        if !private_key_info.ty.is_asymmetric() || !key_info.ty.is_symmetric() {
+            return Err(Error::KeyStore(keystore::Error::InvalidKeyType));
+        }
+        // Check overwrite permission
+        if keystore::KeyStore::is_key_available(*locked_key_store, key_id)
+            && (!overwrite || !key_info.permissions.overwrite)
+        {
+            return Err(Error::KeyStore(keystore::Error::KeyAlreadyExists));
+        }
+
+        let private_key = locked_key_store
+            .export_private_key_insecure(private_key_id, private_key_buffer.as_mut_slice())?;
+        let shared_secret = &mut shared_secret_buffer[..private_key.len()];
+        match private_key_info.ty {
+            KeyType::Asymmetric(Curve::NistP256) => {
+                nist_p256_derive_shared_secret(private_key, public_key, shared_secret)
+            }
+            KeyType::Asymmetric(Curve::NistP384) => {
+                nist_p384_derive_shared_secret(private_key, public_key, shared_secret)
+            }
+            _ => Err(crypto::Error::InvalidPrivateKey),
+        }?;
+        let key = &mut key_buffer[..key_info.ty.key_size()];
+        hkdf_sha2_256(salt, shared_secret, info, key)?;
+        Ok(locked_key_store.import_symmetric_key_insecure(key_id, key)?)
+    }
+
+    async fn attest(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        nonce: &[u8],
+        statement: &'data mut [u8],
+        signature: &'data mut [u8],
+    ) -> Response<'data> {
+        match self
+            .sign_attestation(key_id, nonce, statement, signature)
+            .await
+        {
+            Ok(statement) => Response::Attest {
+                client_id,
+                request_id,
+                statement,
+                signature,
+            },
+            Err(error) => Response::Error {
+                client_id,
+                request_id,
+                error,
+            },
+        }
+    }
+
+    async fn sign_attestation(
+        &mut self,
+        key_id: KeyId,
+        nonce: &[u8],
+        statement: &'data mut [u8],
+        signature: &mut [u8],
+    ) -> Result<&'data mut [u8], Error> {
+        if self.attestation_key_id != Some(key_id) {
+            return Err(Error::KeyStore(keystore::Error::NotAllowed));
+        }
+        let mut private_key_buffer = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        // Lock keystore only once
+        let locked_key_store = self.key_store.lock().await;
+
+        let key_info = keystore::KeyStore::get_key_info(*locked_key_store, key_id)?;
+        if !key_info.ty.is_asymmetric() {
+            return Err(Error::KeyStore(keystore::Error::InvalidKeyType));
+        }
+        // A dedicated attestation key never leaves the HSM
+        if key_info.permissions.export_private {
+            return Err(Error::KeyStore(keystore::Error::NotAllowed));
+        }
+
+        // Statement: label || public key || nonce
+        let public_key_size = key_info.ty.public_key_size();
+        let statement_size = ATTESTATION_LABEL.len() + public_key_size + nonce.len();
+        if statement.len() < statement_size {
+            return Err(Error::Crypto(crypto::Error::InvalidBufferSize));
+        }
+        let (statement, _) = statement.split_at_mut(statement_size);
+        let (label, rest) = statement.split_at_mut(ATTESTATION_LABEL.len());
+        let (public_key, statement_nonce) = rest.split_at_mut(public_key_size);
+        label.copy_from_slice(ATTESTATION_LABEL);
+        locked_key_store.export_public_key(key_id, public_key)?;
+        statement_nonce.copy_from_slice(nonce);
+
+        let private_key = locked_key_store
+            .export_private_key_insecure(key_id, private_key_buffer.as_mut_slice())?;
+        match key_info.ty {
+            KeyType::Asymmetric(Curve::NistP256) => {
+                nist_p256_sign(private_key, statement, signature)
+            }
+            KeyType::Asymmetric(Curve::NistP384) => {
+                nist_p384_sign(private_key, statement, signature)
+            }
+            _ => Err(crypto::Error::InvalidPrivateKey),
+        }?;
+        Ok(statement)
+    }
+
     async fn export_private_key_and_key_info<'a>(
         &mut self,
         key_id: KeyId,
@@ -452,6 +711,46 @@ impl<
     }
 }
 
+/// Verify an ECDSA signature given in either encoding.
+fn verify_signature(
+    curve: Curve,
+    public_key: &[u8],
+    message: &[u8],
+    prehashed: bool,
+    signature: &[u8],
+    encoding: SignatureEncoding,
+) -> Result<(), crypto::Error> {
+    let mut signature_buffer = [0u8; crypto::ecdsa::NIST_P384_SIGNATURE_SIZE];
+    let signature = match encoding {
+        SignatureEncoding::Raw => signature,
+        SignatureEncoding::Der => {
+            let raw_signature =
+                &mut signature_buffer[..KeyType::Asymmetric(curve).signature_size()];
+            match curve {
+                Curve::NistP256 => nist_p256_signature_from_der(signature, raw_signature),
+                Curve::NistP384 => nist_p384_signature_from_der(signature, raw_signature),
+            }?;
+            raw_signature
+        }
+    };
+    match curve {
+        Curve::NistP256 => {
+            if prehashed {
+                nist_p256_verify_prehashed(public_key, message, signature)
+            } else {
+                nist_p256_verify(public_key, message, signature)
+            }
+        }
+        Curve::NistP384 => {
+            if prehashed {
+                nist_p384_verify_prehashed(public_key, message, signature)
+            } else {
+                nist_p384_verify(public_key, message, signature)
+            }
+        }
+    }
+}
+
 fn move_key_pair<'a, const N: usize, const M: usize>(
     mut private_key: [u8; N],
     mut public_key: [u8; M],
@@ -1,17 +1,51 @@
 use crate::{
-    common::jobs::{ClientId, Error, HashAlgorithm, Request, RequestId, Response},
+    common::jobs::{
+        ClientId, Error, HashAlgorithm, HashAlgorithms, Request, RequestId, Response, Segments,
+    },
+    crypto,
+    crypto::hash::{sha256, sha384, sha3_256, sha3_384, sha3_512, sha512},
+    crypto::hkdf::hkdf_sha2_256,
     crypto::hmac::{
         hmac_sha2_256_calculate, hmac_sha2_256_verify, hmac_sha2_384_calculate,
         hmac_sha2_384_verify, hmac_sha2_512_calculate, hmac_sha2_512_verify,
         hmac_sha3_256_calculate, hmac_sha3_256_verify, hmac_sha3_384_calculate,
         hmac_sha3_384_verify, hmac_sha3_512_calculate, hmac_sha3_512_verify,
     },
-    hsm::keystore::{self, KeyId, KeyInfo, KeyType},
+    hsm::{
+        keystore::{self, KeyId, KeyType},
+        workers::export_mac_key_and_key_info,
+    },
 };
 use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
 use futures::{Sink, SinkExt, Stream, StreamExt};
+use heapless::Vec;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_384, Sha3_512};
 use zeroize::Zeroizing;
 
+/// HKDF info used to ratchet a symmetric key forward during rekeying.
+const REKEY_INFO: &[u8] = b"heimlig rekey";
+
+/// Fixed public HMAC key for key fingerprints. It only separates fingerprints from plain hashes
+/// of the same key material. As it is public, it does not stop brute-forcing low-entropy keys.
+/// Symmetric fingerprints therefore act as key check values and require export permission.
+const FINGERPRINT_SALT: &[u8] = b"heimlig key fingerprint";
+
+/// Number of [HashAlgorithm] variants that can be computed at once.
+const MAX_HASHERS: usize = 6;
+
+/// Size of the chunks of the input that are fed to all hashers in turn, so that a multi-hash
+/// only makes a single pass over the input.
+const HASH_MULTI_CHUNK_SIZE: usize = 1024;
+
+/// Size of the largest key material that is hashed for a fingerprint.
+const MAX_FINGERPRINT_INPUT_SIZE: usize =
+    if KeyType::MAX_SYMMETRIC_KEY_SIZE > KeyType::MAX_PUBLIC_KEY_SIZE {
+        KeyType::MAX_SYMMETRIC_KEY_SIZE
+    } else {
+        KeyType::MAX_PUBLIC_KEY_SIZE
+    };
+
 pub struct HmacWorker<
     'data,
     'keystore,
@@ -46,10 +80,19 @@ impl<
                 key_id,
                 hash_algorithm,
                 message,
+                message_type,
                 tag,
             } => {
-                self.calculate_hmac(client_id, request_id, key_id, hash_algorithm, message, tag)
-                    .await
+                self.calculate_hmac(
+                    client_id,
+                    request_id,
+                    key_id,
+                    hash_algorithm,
+                    message,
+                    message_type,
+                    tag,
+                )
+                .await
             }
             Request::CalculateHmacExternalKey {
                 client_id,
@@ -75,10 +118,19 @@ impl<
                 key_id,
                 hash_algorithm,
                 message,
+                message_type,
                 tag,
             } => {
-                self.verify_hmac(client_id, request_id, key_id, hash_algorithm, message, tag)
-                    .await
+                self.verify_hmac(
+                    client_id,
+                    request_id,
+                    key_id,
+                    hash_algorithm,
+                    message,
+                    message_type,
+                    tag,
+                )
+                .await
             }
             Request::VerifyHmacExternalKey {
                 client_id,
@@ -98,11 +150,48 @@ impl<
                 )
                 .await
             }
+            Request::Rekey {
+                client_id,
+                request_id,
+                key_id,
+            } => self.rekey(client_id, request_id, key_id).await,
+            Request::KeyFingerprint {
+                client_id,
+                request_id,
+                key_id,
+                hash_algorithm,
+                fingerprint,
+            } => {
+                self.key_fingerprint(client_id, request_id, key_id, hash_algorithm, fingerprint)
+                    .await
+            }
+            Request::Hash {
+                client_id,
+                request_id,
+                hash_algorithm,
+                data,
+                digest,
+            } => self.hash(client_id, request_id, hash_algorithm, data, digest),
+            Request::HashMulti {
+                client_id,
+                request_id,
+                hash_algorithms,
+                data,
+                digests,
+            } => self.hash_multi(client_id, request_id, hash_algorithms, data, digests),
+            Request::HashSegments {
+                client_id,
+                request_id,
+                hash_algorithm,
+                segments,
+                digest,
+            } => self.hash_segments(client_id, request_id, hash_algorithm, segments, digest),
             _ => Err(Error::UnexpectedRequestType)?,
         };
         self.responses.send(response).await.map_err(|_| Error::Send)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn calculate_hmac(
         &mut self,
         client_id: ClientId,
@@ -110,12 +199,17 @@ impl<
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &[u8],
+        message_type: &[u8],
         tag: &'data mut [u8],
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
-        let key_and_info = self
-            .export_key_and_key_info(key_id, key_buffer.as_mut_slice())
-            .await;
+        let key_and_info = export_mac_key_and_key_info(
+            self.key_store,
+            key_id,
+            message_type,
+            key_buffer.as_mut_slice(),
+        )
+        .await;
         let result = match key_and_info {
             Err(e) => {
                 return Response::Error {
@@ -187,6 +281,7 @@ impl<
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn verify_hmac(
         &mut self,
         client_id: ClientId,
@@ -194,12 +289,17 @@ impl<
         key_id: KeyId,
         hash_algorithm: HashAlgorithm,
         message: &[u8],
+        message_type: &[u8],
         tag: &[u8],
     ) -> Response<'data> {
         let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
-        let key_and_info = self
-            .export_key_and_key_info(key_id, key_buffer.as_mut_slice())
-            .await;
+        let key_and_info = export_mac_key_and_key_info(
+            self.key_store,
+            key_id,
+            message_type,
+            key_buffer.as_mut_slice(),
+        )
+        .await;
         let result = match key_and_info {
             Err(e) => {
                 return Response::Error {
@@ -271,16 +371,280 @@ impl<
         }
     }
 
-    async fn export_key_and_key_info<'a>(
+    /// Replace a symmetric key with a key derived from it via HKDF-SHA-256.
+    /// The old key is overwritten in the key store and can not be recovered afterwards.
+    async fn rekey(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+    ) -> Response<'data> {
+        let mut key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
+        let mut new_key_buffer = Zeroizing::new([0u8; KeyType::MAX_SYMMETRIC_KEY_SIZE]);
+        // Keep keystore locked so that no other worker observes the key in between
+        let mut locked_key_store = self.key_store.lock().await;
+        let result = keystore::KeyStore::get_key_info(*locked_key_store, key_id).and_then(|info| {
+            if !info.ty.is_symmetric() {
+                return Err(keystore::Error::InvalidKeyType);
+            }
+            if !info.permissions.overwrite {
+                return Err(keystore::Error::NotAllowed);
+            }
+            locked_key_store.export_symmetric_key_insecure(key_id, key_buffer.as_mut_slice())
+        });
+        let key = match result {
+            Ok(key) => key,
+            Err(e) => {
+                return Response::Error {
+                    client_id,
+                    request_id,
+                    error: Error::KeyStore(e),
+                }
+            }
+        };
+        let new_key = &mut new_key_buffer[..key.len()];
+        if let Err(e) = hkdf_sha2_256(&[], key, REKEY_INFO, new_key) {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(e),
+            };
+        }
+        match locked_key_store.import_symmetric_key_insecure(key_id, new_key) {
+            Ok(()) => Response::Rekey {
+                client_id,
+                request_id,
+            },
+            Err(e) => Response::Error {
+                client_id,
+                request_id,
+                error: Error::KeyStore(e),
+            },
+        }
+    }
+
+    /// Compute a salted hash of a key that identifies it without exposing the key material.
+    /// Symmetric keys are hashed directly, for asymmetric keys only the public key is hashed.
+    async fn key_fingerprint(
         &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
         key_id: KeyId,
-        key_buffer: &'a mut [u8],
-    ) -> Result<(&'a [u8], KeyInfo), keystore::Error> {
-        // Lock keystore only once
-        let locked_key_store = self.key_store.lock().await;
-        Ok((
-            locked_key_store.export_symmetric_key_insecure(key_id, key_buffer)?,
-            keystore::KeyStore::get_key_info(*locked_key_store, key_id)?,
-        ))
+        hash_algorithm: HashAlgorithm,
+        fingerprint: &'data mut [u8],
+    ) -> Response<'data> {
+        let mut key_buffer = Zeroizing::new([0u8; MAX_FINGERPRINT_INPUT_SIZE]);
+        let key = {
+            let locked_key_store = self.key_store.lock().await;
+            keystore::KeyStore::get_key_info(*locked_key_store, key_id).and_then(|info| {
+                if info.ty.is_symmetric() {
+                    // Guesses of the key can be confirmed offline against its fingerprint
+                    if !info.permissions.export_private {
+                        return Err(keystore::Error::NotAllowed);
+                    }
+                    locked_key_store
+                        .export_symmetric_key_insecure(key_id, key_buffer.as_mut_slice())
+                } else {
+                    locked_key_store.export_public_key_insecure(key_id, key_buffer.as_mut_slice())
+                }
+            })
+        };
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                return Response::Error {
+                    client_id,
+                    request_id,
+                    error: Error::KeyStore(e),
+                }
+            }
+        };
+        let result = match hash_algorithm {
+            HashAlgorithm::Sha2_256 => hmac_sha2_256_calculate(FINGERPRINT_SALT, key, fingerprint),
+            HashAlgorithm::Sha2_384 => hmac_sha2_384_calculate(FINGERPRINT_SALT, key, fingerprint),
+            HashAlgorithm::Sha2_512 => hmac_sha2_512_calculate(FINGERPRINT_SALT, key, fingerprint),
+            HashAlgorithm::Sha3_256 => hmac_sha3_256_calculate(FINGERPRINT_SALT, key, fingerprint),
+            HashAlgorithm::Sha3_384 => hmac_sha3_384_calculate(FINGERPRINT_SALT, key, fingerprint),
+            HashAlgorithm::Sha3_512 => hmac_sha3_512_calculate(FINGERPRINT_SALT, key, fingerprint),
+        };
+        match result {
+            Err(e) => Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(e),
+            },
+            Ok(()) => Response::KeyFingerprint {
+                client_id,
+                request_id,
+                fingerprint,
+            },
+        }
+    }
+
+    /// Calculate the digest of `data`. Only the first digest size bytes of `digest` are used.
+    fn hash(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithm: HashAlgorithm,
+        data: &[u8],
+        digest: &'data mut [u8],
+    ) -> Response<'data> {
+        let digest_size = hash_algorithm.digest_size();
+        if digest.len() < digest_size {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(crypto::Error::InvalidBufferSize),
+            };
+        }
+        let digest = &mut digest[..digest_size];
+        calculate_digest(hash_algorithm, data, digest);
+        Response::Hash {
+            client_id,
+            request_id,
+            digest,
+        }
+    }
+
+    /// Calculate the digests of `data` for all `hash_algorithms`. The digests are concatenated in
+    /// the order of the [HashAlgorithm] variants. Only the first combined digest size bytes of
+    /// `digests` are used.
+    fn hash_multi(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithms: HashAlgorithms,
+        data: &[u8],
+        digests: &'data mut [u8],
+    ) -> Response<'data> {
+        let digests_size = hash_algorithms.digests_size();
+        if digests.len() < digests_size {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(crypto::Error::InvalidBufferSize),
+            };
+        }
+        let digests = &mut digests[..digests_size];
+        let mut hashers: Vec<Hasher, MAX_HASHERS> =
+            hash_algorithms.iter().map(Hasher::new).collect();
+        for chunk in data.chunks(HASH_MULTI_CHUNK_SIZE) {
+            for hasher in hashers.iter_mut() {
+                hasher.update(chunk);
+            }
+        }
+        let mut offset = 0;
+        for hasher in hashers {
+            let digest_size = hasher.digest_size();
+            hasher.finalize_into(&mut digests[offset..offset + digest_size]);
+            offset += digest_size;
+        }
+        Response::HashMulti {
+            client_id,
+            request_id,
+            digests,
+        }
+    }
+
+    /// Calculate the digest of `segments` as if they were concatenated. Only the first digest size
+    /// bytes of `digest` are used.
+    fn hash_segments(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        hash_algorithm: HashAlgorithm,
+        segments: Segments,
+        digest: &'data mut [u8],
+    ) -> Response<'data> {
+        let digest_size = hash_algorithm.digest_size();
+        if digest.len() < digest_size {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(crypto::Error::InvalidBufferSize),
+            };
+        }
+        let digest = &mut digest[..digest_size];
+        let mut hasher = Hasher::new(hash_algorithm);
+        for segment in segments {
+            hasher.update(segment);
+        }
+        hasher.finalize_into(digest);
+        Response::HashSegments {
+            client_id,
+            request_id,
+            digest,
+        }
+    }
+}
+
+/// Write the `hash_algorithm` digest of `data` to `digest`, which has to be exactly digest size
+/// bytes long.
+fn calculate_digest(hash_algorithm: HashAlgorithm, data: &[u8], digest: &mut [u8]) {
+    match hash_algorithm {
+        HashAlgorithm::Sha2_256 => digest.copy_from_slice(&sha256(data)),
+        HashAlgorithm::Sha2_384 => digest.copy_from_slice(&sha384(data)),
+        HashAlgorithm::Sha2_512 => digest.copy_from_slice(&sha512(data)),
+        HashAlgorithm::Sha3_256 => digest.copy_from_slice(&sha3_256(data)),
+        HashAlgorithm::Sha3_384 => digest.copy_from_slice(&sha3_384(data)),
+        HashAlgorithm::Sha3_512 => digest.copy_from_slice(&sha3_512(data)),
+    }
+}
+
+/// Incremental hasher for any [HashAlgorithm].
+enum Hasher {
+    Sha2_256(Sha256),
+    Sha2_384(Sha384),
+    Sha2_512(Sha512),
+    Sha3_256(Sha3_256),
+    Sha3_384(Sha3_384),
+    Sha3_512(Sha3_512),
+}
+
+impl Hasher {
+    fn new(hash_algorithm: HashAlgorithm) -> Self {
+        match hash_algorithm {
+            HashAlgorithm::Sha2_256 => Hasher::Sha2_256(Sha256::new()),
+            HashAlgorithm::Sha2_384 => Hasher::Sha2_384(Sha384::new()),
+            HashAlgorithm::Sha2_512 => Hasher::Sha2_512(Sha512::new()),
+            HashAlgorithm::Sha3_256 => Hasher::Sha3_256(Sha3_256::new()),
+            HashAlgorithm::Sha3_384 => Hasher::Sha3_384(Sha3_384::new()),
+            HashAlgorithm::Sha3_512 => Hasher::Sha3_512(Sha3_512::new()),
+        }
+    }
+
+    fn digest_size(&self) -> usize {
+        match self {
+            Hasher::Sha2_256(_) => HashAlgorithm::Sha2_256.digest_size(),
+            Hasher::Sha2_384(_) => HashAlgorithm::Sha2_384.digest_size(),
+            Hasher::Sha2_512(_) => HashAlgorithm::Sha2_512.digest_size(),
+            Hasher::Sha3_256(_) => HashAlgorithm::Sha3_256.digest_size(),
+            Hasher::Sha3_384(_) => HashAlgorithm::Sha3_384.digest_size(),
+            Hasher::Sha3_512(_) => HashAlgorithm::Sha3_512.digest_size(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha2_256(hasher) => hasher.update(data),
+            Hasher::Sha2_384(hasher) => hasher.update(data),
+            Hasher::Sha2_512(hasher) => hasher.update(data),
+            Hasher::Sha3_256(hasher) => hasher.update(data),
+            Hasher::Sha3_384(hasher) => hasher.update(data),
+            Hasher::Sha3_512(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Write the digest to `digest`, which has to be exactly digest size bytes long.
+    fn finalize_into(self, digest: &mut [u8]) {
+        match self {
+            Hasher::Sha2_256(hasher) => digest.copy_from_slice(&hasher.finalize()),
+            Hasher::Sha2_384(hasher) => digest.copy_from_slice(&hasher.finalize()),
+            Hasher::Sha2_512(hasher) => digest.copy_from_slice(&hasher.finalize()),
+            Hasher::Sha3_256(hasher) => digest.copy_from_slice(&hasher.finalize()),
+            Hasher::Sha3_384(hasher) => digest.copy_from_slice(&hasher.finalize()),
+            Hasher::Sha3_512(hasher) => digest.copy_from_slice(&hasher.finalize()),
+        }
     }
 }
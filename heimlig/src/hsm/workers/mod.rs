@@ -3,3 +3,37 @@ pub mod chachapoly_worker;
 pub mod ecc_worker;
 pub mod hmac_worker;
 pub mod rng_worker;
+
+use crate::{
+    crypto::hkdf::derive_mac_key,
+    hsm::keystore::{self, KeyId, KeyInfo},
+};
+use embassy_sync::{blocking_mutex::raw::RawMutex, mutex::Mutex};
+
+/// Export the symmetric key `key_id` for use as a MAC key. If `message_type` is not empty, the
+/// exported key is derived for `message_type` with [derive_mac_key]. The stored key is left
+/// unchanged.
+pub(crate) async fn export_mac_key_and_key_info<'a, M, KeyStore>(
+    key_store: &Mutex<M, &mut KeyStore>,
+    key_id: KeyId,
+    message_type: &[u8],
+    key_buffer: &'a mut [u8],
+) -> Result<(&'a [u8], KeyInfo), keystore::Error>
+where
+    M: RawMutex,
+    KeyStore: keystore::KeyStore + keystore::InsecureKeyStore + Send,
+{
+    let locked_key_store = key_store.lock().await;
+    let key_info = keystore::KeyStore::get_key_info(*locked_key_store, key_id)?;
+    if !key_info.ty.is_symmetric() {
+        return Err(keystore::Error::InvalidKeyType);
+    }
+    let key_size = locked_key_store
+        .export_symmetric_key_insecure(key_id, key_buffer)?
+        .len();
+    let key = &mut key_buffer[..key_size];
+    if !message_type.is_empty() {
+        derive_mac_key(key, message_type).map_err(|_| keystore::Error::InvalidBufferSize)?;
+    }
+    Ok((key, key_info))
+}
@@ -1,10 +1,12 @@
-use crate::common::jobs::{ClientId, Error, Request, RequestId, Response};
+use crate::common::jobs::{ClientId, Error, HashAlgorithm, Request, RequestId, Response};
 use crate::common::limits::MAX_RANDOM_SIZE;
+use crate::crypto;
 use crate::hsm::keystore::{self, KeyId};
 use embassy_sync::blocking_mutex::raw::RawMutex;
 use embassy_sync::mutex::Mutex;
 use futures::{Sink, SinkExt, Stream, StreamExt};
 use rand_chacha::rand_core::{CryptoRng, RngCore};
+use zeroize::Zeroizing;
 
 pub struct RngWorker<
     'data,
@@ -38,6 +40,7 @@ impl<
     /// This method is supposed to be called by a system task that owns this worker.
     pub async fn execute(&mut self) -> Result<(), Error> {
         let request = self.requests.next().await.ok_or(Error::StreamTerminated)?;
+        let expects_response = request.expects_response();
         let response = match request {
             Request::GetRandom {
                 client_id,
@@ -49,6 +52,7 @@ impl<
                 request_id,
                 key_id,
                 overwrite,
+                no_response: _,
             } => {
                 if let Some(key_store) = self.key_store {
                     self.generate_symmetric_key(client_id, request_id, key_id, overwrite, key_store)
@@ -61,8 +65,36 @@ impl<
                     }
                 }
             }
+            Request::GenerateHmacKey {
+                client_id,
+                request_id,
+                key_id,
+                hash_algorithm,
+                overwrite,
+            } => {
+                if let Some(key_store) = self.key_store {
+                    self.generate_hmac_key(
+                        client_id,
+                        request_id,
+                        key_id,
+                        hash_algorithm,
+                        overwrite,
+                        key_store,
+                    )
+                    .await
+                } else {
+                    Response::Error {
+                        client_id,
+                        request_id,
+                        error: Error::NoKeyStore,
+                    }
+                }
+            }
             _ => Err(Error::UnexpectedRequestType)?,
         };
+        if !response.is_sent(expects_response) {
+            return Ok(());
+        }
         self.responses
             .send(response)
             .await
@@ -107,7 +139,7 @@ impl<
                 error: Error::KeyStore(e),
             },
             Ok(key_info) => {
-                let mut key = [0u8; keystore::KeyType::MAX_SYMMETRIC_KEY_SIZE];
+                let mut key = Zeroizing::new([0u8; keystore::KeyType::MAX_SYMMETRIC_KEY_SIZE]);
                 let key = &mut key[0..key_info.ty.key_size()];
                 self.rng.lock().await.fill_bytes(key);
                 let mut locked_key_store = key_store.lock().await;
@@ -137,4 +169,67 @@ impl<
             }
         }
     }
+
+    async fn generate_hmac_key(
+        &mut self,
+        client_id: ClientId,
+        request_id: RequestId,
+        key_id: KeyId,
+        hash_algorithm: HashAlgorithm,
+        overwrite: bool,
+        key_store: &Mutex<M, &mut KeyStore>,
+    ) -> Response<'data> {
+        // Own variable needed to break mutex lock immediately
+        let key_info = keystore::KeyStore::get_key_info(*key_store.lock().await, key_id);
+        let key_info = match key_info {
+            Err(e) => {
+                return Response::Error {
+                    client_id,
+                    request_id,
+                    error: Error::KeyStore(e),
+                }
+            }
+            Ok(key_info) => key_info,
+        };
+
+        // HMAC keys are sized to the block size of the hash. The key slot has to match.
+        let key_size = hash_algorithm.block_size();
+        if !key_info.ty.is_symmetric() {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::KeyStore(keystore::Error::InvalidKeyType),
+            };
+        }
+        if key_size > keystore::KeyType::MAX_SYMMETRIC_KEY_SIZE {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::Crypto(crypto::Error::InvalidSymmetricKeySize),
+            };
+        }
+        if key_info.ty.key_size() != key_size {
+            return Response::Error {
+                client_id,
+                request_id,
+                error: Error::KeyStore(keystore::Error::InvalidBufferSize),
+            };
+        }
+
+        // The slot matches the block size, so the generated key has the HMAC key size
+        match self
+            .generate_symmetric_key(client_id, request_id, key_id, overwrite, key_store)
+            .await
+        {
+            Response::GenerateSymmetricKey {
+                client_id,
+                request_id,
+            } => Response::GenerateHmacKey {
+                client_id,
+                request_id,
+                key_id,
+            },
+            response => response,
+        }
+    }
 }
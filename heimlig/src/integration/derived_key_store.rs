@@ -0,0 +1,351 @@
+use crate::crypto;
+use crate::crypto::ecdsa::{
+    nist_p256_public_key_from_private_key, nist_p384_public_key_from_private_key,
+};
+use crate::crypto::hkdf::hkdf_sha2_256;
+use crate::hsm::keystore::{Curve, Error, InsecureKeyStore, KeyId, KeyInfo, KeyType};
+use heapless::Vec;
+use zeroize::{Zeroize, Zeroizing};
+
+/// Size of the master seed all keys are derived from.
+pub const MASTER_SEED_SIZE: usize = 32;
+
+/// HKDF info prefix used to derive keys. It is followed by the little-endian key ID and, for
+/// private keys, a one-byte retry counter.
+const DERIVATION_LABEL: &[u8] = b"heimlig derived key";
+
+/// Number of candidates tried before giving up on deriving a private key. A candidate is only
+/// rejected if it is zero or not smaller than the curve order, which is negligibly rare for the
+/// supported curves.
+const MAX_PRIVATE_KEY_ATTEMPTS: u8 = 8;
+
+/// Key store for devices without persistent storage.
+///
+/// No key material is stored. Every key is recomputed on demand from the master seed and its key
+/// ID using HKDF-SHA-256. Symmetric keys are the HKDF output. Private keys are the first HKDF
+/// output that is a valid scalar, with a retry counter appended to the HKDF info. The master seed
+/// is the only secret.
+///
+/// As keys are fixed by the master seed, keys can neither be imported nor deleted individually.
+/// All configured keys are always available until [InsecureKeyStore::erase_all_insecure] zeroizes
+/// the master seed.
+pub struct DerivedKeyStore<const MAX_KEYS: usize> {
+    master_seed: [u8; MASTER_SEED_SIZE],
+    /// Key infos sorted by ID.
+    key_infos: Vec<KeyInfo, MAX_KEYS>,
+    /// Set after all keys were erased.
+    erased: bool,
+}
+
+impl<const MAX_KEYS: usize> DerivedKeyStore<MAX_KEYS> {
+    pub fn try_new(
+        master_seed: &[u8; MASTER_SEED_SIZE],
+        key_infos: &[KeyInfo],
+    ) -> Result<Self, Error> {
+        let mut sorted_infos: Vec<KeyInfo, MAX_KEYS> =
+            Vec::from_slice(key_infos).map_err(|_| Error::KeyStoreTooSmall)?;
+        sorted_infos.sort_unstable_by_key(|key_info| key_info.id);
+        if sorted_infos.windows(2).any(|w| w[0].id == w[1].id) {
+            return Err(Error::DuplicateIds);
+        }
+        Ok(Self {
+            master_seed: *master_seed,
+            key_infos: sorted_infos,
+            erased: false,
+        })
+    }
+
+    fn get(&self, id: KeyId) -> Result<&KeyInfo, Error> {
+        let index = self
+            .key_infos
+            .binary_search_by_key(&id, |key_info| key_info.id)
+            .map_err(|_| Error::InvalidKeyId)?;
+        if self.erased {
+            return Err(Error::KeyNotFound);
+        }
+        Ok(&self.key_infos[index])
+    }
+
+    /// Fill `okm` with key material bound to key ID `id`.
+    fn derive(&self, id: KeyId, okm: &mut [u8]) -> Result<(), Error> {
+        let mut info = [0u8; DERIVATION_LABEL.len() + 4];
+        info[..DERIVATION_LABEL.len()].copy_from_slice(DERIVATION_LABEL);
+        info[DERIVATION_LABEL.len()..].copy_from_slice(&id.0.to_le_bytes());
+        hkdf_sha2_256(&[], &self.master_seed, &info, okm).map_err(|_| Error::InvalidBufferSize)
+    }
+
+    /// Fill `okm` with the private key candidate `counter` of key ID `id`.
+    fn derive_private_key_candidate(
+        &self,
+        id: KeyId,
+        counter: u8,
+        okm: &mut [u8],
+    ) -> Result<(), Error> {
+        let mut info = [0u8; DERIVATION_LABEL.len() + 5];
+        info[..DERIVATION_LABEL.len()].copy_from_slice(DERIVATION_LABEL);
+        info[DERIVATION_LABEL.len()..DERIVATION_LABEL.len() + 4]
+            .copy_from_slice(&id.0.to_le_bytes());
+        info[DERIVATION_LABEL.len() + 4] = counter;
+        hkdf_sha2_256(&[], &self.master_seed, &info, okm).map_err(|_| Error::InvalidBufferSize)
+    }
+
+    /// Derive the key pair of key ID `id`. Returns the sizes of the public and the private key.
+    fn derive_key_pair(
+        &self,
+        id: KeyId,
+        curve: Curve,
+        public_key: &mut [u8; KeyType::MAX_PUBLIC_KEY_SIZE],
+        private_key: &mut [u8; KeyType::MAX_PRIVATE_KEY_SIZE],
+    ) -> Result<(usize, usize), Error> {
+        let ty = KeyType::Asymmetric(curve);
+        let public_key = &mut public_key[..ty.public_key_size()];
+        let private_key = &mut private_key[..ty.private_key_size()];
+        for counter in 0..MAX_PRIVATE_KEY_ATTEMPTS {
+            self.derive_private_key_candidate(id, counter, private_key)?;
+            let result = match curve {
+                Curve::NistP256 => nist_p256_public_key_from_private_key(private_key, public_key),
+                Curve::NistP384 => nist_p384_public_key_from_private_key(private_key, public_key),
+            };
+            match result {
+                Ok(()) => return Ok((public_key.len(), private_key.len())),
+                // Zero or not smaller than the curve order
+                Err(crypto::Error::InvalidPrivateKey) => continue,
+                Err(_) => return Err(Error::InvalidBufferSize),
+            }
+        }
+        // Practically unreachable, every candidate fails with a probability below 2^-32
+        Err(Error::KeyNotFound)
+    }
+}
+
+impl<const MAX_KEYS: usize> Drop for DerivedKeyStore<MAX_KEYS> {
+    fn drop(&mut self) {
+        self.master_seed.zeroize();
+    }
+}
+
+impl<const MAX_KEYS: usize> InsecureKeyStore for DerivedKeyStore<MAX_KEYS> {
+    fn get_key_info(&self, id: KeyId) -> Result<KeyInfo, Error> {
+        self.key_infos
+            .iter()
+            .find(|key_info| key_info.id == id)
+            .copied()
+            .ok_or(Error::InvalidKeyId)
+    }
+
+    fn next_key_info(&self, id: Option<KeyId>) -> Option<KeyInfo> {
+        // Key infos are sorted by key ID
+        self.key_infos
+            .iter()
+            .find(|info| !matches!(id, Some(id) if info.id <= id))
+            .copied()
+    }
+
+    fn import_symmetric_key_insecure(&mut self, _id: KeyId, _data: &[u8]) -> Result<(), Error> {
+        Err(Error::NotAllowed)
+    }
+
+    fn import_key_pair_insecure(
+        &mut self,
+        _id: KeyId,
+        _public_key: &[u8],
+        _private_key: &[u8],
+    ) -> Result<(), Error> {
+        Err(Error::NotAllowed)
+    }
+
+    fn export_symmetric_key_insecure<'data>(
+        &self,
+        id: KeyId,
+        dest: &'data mut [u8],
+    ) -> Result<&'data [u8], Error> {
+        let key_info = self.get(id)?;
+        if !key_info.ty.is_symmetric() {
+            return Err(Error::InvalidKeyType);
+        }
+        let size = key_info.ty.key_size();
+        if dest.len() < size {
+            return Err(Error::InvalidBufferSize);
+        }
+        let dest = &mut dest[..size];
+        self.derive(id, dest)?;
+        Ok(dest)
+    }
+
+    fn export_public_key_insecure<'data>(
+        &self,
+        id: KeyId,
+        dest: &'data mut [u8],
+    ) -> Result<&'data [u8], Error> {
+        let key_info = self.get(id)?;
+        let KeyType::Asymmetric(curve) = key_info.ty else {
+            return Err(Error::InvalidKeyType);
+        };
+        if dest.len() < key_info.ty.public_key_size() {
+            return Err(Error::InvalidBufferSize);
+        }
+        let mut public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+        let mut private_key = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        let (public_key_size, _) =
+            self.derive_key_pair(id, curve, &mut public_key, &mut private_key)?;
+        let dest = &mut dest[..public_key_size];
+        dest.copy_from_slice(&public_key[..public_key_size]);
+        Ok(dest)
+    }
+
+    fn export_private_key_insecure<'data>(
+        &self,
+        id: KeyId,
+        dest: &'data mut [u8],
+    ) -> Result<&'data [u8], Error> {
+        let key_info = self.get(id)?;
+        let KeyType::Asymmetric(curve) = key_info.ty else {
+            return Err(Error::InvalidKeyType);
+        };
+        if dest.len() < key_info.ty.private_key_size() {
+            return Err(Error::InvalidBufferSize);
+        }
+        let mut public_key = [0u8; KeyType::MAX_PUBLIC_KEY_SIZE];
+        let mut private_key = Zeroizing::new([0u8; KeyType::MAX_PRIVATE_KEY_SIZE]);
+        let (_, private_key_size) =
+            self.derive_key_pair(id, curve, &mut public_key, &mut private_key)?;
+        let dest = &mut dest[..private_key_size];
+        dest.copy_from_slice(&private_key[..private_key_size]);
+        Ok(dest)
+    }
+
+    fn delete_insecure(&mut self, id: KeyId) -> Result<(), Error> {
+        // Derived keys can only be erased all at once by zeroizing the master seed
+        self.get(id)?;
+        Err(Error::NotAllowed)
+    }
+
+    fn erase_all_insecure(&mut self) {
+        self.master_seed.zeroize();
+        self.erased = true;
+    }
+
+    fn is_key_available(&self, id: KeyId) -> bool {
+        self.get(id).is_ok()
+    }
+
+    fn size(&self, id: KeyId) -> Result<usize, Error> {
+        Ok(self.get(id)?.ty.key_size())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hsm::keystore::{KeyPermissions, KeyStore};
+
+    const MASTER_SEED: [u8; MASTER_SEED_SIZE] = *b"One seed to rule them all.......";
+    const PERMISSIONS: KeyPermissions = KeyPermissions {
+        import: false,
+        export_private: true,
+        overwrite: false,
+        delete: false,
+    };
+    const KEY_INFOS: [KeyInfo; 3] = [
+        KeyInfo {
+            id: KeyId(1),
+            ty: KeyType::Symmetric(32),
+            permissions: PERMISSIONS,
+        },
+        KeyInfo {
+            id: KeyId(2),
+            ty: KeyType::Symmetric(32),
+            permissions: PERMISSIONS,
+        },
+        KeyInfo {
+            id: KeyId(3),
+            ty: KeyType::Asymmetric(Curve::NistP256),
+            permissions: PERMISSIONS,
+        },
+    ];
+
+    #[test]
+    fn derive_keys() {
+        let key_store =
+            DerivedKeyStore::<3>::try_new(&MASTER_SEED, &KEY_INFOS).expect("failed to create");
+        let same_key_store =
+            DerivedKeyStore::<3>::try_new(&MASTER_SEED, &KEY_INFOS).expect("failed to create");
+        let mut key1 = [0u8; 32];
+        let mut key1_again = [0u8; 32];
+        let mut key2 = [0u8; 32];
+        key_store
+            .export_symmetric_key(KeyId(1), &mut key1)
+            .expect("failed to export key");
+        same_key_store
+            .export_symmetric_key(KeyId(1), &mut key1_again)
+            .expect("failed to export key");
+        key_store
+            .export_symmetric_key(KeyId(2), &mut key2)
+            .expect("failed to export key");
+        assert_eq!(key1, key1_again);
+        assert_ne!(key1, key2);
+
+        let mut public_key = [0u8; KEY_INFOS[2].ty.public_key_size()];
+        let mut public_key_again = [0u8; KEY_INFOS[2].ty.public_key_size()];
+        let mut private_key = [0u8; KEY_INFOS[2].ty.private_key_size()];
+        key_store
+            .export_public_key(KeyId(3), &mut public_key)
+            .expect("failed to export key");
+        same_key_store
+            .export_public_key(KeyId(3), &mut public_key_again)
+            .expect("failed to export key");
+        key_store
+            .export_private_key(KeyId(3), &mut private_key)
+            .expect("failed to export key");
+        assert_eq!(public_key, public_key_again);
+        assert_ne!(private_key[..], key1[..]);
+    }
+
+    #[test]
+    fn derive_key_pair_known_answer() {
+        // HKDF-SHA-256 with info "heimlig derived key" || 03000000 || 00
+        let expected_private_key: [u8; 32] = [
+            0x7d, 0xf5, 0x89, 0xe8, 0x59, 0xa1, 0xa5, 0x46, 0xa6, 0xad, 0x4a, 0xcb, 0x01, 0xdb,
+            0xa6, 0x26, 0x7d, 0x6e, 0x81, 0x16, 0x12, 0x14, 0x39, 0x11, 0xc9, 0xfe, 0xe1, 0x60,
+            0x46, 0x0a, 0x42, 0x74,
+        ];
+        let expected_public_key: [u8; 64] = [
+            0xb7, 0x49, 0xb0, 0xa2, 0xe7, 0x0e, 0x03, 0x53, 0x4a, 0x1b, 0x13, 0x0b, 0x3e, 0xe9,
+            0x32, 0x73, 0x0e, 0x9d, 0x99, 0xed, 0x22, 0x4e, 0xcb, 0xe0, 0x4b, 0xf6, 0x0d, 0x11,
+            0xca, 0x2f, 0xd8, 0x9f, 0x19, 0x7c, 0x76, 0x73, 0x35, 0xe0, 0xd5, 0x8f, 0xce, 0x97,
+            0x59, 0x3d, 0xdb, 0x1c, 0x5f, 0x64, 0x4d, 0xfb, 0x26, 0x71, 0x41, 0x88, 0xd5, 0x28,
+            0x11, 0x16, 0x3c, 0x7c, 0x8e, 0xa0, 0x99, 0x1b,
+        ];
+        let key_store =
+            DerivedKeyStore::<3>::try_new(&MASTER_SEED, &KEY_INFOS).expect("failed to create");
+        let mut public_key = [0u8; KEY_INFOS[2].ty.public_key_size()];
+        let mut private_key = [0u8; KEY_INFOS[2].ty.private_key_size()];
+        key_store
+            .export_public_key(KeyId(3), &mut public_key)
+            .expect("failed to export key");
+        key_store
+            .export_private_key(KeyId(3), &mut private_key)
+            .expect("failed to export key");
+        assert_eq!(private_key, expected_private_key);
+        assert_eq!(public_key, expected_public_key);
+    }
+
+    #[test]
+    fn no_import_and_erase() {
+        let mut key_store =
+            DerivedKeyStore::<3>::try_new(&MASTER_SEED, &KEY_INFOS).expect("failed to create");
+        let mut key = [0u8; 32];
+        assert!(KeyStore::is_key_available(&key_store, KeyId(1)));
+        assert!(!KeyStore::is_key_available(&key_store, KeyId(4)));
+        assert_eq!(
+            key_store.import_symmetric_key_insecure(KeyId(1), &key),
+            Err(Error::NotAllowed)
+        );
+        key_store.erase_all();
+        assert!(!KeyStore::is_key_available(&key_store, KeyId(1)));
+        assert_eq!(
+            key_store.export_symmetric_key(KeyId(1), &mut key),
+            Err(Error::KeyNotFound)
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::hsm::keystore::{Error, InsecureKeyStore, KeyId, KeyInfo};
 use heapless::Vec;
+use zeroize::Zeroize;
 
 pub struct MemoryKeyStore<const STORAGE_SIZE: usize, const MAX_KEYS: usize> {
     storage: [u8; STORAGE_SIZE],
@@ -23,6 +24,15 @@ impl<const STORAGE_SIZE: usize, const NUM_KEYS: usize> InsecureKeyStore
         Ok(key_layout.info)
     }
 
+    fn next_key_info(&self, id: Option<KeyId>) -> Option<KeyInfo> {
+        // Layout is sorted by key ID
+        self.layout
+            .inner
+            .iter()
+            .map(|key_layout| key_layout.info)
+            .find(|info| !matches!(id, Some(id) if info.id <= id))
+    }
+
     fn import_symmetric_key_insecure(&mut self, id: KeyId, data: &[u8]) -> Result<(), Error> {
         let key_layout = self.layout.get_mut(id).ok_or(Error::InvalidKeyId)?;
         assert!(key_layout.info.ty.is_symmetric());
@@ -150,6 +160,13 @@ impl<const STORAGE_SIZE: usize, const NUM_KEYS: usize> InsecureKeyStore
         Ok(())
     }
 
+    fn erase_all_insecure(&mut self) {
+        self.storage.zeroize();
+        for key_layout in self.layout.inner.iter_mut() {
+            key_layout.actual_size = 0;
+        }
+    }
+
     fn is_key_available(&self, id: KeyId) -> bool {
         match self.layout.get(id) {
             None => false,
@@ -438,4 +455,94 @@ pub(crate) mod test {
             .import_symmetric_key(NO_EXPORT_OVERWRITE_NO_DELETE.id, &src_buffer, true)
             .is_ok());
     }
+
+    #[test]
+    fn erase_all() {
+        let key_infos: [KeyInfo; 2] = [KEY1_INFO, KEY2_INFO];
+        let src_buffer = [1u8; KEY2_INFO.ty.key_size()];
+        let mut key_store = MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 2>::try_new(&key_infos)
+            .expect("failed to create key store");
+        key_store
+            .import_symmetric_key(KEY1_INFO.id, &src_buffer[0..KEY1_INFO.ty.key_size()], false)
+            .expect("failed to import key");
+        key_store
+            .import_key_pair(
+                KEY2_INFO.id,
+                &src_buffer[0..KEY2_INFO.ty.public_key_size()],
+                &src_buffer[KEY2_INFO.ty.public_key_size()..],
+                false,
+            )
+            .expect("failed to import key");
+
+        key_store.erase_all();
+        assert!(!KeyStore::is_key_available(&key_store, KEY1_INFO.id));
+        assert!(!KeyStore::is_key_available(&key_store, KEY2_INFO.id));
+        assert!(key_store.storage.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn next_key_info() {
+        let key_infos: [KeyInfo; 2] = [KEY1_INFO, KEY2_INFO];
+        let key_store = MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 2>::try_new(&key_infos)
+            .expect("failed to create key store");
+        let mut ids = [None; 3];
+        let mut next = KeyStore::next_key_info(&key_store, None);
+        for id in ids.iter_mut() {
+            *id = next.map(|info| info.id);
+            next = next.and_then(|info| KeyStore::next_key_info(&key_store, Some(info.id)));
+        }
+        assert_eq!(ids, [Some(KEY2_INFO.id), Some(KEY1_INFO.id), None]);
+    }
+
+    #[test]
+    fn too_small_and_duplicate_ids() {
+        let key_infos: [KeyInfo; 2] = [KEY1_INFO, KEY2_INFO];
+        assert!(matches!(
+            MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 1>::try_new(&key_infos),
+            Err(Error::KeyStoreTooSmall)
+        ));
+        assert!(matches!(
+            MemoryKeyStore::<{ TOTAL_KEY_SIZE - 1 }, 2>::try_new(&key_infos),
+            Err(Error::KeyStoreTooSmall)
+        ));
+        let duplicate = KeyInfo {
+            id: KEY1_INFO.id,
+            ..KEY2_INFO
+        };
+        assert!(matches!(
+            MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 2>::try_new(&[KEY1_INFO, duplicate]),
+            Err(Error::DuplicateIds)
+        ));
+    }
+
+    #[test]
+    fn delete_zeroizes_key() {
+        let key_infos: [KeyInfo; 2] = [KEY1_INFO, KEY2_INFO];
+        let key = [0xffu8; KEY1_INFO.ty.key_size()];
+        let mut key_store = MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 2>::try_new(&key_infos)
+            .expect("failed to create key store");
+        key_store
+            .import_symmetric_key(KEY1_INFO.id, &key, false)
+            .expect("failed to import key");
+        assert!(key_store.storage.iter().any(|byte| *byte != 0));
+        key_store
+            .delete(KEY1_INFO.id)
+            .expect("failed to delete key");
+        assert!(key_store.storage.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn iter_metadata() {
+        let key_infos: [KeyInfo; 2] = [KEY1_INFO, KEY2_INFO];
+        let mut key_store = MemoryKeyStore::<{ TOTAL_KEY_SIZE }, 2>::try_new(&key_infos)
+            .expect("failed to create key store");
+        key_store
+            .import_symmetric_key(KEY1_INFO.id, &[1u8; KEY1_INFO.ty.key_size()], false)
+            .expect("failed to import key");
+        let mut yielded = KeyStore::iter_metadata(&key_store).map(|info| info.id);
+        assert_eq!(yielded.next(), Some(KEY2_INFO.id));
+        assert_eq!(yielded.next(), Some(KEY1_INFO.id));
+        assert_eq!(yielded.next(), None);
+        assert_eq!(yielded.next(), None);
+    }
 }
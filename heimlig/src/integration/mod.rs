@@ -1,3 +1,4 @@
+pub mod derived_key_store;
 pub mod embassy;
 pub mod memory_key_store;
 pub mod raw_errors;
@@ -22,6 +22,12 @@ pub enum JobErrorRaw {
     Crypto(CryptoErrorRaw),
     /// A key store error occurred.
     KeyStore(KeyStoreErrorRaw),
+    /// The HSM is locked after an emergency erase and rejects all requests.
+    Locked,
+    /// The request type is not on the allow-list of the core.
+    OperationNotPermitted,
+    /// Decryption or verification failed.
+    AuthenticationFailed,
 }
 
 /// Raw version of crypto::Error
@@ -56,6 +62,18 @@ pub enum CryptoErrorRaw {
     InvalidSignature,
     /// Invalid size of the digest.
     InvalidDigestSize,
+    /// Malformed signature encoding, e.g. invalid DER.
+    InvalidSignatureEncoding,
+    /// Public key of small order that would lead to a predictable shared secret.
+    WeakPublicKey,
+    /// Integrity check of unwrapped key data failed.
+    IntegrityCheckFailed,
+    /// Length prefix does not match the length of the data it describes.
+    LengthMismatch,
+    /// The random number generator has to be reseeded before generating more output.
+    ReseedRequired,
+    /// Entropy source failed a health test.
+    EntropyFailure,
 }
 
 /// Raw version of keystore::Error
@@ -91,6 +109,9 @@ impl From<jobs::Error> for JobErrorRaw {
             jobs::Error::StreamTerminated => JobErrorRaw::StreamTerminated,
             jobs::Error::Crypto(e) => JobErrorRaw::Crypto(e.into()),
             jobs::Error::KeyStore(e) => JobErrorRaw::KeyStore(e.into()),
+            jobs::Error::Locked => JobErrorRaw::Locked,
+            jobs::Error::OperationNotPermitted => JobErrorRaw::OperationNotPermitted,
+            jobs::Error::AuthenticationFailed => JobErrorRaw::AuthenticationFailed,
         }
     }
 }
@@ -112,6 +133,12 @@ impl From<crypto::Error> for CryptoErrorRaw {
             crypto::Error::InvalidSignatureSize => CryptoErrorRaw::InvalidSignatureSize,
             crypto::Error::InvalidSignature => CryptoErrorRaw::InvalidSignature,
             crypto::Error::InvalidDigestSize => CryptoErrorRaw::InvalidDigestSize,
+            crypto::Error::InvalidSignatureEncoding => CryptoErrorRaw::InvalidSignatureEncoding,
+            crypto::Error::WeakPublicKey => CryptoErrorRaw::WeakPublicKey,
+            crypto::Error::IntegrityCheckFailed => CryptoErrorRaw::IntegrityCheckFailed,
+            crypto::Error::LengthMismatch => CryptoErrorRaw::LengthMismatch,
+            crypto::Error::ReseedRequired => CryptoErrorRaw::ReseedRequired,
+            crypto::Error::EntropyFailure => CryptoErrorRaw::EntropyFailure,
         }
     }
 }
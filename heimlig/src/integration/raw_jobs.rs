@@ -1,8 +1,12 @@
-use crate::common::jobs::{HashAlgorithm, Request, Response};
+use crate::common::jobs::{
+    AeadAlgorithm, CbcPadding, HashAlgorithm, HashAlgorithms, PublicKeyEncoding, Request, Response,
+    Segments, SignatureEncoding,
+};
+use crate::common::limits::MAX_HASH_SEGMENTS;
 use crate::hsm::keystore::{Curve, KeyId};
 use crate::integration::raw_errors::JobErrorRaw;
-use core::mem::offset_of;
-use core::slice;
+use core::mem::{offset_of, size_of};
+use core::{ptr, slice};
 use strum::EnumCount;
 
 type ClientIdRaw = u32;
@@ -10,7 +14,12 @@ type RequestIdRaw = u32;
 type KeyIdRaw = u32;
 type CurveRaw = u32;
 type HashAlgorithmRaw = u32;
+type HashAlgorithmsRaw = u32; // Bit n set == HashAlgorithmRaw n included
 type BoolRaw = u32; // 0 == false, 1 == true
+type CbcPaddingRaw = u32;
+type SignatureEncodingRaw = u32;
+type PublicKeyEncodingRaw = u32;
+type AeadAlgorithmRaw = u32;
 
 pub const NIST_P256: CurveRaw = 0;
 pub const NIST_P384: CurveRaw = 1;
@@ -22,6 +31,20 @@ pub const SHA3_256: HashAlgorithmRaw = 3;
 pub const SHA3_384: HashAlgorithmRaw = 4;
 pub const SHA3_512: HashAlgorithmRaw = 5;
 
+pub const PKCS7: CbcPaddingRaw = 0;
+pub const ISO7816: CbcPaddingRaw = 1;
+pub const NO_PADDING: CbcPaddingRaw = 2;
+
+pub const SIGNATURE_ENCODING_RAW: SignatureEncodingRaw = 0;
+pub const SIGNATURE_ENCODING_DER: SignatureEncodingRaw = 1;
+
+pub const UNCOMPRESSED: PublicKeyEncodingRaw = 0;
+pub const COMPRESSED: PublicKeyEncodingRaw = 1;
+
+pub const AES_GCM: AeadAlgorithmRaw = 0;
+pub const AES_CCM: AeadAlgorithmRaw = 1;
+pub const CHACHA_POLY: AeadAlgorithmRaw = 2;
+
 /// A pair of a raw request and a raw response. This is a convenience type for integrators to
 /// allocate all necessary memory for a request and its response in one go.
 #[repr(C)]
@@ -31,6 +54,14 @@ pub struct RequestResponseRawPair {
     pub response: ResponseRaw,
 }
 
+/// Raw input segment of a segmented hash request.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SegmentRaw {
+    data: *const u8,
+    size: u32,
+}
+
 // TODO: Raw types could be generated by a macro from the non-raw type
 /// Raw request as it is written by clients to shared memory. This type is supposed to be synced
 /// with non-Rust (e.g. C++) clients via cbindgen.
@@ -62,10 +93,12 @@ pub enum RequestDataRaw {
     GenerateSymmetricKey {
         key_id: KeyIdRaw,
         overwrite: BoolRaw,
+        no_response: BoolRaw,
     },
     GenerateKeyPair {
         key_id: KeyIdRaw,
         overwrite: BoolRaw,
+        no_response: BoolRaw,
     },
     ImportSymmetricKey {
         key_id: KeyIdRaw,
@@ -90,6 +123,7 @@ pub enum RequestDataRaw {
         key_id: KeyIdRaw,
         public_key_data: *mut u8,
         public_key_size: u32,
+        encoding: PublicKeyEncodingRaw,
     },
     ExportPrivateKey {
         key_id: KeyIdRaw,
@@ -197,6 +231,7 @@ pub enum RequestDataRaw {
         iv_size: u32,
         buffer_data: *mut u8,
         buffer_size: u32,
+        padding: CbcPaddingRaw,
         plaintext_size: u32,
     },
     EncryptAesCbcExternalKey {
@@ -206,6 +241,7 @@ pub enum RequestDataRaw {
         iv_size: u32,
         buffer_data: *mut u8,
         buffer_size: u32,
+        padding: CbcPaddingRaw,
         plaintext_size: u32,
     },
     DecryptAesCbc {
@@ -214,6 +250,7 @@ pub enum RequestDataRaw {
         iv_size: u32,
         buffer_data: *mut u8,
         buffer_size: u32,
+        padding: CbcPaddingRaw,
     },
     DecryptAesCbcExternalKey {
         key_data: *const u8,
@@ -222,11 +259,14 @@ pub enum RequestDataRaw {
         iv_size: u32,
         buffer_data: *mut u8,
         buffer_size: u32,
+        padding: CbcPaddingRaw,
     },
     CalculateAesCmac {
         key_id: KeyIdRaw,
         message_data: *const u8,
         message_size: u32,
+        message_type_data: *const u8,
+        message_type_size: u32,
         tag_data: *mut u8,
         tag_size: u32,
     },
@@ -242,6 +282,8 @@ pub enum RequestDataRaw {
         key_id: KeyIdRaw,
         message_data: *const u8,
         message_size: u32,
+        message_type_data: *const u8,
+        message_type_size: u32,
         tag_data: *const u8,
         tag_size: u32,
     },
@@ -258,6 +300,8 @@ pub enum RequestDataRaw {
         hash_algorithm: HashAlgorithmRaw,
         message_data: *const u8,
         message_size: u32,
+        message_type_data: *const u8,
+        message_type_size: u32,
         tag_data: *mut u8,
         tag_size: u32,
     },
@@ -275,6 +319,8 @@ pub enum RequestDataRaw {
         hash_algorithm: HashAlgorithmRaw,
         message_data: *const u8,
         message_size: u32,
+        message_type_data: *const u8,
+        message_type_size: u32,
         tag_data: *const u8,
         tag_size: u32,
     },
@@ -311,6 +357,7 @@ pub enum RequestDataRaw {
         prehashed: BoolRaw,
         signature_data: *const u8,
         signature_size: u32,
+        encoding: SignatureEncodingRaw,
     },
     VerifyExternalKey {
         key_data: *const u8,
@@ -320,6 +367,7 @@ pub enum RequestDataRaw {
         prehashed: BoolRaw,
         signature_data: *const u8,
         signature_size: u32,
+        encoding: SignatureEncodingRaw,
     },
     Ecdh {
         public_key_data: *const u8,
@@ -337,6 +385,101 @@ pub enum RequestDataRaw {
         shared_secret_data: *mut u8,
         shared_secret_size: u32,
     },
+    Rekey {
+        key_id: KeyIdRaw,
+    },
+    EmergencyErase {},
+    KeyFingerprint {
+        key_id: KeyIdRaw,
+        hash_algorithm: HashAlgorithmRaw,
+        fingerprint_data: *mut u8,
+        fingerprint_size: u32,
+    },
+    BackupKeystore {
+        kek_id: KeyIdRaw,
+        iv_data: *const u8,
+        iv_size: u32,
+        blob_data: *mut u8,
+        blob_size: u32,
+    },
+    RestoreKeystore {
+        kek_id: KeyIdRaw,
+        blob_data: *mut u8,
+        blob_size: u32,
+    },
+    ListKeys {
+        start: KeyIdRaw,
+        ids_data: *mut KeyIdRaw,
+        ids_size: u32,
+    },
+    EcdhHkdf {
+        private_key_id: KeyIdRaw,
+        public_key_data: *const u8,
+        public_key_size: u32,
+        salt_data: *const u8,
+        salt_size: u32,
+        info_data: *const u8,
+        info_size: u32,
+        key_id: KeyIdRaw,
+        overwrite: BoolRaw,
+    },
+    GenerateHmacKey {
+        key_id: KeyIdRaw,
+        hash_algorithm: HashAlgorithmRaw,
+        overwrite: BoolRaw,
+    },
+    Attest {
+        key_id: KeyIdRaw,
+        nonce_data: *const u8,
+        nonce_size: u32,
+        statement_data: *mut u8,
+        statement_size: u32,
+        signature_data: *mut u8,
+        signature_size: u32,
+    },
+    Version {},
+    AeadOutputLen {
+        algorithm: AeadAlgorithmRaw,
+        plaintext_len: u32,
+    },
+    Hash {
+        hash_algorithm: HashAlgorithmRaw,
+        data_data: *const u8,
+        data_size: u32,
+        digest_data: *mut u8,
+        digest_size: u32,
+    },
+    VerifyKeyMatches {
+        private_key_id: KeyIdRaw,
+        expected_public_key_data: *const u8,
+        expected_public_key_size: u32,
+    },
+    HashMulti {
+        hash_algorithms: HashAlgorithmsRaw,
+        data_data: *const u8,
+        data_size: u32,
+        digests_data: *mut u8,
+        digests_size: u32,
+    },
+    EncryptAesGcmRandomNonce {
+        key_id: KeyIdRaw,
+        iv_data: *mut u8,
+        iv_size: u32,
+        buffer_data: *mut u8,
+        buffer_size: u32,
+        aad_data: *const u8,
+        aad_size: u32,
+        tag_data: *mut u8,
+        tag_size: u32,
+    },
+    /// Only the first `segments_count` entries of `segments` are used.
+    HashSegments {
+        hash_algorithm: HashAlgorithmRaw,
+        segments: [SegmentRaw; MAX_HASH_SEGMENTS],
+        segments_count: u32,
+        digest_data: *mut u8,
+        digest_size: u32,
+    },
 }
 
 /// Raw response as it is written by clients to shared memory. This type is supposed to be synced
@@ -433,6 +576,69 @@ pub enum ResponseDataRaw {
         shared_secret_data: *mut u8,
         shared_secret_size: u32,
     },
+    Rekey {},
+    EmergencyErase {},
+    KeyFingerprint {
+        fingerprint_data: *mut u8,
+        fingerprint_size: u32,
+    },
+    BackupKeystore {
+        blob_data: *mut u8,
+        blob_size: u32,
+        skipped: u32,
+    },
+    RestoreKeystore {
+        restored: u32,
+    },
+    KeyList {
+        ids_data: *mut KeyIdRaw,
+        ids_size: u32,
+        has_next: BoolRaw,
+        next: KeyIdRaw,
+    },
+    EcdhHkdf {},
+    GenerateHmacKey {
+        key_id: KeyIdRaw,
+    },
+    Attest {
+        statement_data: *mut u8,
+        statement_size: u32,
+        signature_data: *mut u8,
+        signature_size: u32,
+    },
+    Version {
+        crate_version_data: *const u8,
+        crate_version_size: u32,
+        protocol_version: u32,
+        features: u32,
+    },
+    AeadOutputLen {
+        ciphertext_len: u32,
+        tag_len: u32,
+    },
+    Hash {
+        digest_data: *mut u8,
+        digest_size: u32,
+    },
+    VerifyKeyMatches {
+        matches: BoolRaw,
+    },
+    HashMulti {
+        digests_data: *mut u8,
+        digests_size: u32,
+    },
+    EncryptAesGcmRandomNonce {
+        iv_data: *mut u8,
+        iv_size: u32,
+        buffer_data: *mut u8,
+        buffer_size: u32,
+        tag_data: *mut u8,
+        tag_size: u32,
+    },
+    HashSegments {
+        digest_data: *mut u8,
+        digest_size: u32,
+    },
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -502,19 +708,27 @@ impl RequestRaw {
                 request_id,
                 output: check_mut_pointer_and_size(output_data, output_size, &validator)?,
             },
-            RequestDataRaw::GenerateSymmetricKey { key_id, overwrite } => {
-                Request::GenerateSymmetricKey {
-                    client_id,
-                    request_id,
-                    key_id: key_id.into(),
-                    overwrite: bool_raw_to_bool(overwrite),
-                }
-            }
-            RequestDataRaw::GenerateKeyPair { key_id, overwrite } => Request::GenerateKeyPair {
+            RequestDataRaw::GenerateSymmetricKey {
+                key_id,
+                overwrite,
+                no_response,
+            } => Request::GenerateSymmetricKey {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+                overwrite: bool_raw_to_bool(overwrite)?,
+                no_response: bool_raw_to_bool(no_response)?,
+            },
+            RequestDataRaw::GenerateKeyPair {
+                key_id,
+                overwrite,
+                no_response,
+            } => Request::GenerateKeyPair {
                 client_id,
                 request_id,
                 key_id: key_id.into(),
-                overwrite: bool_raw_to_bool(overwrite),
+                overwrite: bool_raw_to_bool(overwrite)?,
+                no_response: bool_raw_to_bool(no_response)?,
             },
             RequestDataRaw::ImportSymmetricKey {
                 key_id,
@@ -526,7 +740,7 @@ impl RequestRaw {
                 request_id,
                 key_id: key_id.into(),
                 data: check_pointer_and_size(data_data, data_size, &validator)?,
-                overwrite: bool_raw_to_bool(overwrite),
+                overwrite: bool_raw_to_bool(overwrite)?,
             },
             RequestDataRaw::ImportKeyPair {
                 key_id,
@@ -545,7 +759,7 @@ impl RequestRaw {
                     private_key_size,
                     &validator,
                 )?,
-                overwrite: bool_raw_to_bool(overwrite),
+                overwrite: bool_raw_to_bool(overwrite)?,
             },
             RequestDataRaw::ExportSymmetricKey {
                 key_id,
@@ -561,6 +775,7 @@ impl RequestRaw {
                 key_id,
                 public_key_data,
                 public_key_size,
+                encoding,
             } => Request::ExportPublicKey {
                 client_id,
                 request_id,
@@ -570,6 +785,7 @@ impl RequestRaw {
                     public_key_size,
                     &validator,
                 )?,
+                encoding: encoding.try_into()?,
             },
             RequestDataRaw::ExportPrivateKey {
                 key_id,
@@ -687,6 +903,25 @@ impl RequestRaw {
                 aad: check_pointer_and_size(aad_data, aad_size, &validator)?,
                 tag: check_mut_pointer_and_size(tag_data, tag_size, &validator)?,
             },
+            RequestDataRaw::EncryptAesGcmRandomNonce {
+                key_id,
+                iv_data,
+                iv_size,
+                buffer_data,
+                buffer_size,
+                aad_data,
+                aad_size,
+                tag_data,
+                tag_size,
+            } => Request::EncryptAesGcmRandomNonce {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+                iv: check_mut_pointer_and_size(iv_data, iv_size, &validator)?,
+                buffer: check_mut_pointer_and_size(buffer_data, buffer_size, &validator)?,
+                aad: check_pointer_and_size(aad_data, aad_size, &validator)?,
+                tag: check_mut_pointer_and_size(tag_data, tag_size, &validator)?,
+            },
             RequestDataRaw::EncryptAesGcmExternalKey {
                 key_data,
                 key_size,
@@ -752,6 +987,7 @@ impl RequestRaw {
                 iv_size,
                 buffer_data,
                 buffer_size,
+                padding,
                 plaintext_size,
             } => Request::EncryptAesCbc {
                 client_id,
@@ -759,6 +995,7 @@ impl RequestRaw {
                 key_id: key_id.into(),
                 iv: check_pointer_and_size(iv_data, iv_size, &validator)?,
                 buffer: check_mut_pointer_and_size(buffer_data, buffer_size, &validator)?,
+                padding: padding.try_into()?,
                 plaintext_size: plaintext_size as usize,
             },
             RequestDataRaw::EncryptAesCbcExternalKey {
@@ -768,6 +1005,7 @@ impl RequestRaw {
                 iv_size,
                 buffer_data,
                 buffer_size,
+                padding,
                 plaintext_size,
             } => Request::EncryptAesCbcExternalKey {
                 client_id,
@@ -775,6 +1013,7 @@ impl RequestRaw {
                 key: check_pointer_and_size(key_data, key_size, &validator)?,
                 iv: check_pointer_and_size(iv_data, iv_size, &validator)?,
                 buffer: check_mut_pointer_and_size(buffer_data, buffer_size, &validator)?,
+                padding: padding.try_into()?,
                 plaintext_size: plaintext_size as usize,
             },
             RequestDataRaw::DecryptAesCbc {
@@ -783,12 +1022,14 @@ impl RequestRaw {
                 iv_size,
                 buffer_data,
                 buffer_size,
+                padding,
             } => Request::DecryptAesCbc {
                 client_id,
                 request_id,
                 key_id: key_id.into(),
                 iv: check_pointer_and_size(iv_data, iv_size, &validator)?,
                 buffer: check_mut_pointer_and_size(buffer_data, buffer_size, &validator)?,
+                padding: padding.try_into()?,
             },
             RequestDataRaw::DecryptAesCbcExternalKey {
                 key_data,
@@ -797,17 +1038,21 @@ impl RequestRaw {
                 iv_size,
                 buffer_data,
                 buffer_size,
+                padding,
             } => Request::DecryptAesCbcExternalKey {
                 client_id,
                 request_id,
                 key: check_pointer_and_size(key_data, key_size, &validator)?,
                 iv: check_pointer_and_size(iv_data, iv_size, &validator)?,
                 buffer: check_mut_pointer_and_size(buffer_data, buffer_size, &validator)?,
+                padding: padding.try_into()?,
             },
             RequestDataRaw::CalculateAesCmac {
                 key_id,
                 message_data,
                 message_size,
+                message_type_data,
+                message_type_size,
                 tag_data,
                 tag_size,
             } => Request::CalculateAesCmac {
@@ -815,6 +1060,11 @@ impl RequestRaw {
                 request_id,
                 key_id: key_id.into(),
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
+                message_type: check_pointer_and_size(
+                    message_type_data,
+                    message_type_size,
+                    &validator,
+                )?,
                 tag: check_mut_pointer_and_size(tag_data, tag_size, &validator)?,
             },
             RequestDataRaw::CalculateAesCmacExternalKey {
@@ -835,6 +1085,8 @@ impl RequestRaw {
                 key_id,
                 message_data,
                 message_size,
+                message_type_data,
+                message_type_size,
                 tag_data,
                 tag_size,
             } => Request::VerifyAesCmac {
@@ -842,6 +1094,11 @@ impl RequestRaw {
                 request_id,
                 key_id: key_id.into(),
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
+                message_type: check_pointer_and_size(
+                    message_type_data,
+                    message_type_size,
+                    &validator,
+                )?,
                 tag: check_pointer_and_size(tag_data, tag_size, &validator)?,
             },
             RequestDataRaw::VerifyAesCmacExternalKey {
@@ -863,6 +1120,8 @@ impl RequestRaw {
                 hash_algorithm,
                 message_data,
                 message_size,
+                message_type_data,
+                message_type_size,
                 tag_data,
                 tag_size,
             } => Request::CalculateHmac {
@@ -871,6 +1130,11 @@ impl RequestRaw {
                 key_id: key_id.into(),
                 hash_algorithm: hash_algorithm.try_into()?,
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
+                message_type: check_pointer_and_size(
+                    message_type_data,
+                    message_type_size,
+                    &validator,
+                )?,
                 tag: check_mut_pointer_and_size(tag_data, tag_size, &validator)?,
             },
             RequestDataRaw::CalculateHmacExternalKey {
@@ -894,6 +1158,8 @@ impl RequestRaw {
                 hash_algorithm,
                 message_data,
                 message_size,
+                message_type_data,
+                message_type_size,
                 tag_data,
                 tag_size,
             } => Request::VerifyHmac {
@@ -902,6 +1168,11 @@ impl RequestRaw {
                 key_id: key_id.into(),
                 hash_algorithm: hash_algorithm.try_into()?,
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
+                message_type: check_pointer_and_size(
+                    message_type_data,
+                    message_type_size,
+                    &validator,
+                )?,
                 tag: check_pointer_and_size(tag_data, tag_size, &validator)?,
             },
             RequestDataRaw::VerifyHmacExternalKey {
@@ -932,7 +1203,7 @@ impl RequestRaw {
                 request_id,
                 key_id: key_id.into(),
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
-                prehashed: bool_raw_to_bool(prehashed),
+                prehashed: bool_raw_to_bool(prehashed)?,
                 signature: check_mut_pointer_and_size(signature_data, signature_size, &validator)?,
             },
             RequestDataRaw::SignExternalKey {
@@ -948,7 +1219,7 @@ impl RequestRaw {
                 request_id,
                 private_key: check_pointer_and_size(key_data, key_size, &validator)?,
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
-                prehashed: bool_raw_to_bool(prehashed),
+                prehashed: bool_raw_to_bool(prehashed)?,
                 signature: check_mut_pointer_and_size(signature_data, signature_size, &validator)?,
             },
             RequestDataRaw::Verify {
@@ -958,13 +1229,15 @@ impl RequestRaw {
                 prehashed,
                 signature_data,
                 signature_size,
+                encoding,
             } => Request::Verify {
                 client_id,
                 request_id,
                 key_id: key_id.into(),
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
-                prehashed: bool_raw_to_bool(prehashed),
+                prehashed: bool_raw_to_bool(prehashed)?,
                 signature: check_pointer_and_size(signature_data, signature_size, &validator)?,
+                encoding: encoding.try_into()?,
             },
             RequestDataRaw::VerifyExternalKey {
                 key_data,
@@ -974,13 +1247,15 @@ impl RequestRaw {
                 prehashed,
                 signature_data,
                 signature_size,
+                encoding,
             } => Request::VerifyExternalKey {
                 client_id,
                 request_id,
                 public_key: check_pointer_and_size(key_data, key_size, &validator)?,
                 message: check_pointer_and_size(message_data, message_size, &validator)?,
-                prehashed: bool_raw_to_bool(prehashed),
+                prehashed: bool_raw_to_bool(prehashed)?,
                 signature: check_pointer_and_size(signature_data, signature_size, &validator)?,
+                encoding: encoding.try_into()?,
             },
             RequestDataRaw::Ecdh {
                 public_key_data,
@@ -1023,6 +1298,177 @@ impl RequestRaw {
                     &validator,
                 )?,
             },
+            RequestDataRaw::Rekey { key_id } => Request::Rekey {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+            },
+            RequestDataRaw::EmergencyErase {} => Request::EmergencyErase {
+                client_id,
+                request_id,
+            },
+            RequestDataRaw::KeyFingerprint {
+                key_id,
+                hash_algorithm,
+                fingerprint_data,
+                fingerprint_size,
+            } => Request::KeyFingerprint {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+                hash_algorithm: hash_algorithm.try_into()?,
+                fingerprint: check_mut_pointer_and_size(
+                    fingerprint_data,
+                    fingerprint_size,
+                    &validator,
+                )?,
+            },
+            RequestDataRaw::BackupKeystore {
+                kek_id,
+                iv_data,
+                iv_size,
+                blob_data,
+                blob_size,
+            } => Request::BackupKeystore {
+                client_id,
+                request_id,
+                kek_id: kek_id.into(),
+                iv: check_pointer_and_size(iv_data, iv_size, &validator)?,
+                blob: check_mut_pointer_and_size(blob_data, blob_size, &validator)?,
+            },
+            RequestDataRaw::RestoreKeystore {
+                kek_id,
+                blob_data,
+                blob_size,
+            } => Request::RestoreKeystore {
+                client_id,
+                request_id,
+                kek_id: kek_id.into(),
+                blob: check_mut_pointer_and_size(blob_data, blob_size, &validator)?,
+            },
+            RequestDataRaw::ListKeys {
+                start,
+                ids_data,
+                ids_size,
+            } => Request::ListKeys {
+                client_id,
+                request_id,
+                start: start.into(),
+                ids: check_mut_key_ids_pointer_and_size(ids_data, ids_size, &validator)?,
+            },
+            RequestDataRaw::EcdhHkdf {
+                private_key_id,
+                public_key_data,
+                public_key_size,
+                salt_data,
+                salt_size,
+                info_data,
+                info_size,
+                key_id,
+                overwrite,
+            } => Request::EcdhHkdf {
+                client_id,
+                request_id,
+                private_key_id: private_key_id.into(),
+                public_key: check_pointer_and_size(public_key_data, public_key_size, &validator)?,
+                salt: check_pointer_and_size(salt_data, salt_size, &validator)?,
+                info: check_pointer_and_size(info_data, info_size, &validator)?,
+                key_id: key_id.into(),
+                overwrite: bool_raw_to_bool(overwrite)?,
+            },
+            RequestDataRaw::GenerateHmacKey {
+                key_id,
+                hash_algorithm,
+                overwrite,
+            } => Request::GenerateHmacKey {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+                hash_algorithm: hash_algorithm.try_into()?,
+                overwrite: bool_raw_to_bool(overwrite)?,
+            },
+            RequestDataRaw::Attest {
+                key_id,
+                nonce_data,
+                nonce_size,
+                statement_data,
+                statement_size,
+                signature_data,
+                signature_size,
+            } => Request::Attest {
+                client_id,
+                request_id,
+                key_id: key_id.into(),
+                nonce: check_pointer_and_size(nonce_data, nonce_size, &validator)?,
+                statement: check_mut_pointer_and_size(statement_data, statement_size, &validator)?,
+                signature: check_mut_pointer_and_size(signature_data, signature_size, &validator)?,
+            },
+            RequestDataRaw::Version {} => Request::Version {
+                client_id,
+                request_id,
+            },
+            RequestDataRaw::AeadOutputLen {
+                algorithm,
+                plaintext_len,
+            } => Request::AeadOutputLen {
+                client_id,
+                request_id,
+                algorithm: algorithm.try_into()?,
+                plaintext_len: plaintext_len as usize,
+            },
+            RequestDataRaw::Hash {
+                hash_algorithm,
+                data_data,
+                data_size,
+                digest_data,
+                digest_size,
+            } => Request::Hash {
+                client_id,
+                request_id,
+                hash_algorithm: hash_algorithm.try_into()?,
+                data: check_pointer_and_size(data_data, data_size, &validator)?,
+                digest: check_mut_pointer_and_size(digest_data, digest_size, &validator)?,
+            },
+            RequestDataRaw::VerifyKeyMatches {
+                private_key_id,
+                expected_public_key_data,
+                expected_public_key_size,
+            } => Request::VerifyKeyMatches {
+                client_id,
+                request_id,
+                private_key_id: private_key_id.into(),
+                expected_public_key: check_pointer_and_size(
+                    expected_public_key_data,
+                    expected_public_key_size,
+                    &validator,
+                )?,
+            },
+            RequestDataRaw::HashMulti {
+                hash_algorithms,
+                data_data,
+                data_size,
+                digests_data,
+                digests_size,
+            } => Request::HashMulti {
+                client_id,
+                request_id,
+                hash_algorithms: hash_algorithms.try_into()?,
+                data: check_pointer_and_size(data_data, data_size, &validator)?,
+                digests: check_mut_pointer_and_size(digests_data, digests_size, &validator)?,
+            },
+            RequestDataRaw::HashSegments {
+                hash_algorithm,
+                segments,
+                segments_count,
+                digest_data,
+                digest_size,
+            } => Request::HashSegments {
+                client_id,
+                request_id,
+                hash_algorithm: hash_algorithm.try_into()?,
+                segments: check_segments(&segments, segments_count, &validator)?,
+                digest: check_mut_pointer_and_size(digest_data, digest_size, &validator)?,
+            },
         };
         Ok(request)
     }
@@ -1049,12 +1495,14 @@ impl From<Request<'_>> for RequestRaw {
                 request_id,
                 key_id,
                 overwrite,
+                no_response,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
                 data: RequestDataRaw::GenerateSymmetricKey {
                     key_id: key_id.into(),
                     overwrite: overwrite.into(),
+                    no_response: no_response.into(),
                 },
             },
             Request::GenerateKeyPair {
@@ -1062,12 +1510,14 @@ impl From<Request<'_>> for RequestRaw {
                 request_id,
                 key_id,
                 overwrite,
+                no_response,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
                 data: RequestDataRaw::GenerateKeyPair {
                     key_id: key_id.into(),
                     overwrite: overwrite.into(),
+                    no_response: no_response.into(),
                 },
             },
             Request::ImportSymmetricKey {
@@ -1124,6 +1574,7 @@ impl From<Request<'_>> for RequestRaw {
                 request_id,
                 key_id,
                 public_key,
+                encoding,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
@@ -1131,6 +1582,7 @@ impl From<Request<'_>> for RequestRaw {
                     key_id: key_id.into(),
                     public_key_data: public_key.as_mut_ptr(),
                     public_key_size: public_key.len() as u32,
+                    encoding: encoding.into(),
                 },
             },
             Request::ExportPrivateKey {
@@ -1275,6 +1727,29 @@ impl From<Request<'_>> for RequestRaw {
                     tag_size: tag.len() as u32,
                 },
             },
+            Request::EncryptAesGcmRandomNonce {
+                client_id,
+                request_id,
+                key_id,
+                iv,
+                buffer,
+                aad,
+                tag,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::EncryptAesGcmRandomNonce {
+                    key_id: key_id.into(),
+                    iv_data: iv.as_mut_ptr(),
+                    iv_size: iv.len() as u32,
+                    buffer_data: buffer.as_mut_ptr(),
+                    buffer_size: buffer.len() as u32,
+                    aad_data: aad.as_ptr(),
+                    aad_size: aad.len() as u32,
+                    tag_data: tag.as_mut_ptr(),
+                    tag_size: tag.len() as u32,
+                },
+            },
             Request::EncryptAesGcmExternalKey {
                 client_id,
                 request_id,
@@ -1352,6 +1827,7 @@ impl From<Request<'_>> for RequestRaw {
                 key_id,
                 iv,
                 buffer,
+                padding,
                 plaintext_size,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1362,6 +1838,7 @@ impl From<Request<'_>> for RequestRaw {
                     iv_size: iv.len() as u32,
                     buffer_data: buffer.as_mut_ptr(),
                     buffer_size: buffer.len() as u32,
+                    padding: padding.into(),
                     plaintext_size: plaintext_size as u32,
                 },
             },
@@ -1371,6 +1848,7 @@ impl From<Request<'_>> for RequestRaw {
                 key,
                 iv,
                 buffer,
+                padding,
                 plaintext_size,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1382,6 +1860,7 @@ impl From<Request<'_>> for RequestRaw {
                     iv_size: iv.len() as u32,
                     buffer_data: buffer.as_mut_ptr(),
                     buffer_size: buffer.len() as u32,
+                    padding: padding.into(),
                     plaintext_size: plaintext_size as u32,
                 },
             },
@@ -1391,6 +1870,7 @@ impl From<Request<'_>> for RequestRaw {
                 key_id,
                 iv,
                 buffer,
+                padding,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
@@ -1400,6 +1880,7 @@ impl From<Request<'_>> for RequestRaw {
                     iv_size: iv.len() as u32,
                     buffer_data: buffer.as_mut_ptr(),
                     buffer_size: buffer.len() as u32,
+                    padding: padding.into(),
                 },
             },
             Request::DecryptAesCbcExternalKey {
@@ -1408,6 +1889,7 @@ impl From<Request<'_>> for RequestRaw {
                 key,
                 iv,
                 buffer,
+                padding,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
@@ -1418,6 +1900,7 @@ impl From<Request<'_>> for RequestRaw {
                     iv_size: iv.len() as u32,
                     buffer_data: buffer.as_mut_ptr(),
                     buffer_size: buffer.len() as u32,
+                    padding: padding.into(),
                 },
             },
             Request::CalculateAesCmac {
@@ -1425,6 +1908,7 @@ impl From<Request<'_>> for RequestRaw {
                 request_id,
                 key_id,
                 message,
+                message_type,
                 tag,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1433,6 +1917,8 @@ impl From<Request<'_>> for RequestRaw {
                     key_id: key_id.into(),
                     message_data: message.as_ptr(),
                     message_size: message.len() as u32,
+                    message_type_data: message_type.as_ptr(),
+                    message_type_size: message_type.len() as u32,
                     tag_data: tag.as_mut_ptr(),
                     tag_size: tag.len() as u32,
                 },
@@ -1460,6 +1946,7 @@ impl From<Request<'_>> for RequestRaw {
                 request_id,
                 key_id,
                 message,
+                message_type,
                 tag,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1468,6 +1955,8 @@ impl From<Request<'_>> for RequestRaw {
                     key_id: key_id.into(),
                     message_data: message.as_ptr(),
                     message_size: message.len() as u32,
+                    message_type_data: message_type.as_ptr(),
+                    message_type_size: message_type.len() as u32,
                     tag_data: tag.as_ptr(),
                     tag_size: tag.len() as u32,
                 },
@@ -1496,6 +1985,7 @@ impl From<Request<'_>> for RequestRaw {
                 key_id,
                 hash_algorithm,
                 message,
+                message_type,
                 tag,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1505,6 +1995,8 @@ impl From<Request<'_>> for RequestRaw {
                     hash_algorithm: hash_algorithm.into(),
                     message_data: message.as_ptr(),
                     message_size: message.len() as u32,
+                    message_type_data: message_type.as_ptr(),
+                    message_type_size: message_type.len() as u32,
                     tag_data: tag.as_mut_ptr(),
                     tag_size: tag.len() as u32,
                 },
@@ -1535,6 +2027,7 @@ impl From<Request<'_>> for RequestRaw {
                 key_id,
                 hash_algorithm,
                 message,
+                message_type,
                 tag,
             } => RequestRaw {
                 client_id: client_id.into(),
@@ -1544,6 +2037,8 @@ impl From<Request<'_>> for RequestRaw {
                     hash_algorithm: hash_algorithm.into(),
                     message_data: message.as_ptr(),
                     message_size: message.len() as u32,
+                    message_type_data: message_type.as_ptr(),
+                    message_type_size: message_type.len() as u32,
                     tag_data: tag.as_ptr(),
                     tag_size: tag.len() as u32,
                 },
@@ -1614,6 +2109,7 @@ impl From<Request<'_>> for RequestRaw {
                 message,
                 prehashed,
                 signature,
+                encoding,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
@@ -1624,6 +2120,7 @@ impl From<Request<'_>> for RequestRaw {
                     prehashed: prehashed.into(),
                     signature_data: signature.as_ptr(),
                     signature_size: signature.len() as u32,
+                    encoding: encoding.into(),
                 },
             },
             Request::VerifyExternalKey {
@@ -1633,6 +2130,7 @@ impl From<Request<'_>> for RequestRaw {
                 message,
                 prehashed,
                 signature,
+                encoding,
             } => RequestRaw {
                 client_id: client_id.into(),
                 request_id: request_id.into(),
@@ -1644,6 +2142,7 @@ impl From<Request<'_>> for RequestRaw {
                     prehashed: prehashed.into(),
                     signature_data: signature.as_ptr(),
                     signature_size: signature.len() as u32,
+                    encoding: encoding.into(),
                 },
             },
             Request::Ecdh {
@@ -1683,39 +2182,274 @@ impl From<Request<'_>> for RequestRaw {
                     shared_secret_size: shared_secret.len() as u32,
                 },
             },
-        }
-    }
-}
-
-// Outgoing direction. No validation needed.
-impl ResponseRaw {
-    /// Create a `ResponseRaw` from a raw pointer.
-    ///
-    /// # Arguments
-    ///
-    /// * `ptr`: The pointer to the first byte of the `ResponseRaw`
-    ///
-    /// # Safety
-    ///
-    /// The provided pointer must be valid (non-null, aligned, point to valid memory) and point to a
-    /// valid `ResponseRaw` instance.  
-    ///
-    pub unsafe fn from_raw(ptr: *const u8) -> Result<Self, ValidationError> {
-        // SAFETY: Pointer and size must be checked by integrator
-        let tag: u8 = unsafe { *(ptr.add(offset_of!(RequestRaw, data))) };
-
-        // Validate tag value. Invalid tag values cause UB when transmuted into an enum.
-        if tag >= ResponseDataRaw::COUNT as u8 {
-            return Err(ValidationError::InvalidTagValue);
-        }
-
-        // SAFETY: All members of RequestRaw are valid for all possible values found in memory
-        Ok(*ptr.cast::<ResponseRaw>())
-    }
-}
-
-impl From<Response<'_>> for ResponseRaw {
-    fn from(value: Response<'_>) -> Self {
+            Request::Rekey {
+                client_id,
+                request_id,
+                key_id,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::Rekey {
+                    key_id: key_id.into(),
+                },
+            },
+            Request::EmergencyErase {
+                client_id,
+                request_id,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::EmergencyErase {},
+            },
+            Request::KeyFingerprint {
+                client_id,
+                request_id,
+                key_id,
+                hash_algorithm,
+                fingerprint,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::KeyFingerprint {
+                    key_id: key_id.into(),
+                    hash_algorithm: hash_algorithm.into(),
+                    fingerprint_data: fingerprint.as_mut_ptr(),
+                    fingerprint_size: fingerprint.len() as u32,
+                },
+            },
+            Request::BackupKeystore {
+                client_id,
+                request_id,
+                kek_id,
+                iv,
+                blob,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::BackupKeystore {
+                    kek_id: kek_id.into(),
+                    iv_data: iv.as_ptr(),
+                    iv_size: iv.len() as u32,
+                    blob_data: blob.as_mut_ptr(),
+                    blob_size: blob.len() as u32,
+                },
+            },
+            Request::RestoreKeystore {
+                client_id,
+                request_id,
+                kek_id,
+                blob,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::RestoreKeystore {
+                    kek_id: kek_id.into(),
+                    blob_data: blob.as_mut_ptr(),
+                    blob_size: blob.len() as u32,
+                },
+            },
+            Request::ListKeys {
+                client_id,
+                request_id,
+                start,
+                ids,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::ListKeys {
+                    start: start.into(),
+                    ids_data: ids.as_mut_ptr().cast(),
+                    ids_size: ids.len() as u32,
+                },
+            },
+            Request::EcdhHkdf {
+                client_id,
+                request_id,
+                private_key_id,
+                public_key,
+                salt,
+                info,
+                key_id,
+                overwrite,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::EcdhHkdf {
+                    private_key_id: private_key_id.into(),
+                    public_key_data: public_key.as_ptr(),
+                    public_key_size: public_key.len() as u32,
+                    salt_data: salt.as_ptr(),
+                    salt_size: salt.len() as u32,
+                    info_data: info.as_ptr(),
+                    info_size: info.len() as u32,
+                    key_id: key_id.into(),
+                    overwrite: overwrite.into(),
+                },
+            },
+            Request::GenerateHmacKey {
+                client_id,
+                request_id,
+                key_id,
+                hash_algorithm,
+                overwrite,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::GenerateHmacKey {
+                    key_id: key_id.into(),
+                    hash_algorithm: hash_algorithm.into(),
+                    overwrite: overwrite.into(),
+                },
+            },
+            Request::Attest {
+                client_id,
+                request_id,
+                key_id,
+                nonce,
+                statement,
+                signature,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::Attest {
+                    key_id: key_id.into(),
+                    nonce_data: nonce.as_ptr(),
+                    nonce_size: nonce.len() as u32,
+                    statement_data: statement.as_mut_ptr(),
+                    statement_size: statement.len() as u32,
+                    signature_data: signature.as_mut_ptr(),
+                    signature_size: signature.len() as u32,
+                },
+            },
+            Request::Version {
+                client_id,
+                request_id,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::Version {},
+            },
+            Request::AeadOutputLen {
+                client_id,
+                request_id,
+                algorithm,
+                plaintext_len,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::AeadOutputLen {
+                    algorithm: algorithm.into(),
+                    plaintext_len: plaintext_len as u32,
+                },
+            },
+            Request::Hash {
+                client_id,
+                request_id,
+                hash_algorithm,
+                data,
+                digest,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::Hash {
+                    hash_algorithm: hash_algorithm.into(),
+                    data_data: data.as_ptr(),
+                    data_size: data.len() as u32,
+                    digest_data: digest.as_mut_ptr(),
+                    digest_size: digest.len() as u32,
+                },
+            },
+            Request::VerifyKeyMatches {
+                client_id,
+                request_id,
+                private_key_id,
+                expected_public_key,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::VerifyKeyMatches {
+                    private_key_id: private_key_id.into(),
+                    expected_public_key_data: expected_public_key.as_ptr(),
+                    expected_public_key_size: expected_public_key.len() as u32,
+                },
+            },
+            Request::HashMulti {
+                client_id,
+                request_id,
+                hash_algorithms,
+                data,
+                digests,
+            } => RequestRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: RequestDataRaw::HashMulti {
+                    hash_algorithms: hash_algorithms.into(),
+                    data_data: data.as_ptr(),
+                    data_size: data.len() as u32,
+                    digests_data: digests.as_mut_ptr(),
+                    digests_size: digests.len() as u32,
+                },
+            },
+            Request::HashSegments {
+                client_id,
+                request_id,
+                hash_algorithm,
+                segments,
+                digest,
+            } => {
+                let mut segments_raw = [SegmentRaw {
+                    data: ptr::null(),
+                    size: 0,
+                }; MAX_HASH_SEGMENTS];
+                for (segment_raw, segment) in segments_raw.iter_mut().zip(&segments) {
+                    segment_raw.data = segment.as_ptr();
+                    segment_raw.size = segment.len() as u32;
+                }
+                RequestRaw {
+                    client_id: client_id.into(),
+                    request_id: request_id.into(),
+                    data: RequestDataRaw::HashSegments {
+                        hash_algorithm: hash_algorithm.into(),
+                        segments: segments_raw,
+                        segments_count: segments.len() as u32,
+                        digest_data: digest.as_mut_ptr(),
+                        digest_size: digest.len() as u32,
+                    },
+                }
+            }
+        }
+    }
+}
+
+// Outgoing direction. No validation needed.
+impl ResponseRaw {
+    /// Create a `ResponseRaw` from a raw pointer.
+    ///
+    /// # Arguments
+    ///
+    /// * `ptr`: The pointer to the first byte of the `ResponseRaw`
+    ///
+    /// # Safety
+    ///
+    /// The provided pointer must be valid (non-null, aligned, point to valid memory) and point to a
+    /// valid `ResponseRaw` instance.  
+    ///
+    pub unsafe fn from_raw(ptr: *const u8) -> Result<Self, ValidationError> {
+        // SAFETY: Pointer and size must be checked by integrator
+        let tag: u8 = unsafe { *(ptr.add(offset_of!(RequestRaw, data))) };
+
+        // Validate tag value. Invalid tag values cause UB when transmuted into an enum.
+        if tag >= ResponseDataRaw::COUNT as u8 {
+            return Err(ValidationError::InvalidTagValue);
+        }
+
+        // SAFETY: All members of RequestRaw are valid for all possible values found in memory
+        Ok(*ptr.cast::<ResponseRaw>())
+    }
+}
+
+impl From<Response<'_>> for ResponseRaw {
+    fn from(value: Response<'_>) -> Self {
         match value {
             Response::Error {
                 client_id,
@@ -1861,6 +2595,24 @@ impl From<Response<'_>> for ResponseRaw {
                     tag_size: tag.len() as u32,
                 },
             },
+            Response::EncryptAesGcmRandomNonce {
+                client_id,
+                request_id,
+                iv,
+                buffer,
+                tag,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::EncryptAesGcmRandomNonce {
+                    iv_data: iv.as_mut_ptr(),
+                    iv_size: iv.len() as u32,
+                    buffer_data: buffer.as_mut_ptr(),
+                    buffer_size: buffer.len() as u32,
+                    tag_data: tag.as_mut_ptr(),
+                    tag_size: tag.len() as u32,
+                },
+            },
             Response::DecryptAesGcm {
                 client_id,
                 request_id,
@@ -1978,6 +2730,182 @@ impl From<Response<'_>> for ResponseRaw {
                     shared_secret_size: shared_secret.len() as u32,
                 },
             },
+            Response::Rekey {
+                client_id,
+                request_id,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::Rekey {},
+            },
+            Response::EmergencyErase {
+                client_id,
+                request_id,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::EmergencyErase {},
+            },
+            Response::KeyFingerprint {
+                client_id,
+                request_id,
+                fingerprint,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::KeyFingerprint {
+                    fingerprint_data: fingerprint.as_mut_ptr(),
+                    fingerprint_size: fingerprint.len() as u32,
+                },
+            },
+            Response::BackupKeystore {
+                client_id,
+                request_id,
+                blob,
+                skipped,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::BackupKeystore {
+                    blob_data: blob.as_mut_ptr(),
+                    blob_size: blob.len() as u32,
+                    skipped,
+                },
+            },
+            Response::RestoreKeystore {
+                client_id,
+                request_id,
+                restored,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::RestoreKeystore { restored },
+            },
+            Response::KeyList {
+                client_id,
+                request_id,
+                ids,
+                next,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::KeyList {
+                    ids_data: ids.as_mut_ptr().cast(),
+                    ids_size: ids.len() as u32,
+                    has_next: next.is_some().into(),
+                    next: next.unwrap_or_default().into(),
+                },
+            },
+            Response::EcdhHkdf {
+                client_id,
+                request_id,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::EcdhHkdf {},
+            },
+            Response::GenerateHmacKey {
+                client_id,
+                request_id,
+                key_id,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::GenerateHmacKey {
+                    key_id: key_id.into(),
+                },
+            },
+            Response::Attest {
+                client_id,
+                request_id,
+                statement,
+                signature,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::Attest {
+                    statement_data: statement.as_mut_ptr(),
+                    statement_size: statement.len() as u32,
+                    signature_data: signature.as_mut_ptr(),
+                    signature_size: signature.len() as u32,
+                },
+            },
+            Response::Version {
+                client_id,
+                request_id,
+                crate_version,
+                protocol_version,
+                features,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::Version {
+                    crate_version_data: crate_version.as_ptr(),
+                    crate_version_size: crate_version.len() as u32,
+                    protocol_version,
+                    features,
+                },
+            },
+            Response::AeadOutputLen {
+                client_id,
+                request_id,
+                ciphertext_len,
+                tag_len,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::AeadOutputLen {
+                    ciphertext_len: ciphertext_len as u32,
+                    tag_len: tag_len as u32,
+                },
+            },
+            Response::Hash {
+                client_id,
+                request_id,
+                digest,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::Hash {
+                    digest_data: digest.as_mut_ptr(),
+                    digest_size: digest.len() as u32,
+                },
+            },
+            Response::VerifyKeyMatches {
+                client_id,
+                request_id,
+                matches,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::VerifyKeyMatches {
+                    matches: matches.into(),
+                },
+            },
+            Response::HashMulti {
+                client_id,
+                request_id,
+                digests,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::HashMulti {
+                    digests_data: digests.as_mut_ptr(),
+                    digests_size: digests.len() as u32,
+                },
+            },
+            Response::HashSegments {
+                client_id,
+                request_id,
+                digest,
+            } => ResponseRaw {
+                client_id: client_id.into(),
+                request_id: request_id.into(),
+                data: ResponseDataRaw::HashSegments {
+                    digest_data: digest.as_mut_ptr(),
+                    digest_size: digest.len() as u32,
+                },
+            },
         }
     }
 }
@@ -2044,6 +2972,111 @@ impl TryFrom<HashAlgorithmRaw> for HashAlgorithm {
     }
 }
 
+impl From<HashAlgorithms> for HashAlgorithmsRaw {
+    fn from(value: HashAlgorithms) -> Self {
+        value.bits().into()
+    }
+}
+
+impl TryFrom<HashAlgorithmsRaw> for HashAlgorithms {
+    type Error = ValidationError;
+
+    fn try_from(value: HashAlgorithmsRaw) -> Result<Self, Self::Error> {
+        u8::try_from(value)
+            .ok()
+            .and_then(HashAlgorithms::from_bits)
+            .ok_or(ValidationError::InvalidValue)
+    }
+}
+
+impl From<CbcPadding> for CbcPaddingRaw {
+    fn from(value: CbcPadding) -> Self {
+        match value {
+            CbcPadding::Pkcs7 => PKCS7,
+            CbcPadding::Iso7816 => ISO7816,
+            CbcPadding::NoPadding => NO_PADDING,
+        }
+    }
+}
+
+impl TryFrom<CbcPaddingRaw> for CbcPadding {
+    type Error = ValidationError;
+
+    fn try_from(value: CbcPaddingRaw) -> Result<Self, Self::Error> {
+        match value {
+            PKCS7 => Ok(Self::Pkcs7),
+            ISO7816 => Ok(Self::Iso7816),
+            NO_PADDING => Ok(Self::NoPadding),
+            _ => Err(ValidationError::InvalidValue),
+        }
+    }
+}
+
+impl From<SignatureEncoding> for SignatureEncodingRaw {
+    fn from(value: SignatureEncoding) -> Self {
+        match value {
+            SignatureEncoding::Raw => SIGNATURE_ENCODING_RAW,
+            SignatureEncoding::Der => SIGNATURE_ENCODING_DER,
+        }
+    }
+}
+
+impl TryFrom<SignatureEncodingRaw> for SignatureEncoding {
+    type Error = ValidationError;
+
+    fn try_from(value: SignatureEncodingRaw) -> Result<Self, Self::Error> {
+        match value {
+            SIGNATURE_ENCODING_RAW => Ok(Self::Raw),
+            SIGNATURE_ENCODING_DER => Ok(Self::Der),
+            _ => Err(ValidationError::InvalidValue),
+        }
+    }
+}
+
+impl From<PublicKeyEncoding> for PublicKeyEncodingRaw {
+    fn from(value: PublicKeyEncoding) -> Self {
+        match value {
+            PublicKeyEncoding::Uncompressed => UNCOMPRESSED,
+            PublicKeyEncoding::Compressed => COMPRESSED,
+        }
+    }
+}
+
+impl TryFrom<PublicKeyEncodingRaw> for PublicKeyEncoding {
+    type Error = ValidationError;
+
+    fn try_from(value: PublicKeyEncodingRaw) -> Result<Self, Self::Error> {
+        match value {
+            UNCOMPRESSED => Ok(Self::Uncompressed),
+            COMPRESSED => Ok(Self::Compressed),
+            _ => Err(ValidationError::InvalidValue),
+        }
+    }
+}
+
+impl From<AeadAlgorithm> for AeadAlgorithmRaw {
+    fn from(value: AeadAlgorithm) -> Self {
+        match value {
+            AeadAlgorithm::AesGcm => AES_GCM,
+            AeadAlgorithm::AesCcm => AES_CCM,
+            AeadAlgorithm::ChaChaPoly => CHACHA_POLY,
+        }
+    }
+}
+
+impl TryFrom<AeadAlgorithmRaw> for AeadAlgorithm {
+    type Error = ValidationError;
+
+    fn try_from(value: AeadAlgorithmRaw) -> Result<Self, Self::Error> {
+        match value {
+            AES_GCM => Ok(Self::AesGcm),
+            AES_CCM => Ok(Self::AesCcm),
+            CHACHA_POLY => Ok(Self::ChaChaPoly),
+            _ => Err(ValidationError::InvalidValue),
+        }
+    }
+}
+
 /// Check an untrusted pointer and size pair using a provided validator function.
 fn check_pointer_and_size<'a>(
     data: *const u8,
@@ -2076,8 +3109,46 @@ fn check_mut_pointer_and_size<'a>(
     Ok(unsafe { slice::from_raw_parts_mut(data, size as usize) })
 }
 
-fn bool_raw_to_bool(overwrite: BoolRaw) -> bool {
-    overwrite != 0
+/// Check an untrusted pointer to a number of key IDs using a provided validator function.
+fn check_mut_key_ids_pointer_and_size<'a>(
+    data: *mut KeyIdRaw,
+    size: u32,
+    validator: &impl Fn(*const u8, u32) -> bool,
+) -> Result<&'a mut [KeyId], ValidationError> {
+    if !data.is_aligned() {
+        return Err(ValidationError::InvalidPointer);
+    }
+    let bytes = size
+        .checked_mul(size_of::<KeyIdRaw>() as u32)
+        .ok_or(ValidationError::InvalidPointer)?;
+    let data = check_mut_pointer_and_size(data.cast(), bytes, validator)?;
+    // SAFETY: `KeyId` is a transparent wrapper around `KeyIdRaw` and the pointer is aligned
+    Ok(unsafe { slice::from_raw_parts_mut(data.as_mut_ptr().cast(), size as usize) })
+}
+
+/// Check the first `count` untrusted segments using a provided validator function.
+fn check_segments<'a>(
+    segments: &[SegmentRaw],
+    count: u32,
+    validator: &impl Fn(*const u8, u32) -> bool,
+) -> Result<Segments<'a>, ValidationError> {
+    let segments = segments
+        .get(..count as usize)
+        .ok_or(ValidationError::InvalidValue)?;
+    segments
+        .iter()
+        .map(|segment| check_pointer_and_size(segment.data, segment.size, validator))
+        .collect()
+}
+
+/// Convert a raw boolean. Values other than 0 and 1 are rejected to catch clients with a different
+/// understanding of the field.
+fn bool_raw_to_bool(value: BoolRaw) -> Result<bool, ValidationError> {
+    match value {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(ValidationError::InvalidValue),
+    }
 }
 
 /// Function to trigger the generation of `RequestRaw` and `ResponseRaw` definition
@@ -2209,4 +3280,67 @@ mod test {
             },
         }
     }
+
+    #[test]
+    fn test_invalid_bool_value() {
+        let message = [0u8; 32];
+        let mut signature = [0u8; 64];
+        let signature_data = signature.as_mut_ptr();
+        let always_valid = |_data: *const u8, _size: u32| true;
+        let sign_request = |prehashed| RequestRaw {
+            client_id: 5,
+            request_id: 7,
+            data: RequestDataRaw::Sign {
+                key_id: 1,
+                message_data: message.as_ptr(),
+                message_size: message.len() as u32,
+                prehashed,
+                signature_data,
+                signature_size: signature.len() as u32,
+            },
+        };
+        assert!(matches!(
+            sign_request(1).verify(&always_valid),
+            Ok(Request::Sign {
+                prehashed: true,
+                ..
+            })
+        ));
+        assert!(matches!(
+            sign_request(2).verify(&always_valid),
+            Err(ValidationError::InvalidValue)
+        ));
+    }
+
+    #[test]
+    fn test_hash_segments() {
+        let header = [1u8; 4];
+        let body = [2u8; 8];
+        let mut digest = [0u8; 32];
+        let request = Request::HashSegments {
+            client_id: ClientId(5),
+            request_id: RequestId(7),
+            hash_algorithm: HashAlgorithm::Sha2_256,
+            segments: Segments::from_slice(&[&header, &body]).expect("too many segments"),
+            digest: &mut digest,
+        };
+        let mut request_raw: RequestRaw = request.into();
+        let always_valid = |_data: *const u8, _size: u32| true;
+        match request_raw.verify(&always_valid) {
+            Ok(Request::HashSegments { segments, .. }) => {
+                assert_eq!(segments.as_slice(), [&header[..], &body[..]]);
+            }
+            _ => panic!("Unexpected reconstructed request"),
+        }
+
+        // Segment count larger than the segment array
+        let RequestDataRaw::HashSegments { segments_count, .. } = &mut request_raw.data else {
+            panic!("Unexpected raw request type")
+        };
+        *segments_count = MAX_HASH_SEGMENTS as u32 + 1;
+        assert!(matches!(
+            request_raw.verify(&always_valid),
+            Err(ValidationError::InvalidValue)
+        ));
+    }
 }
@@ -4,8 +4,9 @@ mod common;
 pub use common::*;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use heimlig::{
-    client::api::SymmetricAlgorithm::AesCbc,
-    common::jobs::{RequestType, Response},
+    client::api::SymmetricAlgorithm::{AesCbc, AesGcm},
+    common::jobs::{CbcPadding, Error, RequestType, Response},
+    crypto,
     hsm::workers::aes_worker::AesWorker,
 };
 
@@ -36,7 +37,9 @@ async fn aes_cbc_encrypt_in_place() {
         &mut worker_responses,
         Some(&key_store),
     );
+    let rng = init_rng();
     let mut worker = AesWorker {
+        rng: &rng,
         key_store: &key_store,
         requests: req_worker_rx,
         responses: resp_worker_tx,
@@ -47,7 +50,7 @@ async fn aes_cbc_encrypt_in_place() {
     // Encrypt data with imported key
     let org_request_id = api
         .encrypt_in_place(
-            AesCbc,
+            AesCbc(CbcPadding::Pkcs7),
             SYM_256_KEY.id,
             &iv,
             plaintext_size,
@@ -70,7 +73,7 @@ async fn aes_cbc_encrypt_in_place() {
     // Encrypt data with external key
     let org_request_id = api
         .encrypt_in_place_external_key(
-            AesCbc,
+            AesCbc(CbcPadding::Pkcs7),
             &key,
             &iv,
             plaintext_size,
@@ -94,7 +97,14 @@ async fn aes_cbc_encrypt_in_place() {
 
     // Decrypt data with imported key
     let org_request_id = api
-        .decrypt_in_place(AesCbc, SYM_256_KEY.id, &iv, buffer, &[], &[])
+        .decrypt_in_place(
+            AesCbc(CbcPadding::Pkcs7),
+            SYM_256_KEY.id,
+            &iv,
+            buffer,
+            &[],
+            &[],
+        )
         .await
         .expect("failed to send request");
     let Response::DecryptAesCbc {
@@ -110,7 +120,14 @@ async fn aes_cbc_encrypt_in_place() {
 
     // Decrypt data with external key
     let org_request_id = api
-        .decrypt_in_place_external_key(AesCbc, &key, &iv, buffer_external_key, &[], &[])
+        .decrypt_in_place_external_key(
+            AesCbc(CbcPadding::Pkcs7),
+            &key,
+            &iv,
+            buffer_external_key,
+            &[],
+            &[],
+        )
         .await
         .expect("failed to send request");
     let Response::DecryptAesCbc {
@@ -124,3 +141,180 @@ async fn aes_cbc_encrypt_in_place() {
     assert_eq!(request_id, org_request_id);
     assert_eq!(plaintext_external_key, org_plaintext);
 }
+
+#[async_std::test]
+async fn aes_cbc_padding_schemes() {
+    let key = *b"Or was it 'open quinoa' instead?";
+    let iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let org_plaintext = *b"Hi!";
+    let mut buffers = [[0u8; 16]; 3];
+    let mut unaligned_buffer = [0u8; 16];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::EncryptAesCbc, RequestType::DecryptAesCbc],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key).await;
+
+    for (padding, buffer) in [
+        CbcPadding::Pkcs7,
+        CbcPadding::Iso7816,
+        CbcPadding::NoPadding,
+    ]
+    .into_iter()
+    .zip(buffers.iter_mut())
+    {
+        let plaintext_size = match padding {
+            CbcPadding::NoPadding => buffer.len(),
+            _ => org_plaintext.len(),
+        };
+        buffer[..org_plaintext.len()].copy_from_slice(&org_plaintext);
+        api.encrypt_in_place(
+            AesCbc(padding),
+            SYM_256_KEY.id,
+            &iv,
+            plaintext_size,
+            buffer,
+            &[],
+            &mut [],
+        )
+        .await
+        .expect("failed to send request");
+        let Response::EncryptAesCbc {
+            client_id: _,
+            request_id: _,
+            buffer,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(buffer.len(), crypto::aes::BLOCK_SIZE);
+
+        api.decrypt_in_place(AesCbc(padding), SYM_256_KEY.id, &iv, buffer, &[], &[])
+            .await
+            .expect("failed to send request");
+        let Response::DecryptAesCbc {
+            client_id: _,
+            request_id: _,
+            plaintext,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(plaintext.len(), plaintext_size);
+        assert_eq!(plaintext[..org_plaintext.len()], org_plaintext);
+    }
+
+    // Without padding, the plaintext has to be block aligned
+    api.encrypt_in_place(
+        AesCbc(CbcPadding::NoPadding),
+        SYM_256_KEY.id,
+        &iv,
+        org_plaintext.len(),
+        &mut unaligned_buffer,
+        &[],
+        &mut [],
+    )
+    .await
+    .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id: _,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(error, Error::Crypto(crypto::Error::InvalidBufferSize));
+}
+
+#[async_std::test]
+async fn decryption_errors_are_generic() {
+    let key = *b"Or was it 'open quinoa' instead?";
+    let cbc_iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    let gcm_iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    let mut cbc_ciphertext = [0u8; crypto::aes::BLOCK_SIZE];
+    let mut gcm_ciphertext = [0u8; crypto::aes::BLOCK_SIZE];
+    let gcm_tag = [0u8; crypto::aes::GCM_TAG_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::DecryptAesCbc, RequestType::DecryptAesGcm],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key).await;
+
+    // Invalid padding
+    api.decrypt_in_place(
+        AesCbc(CbcPadding::Pkcs7),
+        SYM_256_KEY.id,
+        &cbc_iv,
+        &mut cbc_ciphertext,
+        &[],
+        &[],
+    )
+    .await
+    .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id: _,
+        error: padding_error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+
+    // Invalid tag
+    api.decrypt_in_place(
+        AesGcm,
+        SYM_256_KEY.id,
+        &gcm_iv,
+        &mut gcm_ciphertext,
+        &[],
+        &gcm_tag,
+    )
+    .await
+    .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id: _,
+        error: tag_error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+
+    assert_eq!(padding_error, Error::AuthenticationFailed);
+    assert_eq!(tag_error, Error::AuthenticationFailed);
+}
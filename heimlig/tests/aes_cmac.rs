@@ -33,7 +33,9 @@ async fn aes_cmac_calculate_verify() {
         &mut worker_responses,
         Some(&key_store),
     );
+    let rng = init_rng();
     let mut worker = AesWorker {
+        rng: &rng,
         key_store: &key_store,
         requests: req_worker_rx,
         responses: resp_worker_tx,
@@ -43,7 +45,7 @@ async fn aes_cmac_calculate_verify() {
 
     // Calculate CMAC tag with imported key
     let org_request_id = api
-        .calculate_aes_cmac(SYM_256_KEY.id, &message, &mut tag)
+        .calculate_aes_cmac(SYM_256_KEY.id, &message, &[], &mut tag)
         .await
         .expect("failed to send request");
     let Response::CalculateAesCmac {
@@ -75,7 +77,7 @@ async fn aes_cmac_calculate_verify() {
 
     // Verify CMAC tag with imported key.
     let org_request_id = api
-        .verify_aes_cmac(SYM_256_KEY.id, &message, tag)
+        .verify_aes_cmac(SYM_256_KEY.id, &message, &[], tag)
         .await
         .expect("failed to send request");
     let Response::VerifyAesCmac {
@@ -5,9 +5,9 @@ pub use common::*;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use heimlig::{
     client::api::SymmetricAlgorithm::AesGcm,
-    common::jobs::{RequestType, Response},
+    common::jobs::{Error, RequestType, Response},
     crypto,
-    hsm::workers::aes_worker::AesWorker,
+    hsm::{keystore, keystore::InsecureKeyStore, workers::aes_worker::AesWorker},
 };
 
 #[async_std::test]
@@ -38,7 +38,9 @@ async fn aes_gcm_encrypt_in_place() {
         &mut worker_responses,
         Some(&key_store),
     );
+    let rng = init_rng();
     let mut worker = AesWorker {
+        rng: &rng,
         key_store: &key_store,
         requests: req_worker_rx,
         responses: resp_worker_tx,
@@ -136,3 +138,414 @@ async fn aes_gcm_encrypt_in_place() {
     assert_eq!(request_id, org_request_id);
     assert_eq!(plaintext_external_key, org_plaintext)
 }
+
+#[async_std::test]
+async fn aes_gcm_reuse_stored_key() {
+    let key = *b"Open sesame! ...";
+    let aad = *b"Never gonna give you up, Never gonna let you down!";
+    let ivs: [[u8; crypto::aes::GCM_IV_SIZE]; 3] = [[1; 12], [2; 12], [3; 12]];
+    let mut buffers = [*b"Hello, World!"; 3];
+    let mut tags = [[0u8; crypto::aes::GCM_TAG_SIZE]; 3];
+    let mut missing_key_buffer = *b"Hello, World!";
+    let mut missing_key_tag = [0u8; crypto::aes::GCM_TAG_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::EncryptAesGcm],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    // Import the key once and reference it by ID for every encryption
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &key).await;
+    for ((iv, buffer), tag) in ivs.iter().zip(buffers.iter_mut()).zip(tags.iter_mut()) {
+        let mut expected_buffer = *buffer;
+        let mut expected_tag = [0u8; crypto::aes::GCM_TAG_SIZE];
+        crypto::aes::gcm::aes128gcm_encrypt_in_place_detached(
+            &key,
+            iv,
+            &aad,
+            &mut expected_buffer,
+            &mut expected_tag,
+        )
+        .expect("failed to encrypt");
+
+        let org_request_id = api
+            .encrypt_in_place(AesGcm, SYM_128_KEY.id, iv, buffer.len(), buffer, &aad, tag)
+            .await
+            .expect("failed to send request");
+        let Response::EncryptAesGcm {
+            client_id: _,
+            request_id,
+            buffer,
+            tag,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        assert_eq!(buffer, expected_buffer);
+        assert_eq!(tag, expected_tag);
+    }
+
+    // Key ID without imported key
+    let org_request_id = api
+        .encrypt_in_place(
+            AesGcm,
+            SYM_256_KEY.id,
+            &[0u8; crypto::aes::GCM_IV_SIZE],
+            missing_key_buffer.len(),
+            &mut missing_key_buffer,
+            &aad,
+            &mut missing_key_tag,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::KeyNotFound));
+}
+
+#[async_std::test]
+async fn aes_gcm_encrypt_random_nonce() {
+    let key = *b"Open sesame! ...";
+    let aad = *b"Never gonna give you up, Never gonna let you down!";
+    let org_plaintext = *b"Hello, World!";
+    let mut plaintexts = [org_plaintext; 2];
+    let mut nonces = [[0u8; crypto::aes::GCM_IV_SIZE]; 2];
+    let mut tags = [[0u8; crypto::aes::GCM_TAG_SIZE]; 2];
+    let mut short_nonce = [0u8; crypto::aes::GCM_IV_SIZE - 1];
+    let mut short_nonce_plaintext = org_plaintext;
+    let mut short_nonce_tag = [0u8; crypto::aes::GCM_TAG_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::EncryptAesGcmRandomNonce],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &key).await;
+
+    let mut used_nonces = [[0u8; crypto::aes::GCM_IV_SIZE]; 2];
+    for (((nonce, buffer), tag), used_nonce) in nonces
+        .iter_mut()
+        .zip(plaintexts.iter_mut())
+        .zip(tags.iter_mut())
+        .zip(used_nonces.iter_mut())
+    {
+        let org_request_id = api
+            .encrypt_aes_gcm_random_nonce(SYM_128_KEY.id, nonce, buffer, &aad, tag)
+            .await
+            .expect("failed to send request");
+        let Response::EncryptAesGcmRandomNonce {
+            client_id: _,
+            request_id,
+            iv,
+            buffer,
+            tag,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+
+        // The returned nonce decrypts the ciphertext
+        crypto::aes::gcm::aes128gcm_decrypt_in_place_detached(&key, iv, &aad, buffer, tag)
+            .expect("failed to decrypt");
+        assert_eq!(buffer, org_plaintext);
+        used_nonce.copy_from_slice(iv);
+    }
+    assert_ne!(used_nonces[0], used_nonces[1]);
+
+    // Nonce buffer of the wrong size
+    let org_request_id = api
+        .encrypt_aes_gcm_random_nonce(
+            SYM_128_KEY.id,
+            &mut short_nonce,
+            &mut short_nonce_plaintext,
+            &aad,
+            &mut short_nonce_tag,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::Crypto(crypto::Error::InvalidIvSize));
+}
+
+#[async_std::test]
+async fn backup_and_restore_keystore() {
+    let kek = *b"Or was it 'open quinoa' instead?";
+    let non_exportable_key = *b"Open sesame! ...";
+    let private_key = [2u8; 32];
+    let mut public_key = [0u8; 64];
+    crypto::ecdsa::nist_p256_public_key_from_private_key(&private_key, &mut public_key)
+        .expect("failed to derive public key");
+    let iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    let mut blob = [0u8; 256];
+    let mut blob_copy = [0u8; 256];
+    let mut exported_public_key = [0u8; 64];
+    let mut exported_private_key = [0u8; 32];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::BackupKeystore, RequestType::RestoreKeystore],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &kek).await;
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &non_exportable_key).await;
+    api.import_key_pair(ASYM_NIST_P256_KEY.id, &public_key, &private_key, false)
+        .await
+        .expect("failed to send request");
+    let Response::ImportKeyPair { .. } = get_response_from_core(&mut api, &mut core).await else {
+        panic!("Unexpected response type")
+    };
+
+    // Backup contains the key pair only. KEK is excluded and the AES-128 key is not exportable.
+    let org_request_id = api
+        .backup_keystore(SYM_256_KEY.id, &iv, &mut blob)
+        .await
+        .expect("failed to send request");
+    let Response::BackupKeystore {
+        client_id: _,
+        request_id,
+        blob,
+        skipped,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(skipped, 1);
+    assert_eq!(
+        blob.len(),
+        crypto::aes::GCM_IV_SIZE + 6 + 64 + 32 + crypto::aes::GCM_TAG_SIZE
+    );
+    assert!(!blob.windows(private_key.len()).any(|w| w == private_key));
+    let blob_copy = &mut blob_copy[..blob.len()];
+    blob_copy.copy_from_slice(blob);
+
+    key_store
+        .lock()
+        .await
+        .delete_insecure(ASYM_NIST_P256_KEY.id)
+        .expect("failed to delete key");
+
+    let org_request_id = api
+        .restore_keystore(SYM_256_KEY.id, blob)
+        .await
+        .expect("failed to send request");
+    let Response::RestoreKeystore {
+        client_id: _,
+        request_id,
+        restored,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(restored, 1);
+
+    // Restored keys are not overwritten without the overwrite permission
+    let org_request_id = api
+        .restore_keystore(SYM_256_KEY.id, blob_copy)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::KeyAlreadyExists));
+
+    let locked_key_store = key_store.lock().await;
+    let exported_public_key = locked_key_store
+        .export_public_key_insecure(ASYM_NIST_P256_KEY.id, &mut exported_public_key)
+        .expect("failed to export public key");
+    assert_eq!(exported_public_key, public_key);
+    let exported_private_key = locked_key_store
+        .export_private_key_insecure(ASYM_NIST_P256_KEY.id, &mut exported_private_key)
+        .expect("failed to export private key");
+    assert_eq!(exported_private_key, private_key);
+}
+
+#[async_std::test]
+async fn restore_keystore_rejects_invalid_key_pair() {
+    let kek = *b"Or was it 'open quinoa' instead?";
+    let public_key = [1u8; 64];
+    let private_key = [2u8; 32];
+    let iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    let mut blob = [0u8; 256];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::BackupKeystore, RequestType::RestoreKeystore],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &kek).await;
+    // The key store does not check that the public key belongs to the private key
+    key_store
+        .lock()
+        .await
+        .import_key_pair_insecure(ASYM_NIST_P256_KEY.id, &public_key, &private_key)
+        .expect("failed to import key pair");
+
+    api.backup_keystore(SYM_256_KEY.id, &iv, &mut blob)
+        .await
+        .expect("failed to send request");
+    let Response::BackupKeystore { blob, .. } = get_response_from_worker!(api, core, worker) else {
+        panic!("Unexpected response type")
+    };
+    key_store
+        .lock()
+        .await
+        .delete_insecure(ASYM_NIST_P256_KEY.id)
+        .expect("failed to delete key");
+
+    api.restore_keystore(SYM_256_KEY.id, blob)
+        .await
+        .expect("failed to send request");
+    let Response::Error { error, .. } = get_response_from_worker!(api, core, worker) else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(error, Error::Crypto(crypto::Error::InvalidPublicKey));
+    assert!(!key_store
+        .lock()
+        .await
+        .is_key_available(ASYM_NIST_P256_KEY.id));
+}
+
+#[async_std::test]
+async fn restore_keystore_rejects_duplicate_key_ids() {
+    let kek = *b"Or was it 'open quinoa' instead?";
+    let iv = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12];
+    const RECORD_SIZE: usize = 6 + 16;
+    let mut blob = [0u8; crypto::aes::GCM_IV_SIZE + 2 * RECORD_SIZE + crypto::aes::GCM_TAG_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::RestoreKeystore],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = AesWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &kek).await;
+
+    // Forge a backup with two records for the same key that must not be overwritten
+    let (blob_iv, rest) = blob.split_at_mut(crypto::aes::GCM_IV_SIZE);
+    let (records, tag) = rest.split_at_mut(2 * RECORD_SIZE);
+    blob_iv.copy_from_slice(&iv);
+    for (record, key) in records
+        .chunks_exact_mut(RECORD_SIZE)
+        .zip([b"Speak, friend...", b"and enter......."])
+    {
+        record[..4].copy_from_slice(&SYM_128_KEY.id.0.to_le_bytes());
+        record[4..6].copy_from_slice(&(key.len() as u16).to_le_bytes());
+        record[6..].copy_from_slice(key);
+    }
+    crypto::aes::gcm::aes256gcm_encrypt_in_place_detached(
+        &kek,
+        &iv,
+        b"heimlig keystore backup",
+        records,
+        tag,
+    )
+    .expect("failed to encrypt backup");
+
+    api.restore_keystore(SYM_256_KEY.id, &mut blob)
+        .await
+        .expect("failed to send request");
+    let Response::Error { error, .. } = get_response_from_worker!(api, core, worker) else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(error, Error::KeyStore(keystore::Error::DuplicateIds));
+    assert!(!key_store.lock().await.is_key_available(SYM_128_KEY.id));
+}
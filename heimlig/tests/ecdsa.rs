@@ -4,8 +4,18 @@ mod common;
 pub use common::*;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use heimlig::{
-    common::jobs::{RequestType, Response},
-    hsm::workers::ecc_worker::EccWorker,
+    common::jobs::{Error, PublicKeyEncoding, RequestType, Response, SignatureEncoding},
+    crypto,
+    crypto::{
+        ecdh::nist_p256_derive_shared_secret,
+        ecdsa::{
+            nist_p256_compress_public_key, nist_p256_generate_key_pair, nist_p256_sign,
+            nist_p256_verify, NIST_P256_COMPRESSED_PUBLIC_KEY_SIZE,
+        },
+        hkdf::hkdf_sha2_256,
+    },
+    hsm::keystore::{self, KeyInfo, KeyPermissions},
+    hsm::workers::ecc_worker::{EccWorker, ATTESTATION_LABEL},
 };
 use sha2::{Digest, Sha256};
 
@@ -42,6 +52,7 @@ async fn sign_verify_nist_p256() {
         key_store: &key_store,
         requests: req_worker_rx,
         responses: resp_worker_tx,
+        attestation_key_id: None,
     };
 
     // Generate key
@@ -62,7 +73,11 @@ async fn sign_verify_nist_p256() {
 
     // Export public key
     let org_request_id = api
-        .export_public_key(ASYM_NIST_P256_KEY.id, &mut large_public_key_buffer)
+        .export_public_key(
+            ASYM_NIST_P256_KEY.id,
+            &mut large_public_key_buffer,
+            PublicKeyEncoding::Uncompressed,
+        )
         .await
         .expect("failed to send request");
     let Response::ExportPublicKey {
@@ -109,7 +124,13 @@ async fn sign_verify_nist_p256() {
 
     // Verify message with generated key
     let org_request_id = api
-        .verify(ASYM_NIST_P256_KEY.id, message, false, signature)
+        .verify(
+            ASYM_NIST_P256_KEY.id,
+            message,
+            false,
+            signature,
+            SignatureEncoding::Raw,
+        )
         .await
         .expect("failed to send request");
     let Response::Verify {
@@ -147,7 +168,13 @@ async fn sign_verify_nist_p256() {
 
     // Verify digest with external key
     let org_request_id = api
-        .verify_external_key(public_key, digest.as_slice(), true, signature_external_key)
+        .verify_external_key(
+            public_key,
+            digest.as_slice(),
+            true,
+            signature_external_key,
+            SignatureEncoding::Raw,
+        )
         .await
         .expect("failed to send request");
     let Response::Verify {
@@ -161,3 +188,531 @@ async fn sign_verify_nist_p256() {
     assert_eq!(request_id, org_request_id);
     assert!(verified);
 }
+
+#[async_std::test]
+async fn ecdh_hkdf_nist_p256() {
+    let salt: &[u8] = b"session salt";
+    let info: &[u8] = b"session key";
+    let mut hsm_public_key = [0u8; ASYM_NIST_P256_KEY.ty.public_key_size()];
+    let mut hsm_derived_key = [0u8; SYM_256_KEY.ty.key_size()];
+    let (peer_private_key, peer_public_key) =
+        nist_p256_generate_key_pair(&mut *init_rng().lock().await);
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GenerateKeyPair, RequestType::EcdhHkdf],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = EccWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+        attestation_key_id: None,
+    };
+
+    api.generate_key_pair(ASYM_NIST_P256_KEY.id, false)
+        .await
+        .expect("failed to send request");
+    let Response::GenerateKeyPair { .. } = get_response_from_worker!(api, core, worker) else {
+        panic!("Unexpected response type")
+    };
+    api.export_public_key(
+        ASYM_NIST_P256_KEY.id,
+        &mut hsm_public_key,
+        PublicKeyEncoding::Uncompressed,
+    )
+    .await
+    .expect("failed to send request");
+    let Response::ExportPublicKey { public_key, .. } =
+        get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+
+    // Peer side of the exchange
+    let mut peer_shared_secret = [0u8; ASYM_NIST_P256_KEY.ty.private_key_size()];
+    let mut peer_derived_key = [0u8; SYM_256_KEY.ty.key_size()];
+    nist_p256_derive_shared_secret(&peer_private_key, public_key, &mut peer_shared_secret)
+        .expect("failed to derive shared secret");
+    hkdf_sha2_256(salt, &peer_shared_secret, info, &mut peer_derived_key)
+        .expect("failed to derive key");
+
+    // HSM side of the exchange
+    let org_request_id = api
+        .ecdh_hkdf(
+            ASYM_NIST_P256_KEY.id,
+            &peer_public_key,
+            salt,
+            info,
+            SYM_256_KEY.id,
+            false,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::EcdhHkdf {
+        client_id: _,
+        request_id,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    api.export_symmetric_key(SYM_256_KEY.id, &mut hsm_derived_key)
+        .await
+        .expect("failed to send request");
+    let Response::ExportSymmetricKey { key, .. } =
+        get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(key, peer_derived_key);
+}
+
+#[async_std::test]
+async fn attest_nist_p256() {
+    const ATTESTATION_KEY: KeyInfo = KeyInfo {
+        permissions: KeyPermissions {
+            import: false,
+            export_private: false,
+            overwrite: false,
+            delete: false,
+        },
+        ..ASYM_NIST_P256_KEY
+    };
+    let nonce: &[u8] = b"Mellon";
+    let mut public_key_buffer = [0u8; ATTESTATION_KEY.ty.public_key_size()];
+    let mut statement_buffer = [0u8; 128];
+    let mut signature_buffer = [0u8; ATTESTATION_KEY.ty.signature_size()];
+    let mut other_statement_buffer = [0u8; 128];
+    let mut other_signature_buffer = [0u8; ATTESTATION_KEY.ty.signature_size()];
+    let mut forged_signature_buffer = [0u8; ATTESTATION_KEY.ty.signature_size()];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&[SYM_128_KEY, SYM_256_KEY, ATTESTATION_KEY]);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[
+            RequestType::GenerateKeyPair,
+            RequestType::Attest,
+            RequestType::Sign,
+        ],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = EccWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+        attestation_key_id: Some(ATTESTATION_KEY.id),
+    };
+
+    // Generate attestation key
+    let org_request_id = api
+        .generate_key_pair(ATTESTATION_KEY.id, false)
+        .await
+        .expect("failed to send request");
+    let Response::GenerateKeyPair {
+        client_id: _,
+        request_id,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    // Export device public key
+    let org_request_id = api
+        .export_public_key(
+            ATTESTATION_KEY.id,
+            &mut public_key_buffer,
+            PublicKeyEncoding::Uncompressed,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::ExportPublicKey {
+        client_id: _,
+        request_id,
+        public_key,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    // Attest
+    let org_request_id = api
+        .attest(
+            ATTESTATION_KEY.id,
+            nonce,
+            &mut statement_buffer,
+            &mut signature_buffer,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Attest {
+        client_id: _,
+        request_id,
+        statement,
+        signature,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    let (label, rest) = statement.split_at(ATTESTATION_LABEL.len());
+    let (statement_public_key, statement_nonce) = rest.split_at(public_key.len());
+    assert_eq!(label, ATTESTATION_LABEL);
+    assert_eq!(statement_public_key, &public_key[..]);
+    assert_eq!(statement_nonce, nonce);
+    nist_p256_verify(public_key, statement, signature).expect("failed to verify attestation");
+
+    // Only the configured attestation key signs statements
+    let org_request_id = api
+        .attest(
+            SYM_128_KEY.id,
+            nonce,
+            &mut other_statement_buffer,
+            &mut other_signature_buffer,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::NotAllowed));
+
+    // The attestation key can not sign arbitrary messages, e.g. a forged statement
+    let org_request_id = api
+        .sign(
+            ATTESTATION_KEY.id,
+            statement,
+            false,
+            &mut forged_signature_buffer,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::NotAllowed));
+}
+
+#[async_std::test]
+async fn verify_key_matches_nist_p256() {
+    let mut public_key_buffer = [0u8; ASYM_NIST_P256_KEY.ty.public_key_size()];
+    let rng = init_rng();
+    let (_, other_public_key) = nist_p256_generate_key_pair(&mut *rng.lock().await);
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GenerateKeyPair, RequestType::VerifyKeyMatches],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let mut worker = EccWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+        attestation_key_id: None,
+    };
+
+    api.generate_key_pair(ASYM_NIST_P256_KEY.id, false)
+        .await
+        .expect("failed to send request");
+    let Response::GenerateKeyPair { .. } = get_response_from_worker!(api, core, worker) else {
+        panic!("Unexpected response type")
+    };
+    let org_request_id = api
+        .export_public_key(
+            ASYM_NIST_P256_KEY.id,
+            &mut public_key_buffer,
+            PublicKeyEncoding::Uncompressed,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::ExportPublicKey {
+        client_id: _,
+        request_id,
+        public_key,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    // Matching key pair
+    let org_request_id = api
+        .verify_key_matches(ASYM_NIST_P256_KEY.id, public_key)
+        .await
+        .expect("failed to send request");
+    let Response::VerifyKeyMatches {
+        client_id: _,
+        request_id,
+        matches,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert!(matches);
+
+    // Mismatched key pair
+    let org_request_id = api
+        .verify_key_matches(ASYM_NIST_P256_KEY.id, &other_public_key)
+        .await
+        .expect("failed to send request");
+    let Response::VerifyKeyMatches {
+        client_id: _,
+        request_id,
+        matches,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert!(!matches);
+}
+
+#[async_std::test]
+async fn attest_exportable_key() {
+    let mut statement = [0u8; 128];
+    let mut signature = [0u8; ASYM_NIST_P256_KEY.ty.signature_size()];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::Attest],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = EccWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+        attestation_key_id: None,
+    };
+
+    // Exportable keys can not be used for attestation
+    let org_request_id = api
+        .attest(
+            ASYM_NIST_P256_KEY.id,
+            b"Mellon",
+            &mut statement,
+            &mut signature,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::NotAllowed));
+}
+
+#[async_std::test]
+async fn verify_der_signature_nist_p256() {
+    let mut signature = [0u8; ASYM_NIST_P256_KEY.ty.signature_size()];
+    let message: &[u8] = b"Never trust a signature you can't parse.";
+    let (private_key, public_key) = nist_p256_generate_key_pair(&mut *init_rng().lock().await);
+    nist_p256_sign(&private_key, message, &mut signature).expect("failed to sign");
+    let der_signature = p256::ecdsa::Signature::from_slice(&signature)
+        .expect("failed to parse signature")
+        .to_der();
+    let malformed_der_signature = &der_signature.as_bytes()[1..];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::VerifyExternalKey],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = EccWorker {
+        rng: &rng,
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+        attestation_key_id: None,
+    };
+
+    // The same signature verifies in both encodings
+    for (signature, encoding) in [
+        (signature.as_slice(), SignatureEncoding::Raw),
+        (der_signature.as_bytes(), SignatureEncoding::Der),
+    ] {
+        let org_request_id = api
+            .verify_external_key(&public_key, message, false, signature, encoding)
+            .await
+            .expect("failed to send request");
+        let Response::Verify {
+            client_id: _,
+            request_id,
+            verified,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        assert!(verified);
+    }
+
+    // Malformed DER signatures are rejected
+    let org_request_id = api
+        .verify_external_key(
+            &public_key,
+            message,
+            false,
+            malformed_der_signature,
+            SignatureEncoding::Der,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(
+        error,
+        Error::Crypto(crypto::Error::InvalidSignatureEncoding)
+    );
+}
+
+#[async_std::test]
+async fn compressed_public_key_nist_p256() {
+    let mut compressed_public_key = [0u8; NIST_P256_COMPRESSED_PUBLIC_KEY_SIZE];
+    let mut exported_compressed_public_key = [0u8; ASYM_NIST_P256_KEY.ty.public_key_size()];
+    let mut exported_public_key = [0u8; ASYM_NIST_P256_KEY.ty.public_key_size()];
+    let (private_key, public_key) = nist_p256_generate_key_pair(&mut *init_rng().lock().await);
+    nist_p256_compress_public_key(&public_key, &mut compressed_public_key)
+        .expect("failed to compress public key");
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, _req_worker_rx, _resp_worker_tx) = init_core(
+        &[],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+
+    // Import key pair with compressed public key
+    let org_request_id = api
+        .import_key_pair(
+            ASYM_NIST_P256_KEY.id,
+            &compressed_public_key,
+            &private_key,
+            false,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::ImportKeyPair {
+        client_id: _,
+        request_id,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    // Export compressed public key
+    let org_request_id = api
+        .export_public_key(
+            ASYM_NIST_P256_KEY.id,
+            &mut exported_compressed_public_key,
+            PublicKeyEncoding::Compressed,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::ExportPublicKey {
+        client_id: _,
+        request_id,
+        public_key: exported_compressed_public_key,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(exported_compressed_public_key, compressed_public_key);
+
+    // Key store holds the decompressed public key
+    let org_request_id = api
+        .export_public_key(
+            ASYM_NIST_P256_KEY.id,
+            &mut exported_public_key,
+            PublicKeyEncoding::Uncompressed,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::ExportPublicKey {
+        client_id: _,
+        request_id,
+        public_key: exported_public_key,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(exported_public_key, public_key);
+}
@@ -4,9 +4,16 @@ mod common;
 pub use common::*;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use heimlig::{
-    common::jobs::{HashAlgorithm, RequestType, Response},
+    client::api::Api,
+    common::jobs::{Error, HashAlgorithm, HashAlgorithms, RequestType, Response, Segments},
     crypto,
-    hsm::workers::hmac_worker::HmacWorker,
+    hsm::core::Builder,
+    hsm::keystore::{self, KeyId, KeyInfo, KeyPermissions, KeyType},
+    hsm::workers::{hmac_worker::HmacWorker, rng_worker::RngWorker},
+    integration::{
+        embassy::{RequestQueueSink, RequestQueueSource, ResponseQueueSink, ResponseQueueSource},
+        memory_key_store::MemoryKeyStore,
+    },
 };
 
 #[async_std::test]
@@ -44,7 +51,7 @@ async fn calculate_verify_hmac_sha3_512() {
 
     // Calculate HMAC tag with imported key
     let org_request_id = api
-        .calculate_hmac(SYM_256_KEY.id, hash_algorithm, &message, &mut tag)
+        .calculate_hmac(SYM_256_KEY.id, hash_algorithm, &message, &[], &mut tag)
         .await
         .expect("failed to send request");
     let Response::CalculateHmac {
@@ -76,7 +83,7 @@ async fn calculate_verify_hmac_sha3_512() {
 
     // Verify HMAC tag with imported key
     let org_request_id = api
-        .verify_hmac(SYM_256_KEY.id, hash_algorithm, &message, tag)
+        .verify_hmac(SYM_256_KEY.id, hash_algorithm, &message, &[], tag)
         .await
         .expect("failed to send request");
     let Response::VerifyHmac {
@@ -106,3 +113,544 @@ async fn calculate_verify_hmac_sha3_512() {
     assert_eq!(request_id, org_request_id);
     assert!(verified);
 }
+
+#[async_std::test]
+async fn rekey() {
+    const REKEYABLE_KEY: KeyInfo = KeyInfo {
+        permissions: KeyPermissions {
+            import: true,
+            export_private: true,
+            overwrite: true,
+            delete: false,
+        },
+        ..SYM_256_KEY
+    };
+    let key: [u8; crypto::aes::KEY256_SIZE] = *b"Guardian of the Third Age Istar.";
+    let mut exported_key = [0u8; crypto::aes::KEY256_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&[SYM_128_KEY, REKEYABLE_KEY, ASYM_NIST_P256_KEY]);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::Rekey],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &key[..16]).await;
+    import_symmetric_key(&mut api, &mut core, REKEYABLE_KEY.id, &key).await;
+
+    // Rekeying a key that must not be overwritten fails
+    let org_request_id = api
+        .rekey(SYM_128_KEY.id)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::NotAllowed));
+
+    // Rekey
+    let org_request_id = api
+        .rekey(REKEYABLE_KEY.id)
+        .await
+        .expect("failed to send request");
+    let Response::Rekey {
+        client_id: _,
+        request_id,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+
+    // Exported key is the derived one
+    let org_request_id = api
+        .export_symmetric_key(REKEYABLE_KEY.id, &mut exported_key)
+        .await
+        .expect("failed to send request");
+    let Response::ExportSymmetricKey {
+        client_id: _,
+        request_id,
+        key: exported_key,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    let mut expected_key = [0u8; crypto::aes::KEY256_SIZE];
+    crypto::hkdf::hkdf_sha2_256(&[], &key, b"heimlig rekey", &mut expected_key)
+        .expect("failed to derive key");
+    assert_eq!(exported_key, expected_key);
+    assert_ne!(exported_key, key);
+}
+
+#[async_std::test]
+async fn key_fingerprint() {
+    const EXPORTABLE_SYM_128_KEY: KeyInfo = KeyInfo {
+        id: KeyId(2),
+        ty: KeyType::Symmetric(16),
+        permissions: KeyPermissions {
+            import: true,
+            export_private: true,
+            overwrite: false,
+            delete: false,
+        },
+    };
+    let key1: [u8; crypto::aes::KEY128_SIZE] = *b"Speak, friend...";
+    let key2: [u8; crypto::aes::KEY256_SIZE] = *b"Guardian of the Third Age Istar.";
+    let mut fingerprint_buffers = [[0u8; crypto::hmac::HMAC_SHA2_256_SIZE]; 3];
+    let mut secret_fingerprint_buffer = [0u8; crypto::hmac::HMAC_SHA2_256_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&[SYM_128_KEY, SYM_256_KEY, EXPORTABLE_SYM_128_KEY]);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::KeyFingerprint],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, EXPORTABLE_SYM_128_KEY.id, &key1).await;
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key2).await;
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &key1).await;
+
+    // Fingerprint first key twice and second key once
+    let mut fingerprints = [[0u8; crypto::hmac::HMAC_SHA2_256_SIZE]; 3];
+    let key_ids = [
+        EXPORTABLE_SYM_128_KEY.id,
+        EXPORTABLE_SYM_128_KEY.id,
+        SYM_256_KEY.id,
+    ];
+    for ((key_id, buffer), result) in key_ids
+        .into_iter()
+        .zip(fingerprint_buffers.iter_mut())
+        .zip(fingerprints.iter_mut())
+    {
+        let org_request_id = api
+            .key_fingerprint(key_id, HashAlgorithm::Sha2_256, buffer)
+            .await
+            .expect("failed to send request");
+        let Response::KeyFingerprint {
+            client_id: _,
+            request_id,
+            fingerprint,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        result.copy_from_slice(fingerprint);
+    }
+    assert_eq!(fingerprints[0], fingerprints[1]);
+    assert_ne!(fingerprints[0], fingerprints[2]);
+    assert_ne!(&fingerprints[2][..], &key2[..]);
+
+    // Symmetric keys without export permission have no fingerprint
+    let org_request_id = api
+        .key_fingerprint(
+            SYM_128_KEY.id,
+            HashAlgorithm::Sha2_256,
+            &mut secret_fingerprint_buffer,
+        )
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::NotAllowed));
+}
+
+#[async_std::test]
+async fn hmac_message_types() {
+    let key: [u8; crypto::aes::KEY256_SIZE] = *b"Guardian of the Third Age Istar.";
+    let message: &[u8] = b"You Shall Not Pass!";
+    let mut tag_plain = [0u8; crypto::hmac::HMAC_SHA2_256_SIZE];
+    let mut tag_a = [0u8; crypto::hmac::HMAC_SHA2_256_SIZE];
+    let mut tag_b = [0u8; crypto::hmac::HMAC_SHA2_256_SIZE];
+    let hash_algorithm = HashAlgorithm::Sha2_256;
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::CalculateHmac, RequestType::VerifyHmac],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key).await;
+
+    let mut tags = [&[] as &[u8]; 3];
+    for (i, (message_type, tag)) in [
+        (b"" as &[u8], &mut tag_plain),
+        (b"status", &mut tag_a),
+        (b"command", &mut tag_b),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        api.calculate_hmac(SYM_256_KEY.id, hash_algorithm, message, message_type, tag)
+            .await
+            .expect("failed to send request");
+        let Response::CalculateHmac {
+            client_id: _,
+            request_id: _,
+            tag,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        tags[i] = tag;
+    }
+    assert_ne!(tags[0], tags[1]);
+    assert_ne!(tags[0], tags[2]);
+    assert_ne!(tags[1], tags[2]);
+
+    // MAC of one message type is rejected for another one
+    for (message_type, expected) in [(b"status" as &[u8], true), (b"command", false)] {
+        api.verify_hmac(
+            SYM_256_KEY.id,
+            hash_algorithm,
+            message,
+            message_type,
+            tags[1],
+        )
+        .await
+        .expect("failed to send request");
+        let Response::VerifyHmac {
+            client_id: _,
+            request_id: _,
+            verified,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(verified, expected);
+    }
+}
+
+#[async_std::test]
+async fn generate_hmac_key() {
+    const HMAC_KEY: KeyInfo = KeyInfo {
+        ty: KeyType::Symmetric(HashAlgorithm::Sha2_256.block_size()),
+        ..SYM_128_KEY
+    };
+    let message: &[u8] = b"You Shall Not Pass!";
+    let mut tag = [0u8; crypto::hmac::HMAC_SHA2_256_SIZE];
+    let hash_algorithm = HashAlgorithm::Sha2_256;
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut rng_requests, mut rng_responses) = allocate_channel();
+    let (mut hmac_requests, mut hmac_responses) = allocate_channel();
+    let (req_client_rx, req_client_tx, resp_client_rx, resp_client_tx) =
+        split_queues(&mut client_requests, &mut client_responses);
+    let (rng_requests_rx, rng_requests_tx, rng_responses_rx, rng_responses_tx) =
+        split_queues(&mut rng_requests, &mut rng_responses);
+    let (hmac_requests_rx, hmac_requests_tx, hmac_responses_rx, hmac_responses_tx) =
+        split_queues(&mut hmac_requests, &mut hmac_responses);
+    let rng = init_rng();
+    let mut key_store = init_key_store(&[HMAC_KEY, SYM_256_KEY]);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let mut rng_worker = RngWorker {
+        rng: &rng,
+        key_store: Some(&key_store),
+        requests: rng_requests_rx,
+        responses: rng_responses_tx,
+    };
+    let mut hmac_worker = HmacWorker {
+        key_store: &key_store,
+        requests: hmac_requests_rx,
+        responses: hmac_responses_tx,
+    };
+    let mut core = Builder::<
+        NoopRawMutex,
+        RequestQueueSource<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSink<'_, '_, QUEUE_SIZE>,
+        RequestQueueSink<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSource<'_, '_, QUEUE_SIZE>,
+        MemoryKeyStore<{ TOTAL_KEY_SIZE }, { NUM_KEYS }>,
+    >::default()
+    .with_keystore(&key_store)
+    .with_client(req_client_rx, resp_client_tx)
+    .expect("failed to add client")
+    .with_worker(
+        &[RequestType::GenerateHmacKey],
+        rng_requests_tx,
+        rng_responses_rx,
+    )
+    .expect("failed to add worker")
+    .with_worker(
+        &[RequestType::CalculateHmac],
+        hmac_requests_tx,
+        hmac_responses_rx,
+    )
+    .expect("failed to add worker")
+    .build();
+    let mut api = Api::new(req_client_tx, resp_client_rx);
+
+    // Key slot does not match the block size of the hash
+    let org_request_id = api
+        .generate_hmac_key(SYM_256_KEY.id, hash_algorithm, false)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, rng_worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::InvalidBufferSize));
+
+    // Block size exceeds the maximum symmetric key size
+    let org_request_id = api
+        .generate_hmac_key(SYM_256_KEY.id, HashAlgorithm::Sha2_512, false)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, rng_worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::Crypto(crypto::Error::InvalidSymmetricKeySize));
+
+    // Generate HMAC key
+    let org_request_id = api
+        .generate_hmac_key(HMAC_KEY.id, hash_algorithm, false)
+        .await
+        .expect("failed to send request");
+    let Response::GenerateHmacKey {
+        client_id: _,
+        request_id,
+        key_id,
+    } = get_response_from_worker!(api, core, rng_worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(key_id, HMAC_KEY.id);
+
+    // Use generated key for HMAC
+    let org_request_id = api
+        .calculate_hmac(key_id, hash_algorithm, message, &[], &mut tag)
+        .await
+        .expect("failed to send request");
+    let Response::CalculateHmac {
+        client_id: _,
+        request_id,
+        tag,
+    } = get_response_from_worker!(api, core, hmac_worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_ne!(tag, [0u8; crypto::hmac::HMAC_SHA2_256_SIZE]);
+}
+
+#[async_std::test]
+async fn hash_sha2_256() {
+    let mut empty_digest = [0u8; crypto::hash::SHA512_SIZE];
+    let mut abc_digest = [0u8; crypto::hash::SHA256_SIZE];
+    let mut small_digest = [0u8; crypto::hash::SHA256_SIZE - 1];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::Hash],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    // NIST CSRC example values. Larger buffers are only used partially.
+    for (data, digest, expected) in [
+        (
+            b"".as_slice(),
+            empty_digest.as_mut_slice(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+        ),
+        (
+            b"abc".as_slice(),
+            abc_digest.as_mut_slice(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        ),
+    ] {
+        let org_request_id = api
+            .hash(HashAlgorithm::Sha2_256, data, digest)
+            .await
+            .expect("failed to send request");
+        let Response::Hash {
+            client_id: _,
+            request_id,
+            digest,
+        } = get_response_from_worker!(api, core, worker)
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        assert_eq!(
+            digest,
+            hex::decode(expected)
+                .expect("failed to decode hex string")
+                .as_slice()
+        );
+    }
+
+    // Digest buffer too small
+    let org_request_id = api
+        .hash(HashAlgorithm::Sha2_256, b"abc", &mut small_digest)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::Crypto(crypto::Error::InvalidBufferSize));
+}
+
+#[async_std::test]
+async fn hash_multi_sha2_256_and_sha2_512() {
+    let data: &[u8] = b"One does not simply walk into Mordor.";
+    let hash_algorithms = HashAlgorithms::default()
+        .with(HashAlgorithm::Sha2_512)
+        .with(HashAlgorithm::Sha2_256);
+    let mut digests = [0u8; crypto::hash::SHA256_SIZE + crypto::hash::SHA512_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::HashMulti],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    assert_eq!(hash_algorithms.digests_size(), digests.len());
+    let org_request_id = api
+        .hash_multi(hash_algorithms, data, &mut digests)
+        .await
+        .expect("failed to send request");
+    let Response::HashMulti {
+        client_id: _,
+        request_id,
+        digests,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    // Digests are ordered like the hash algorithm variants, not like they were added
+    let (sha256_digest, sha512_digest) = digests.split_at(crypto::hash::SHA256_SIZE);
+    assert_eq!(sha256_digest, crypto::hash::sha256(data));
+    assert_eq!(sha512_digest, crypto::hash::sha512(data));
+}
+
+#[async_std::test]
+async fn hash_segments_sha3_384() {
+    let data: &[u8] = b"One does not simply walk into Mordor.";
+    let (header, body) = data.split_at(9);
+    let segments = Segments::from_slice(&[header, &[], body]).expect("too many segments");
+    let mut digest = [0u8; crypto::hash::SHA384_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::HashSegments],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let mut worker = HmacWorker {
+        key_store: &key_store,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    let org_request_id = api
+        .hash_segments(HashAlgorithm::Sha3_384, segments, &mut digest)
+        .await
+        .expect("failed to send request");
+    let Response::HashSegments {
+        client_id: _,
+        request_id,
+        digest,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(digest, crypto::hash::sha3_384(data));
+}
@@ -2,11 +2,19 @@
 mod common;
 
 pub use common::*;
+use embassy_futures::join::join;
 use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
+use futures::FutureExt;
 use heimlig::{
-    client::api::Api,
-    common::jobs::{Error, RequestType, Response},
+    client::api::{self, random_bytes, Api},
+    common::jobs::{AeadAlgorithm, ClientId, Error, Request, RequestId, RequestType, Response},
+    common::version,
+    crypto::{
+        aes::{ccm::aes128ccm_encrypt, gcm::aes128gcm_encrypt_in_place_detached},
+        chacha20poly1305,
+    },
     hsm::core::Builder,
+    hsm::keystore::{self, KeyId, KeyStore},
     hsm::workers::rng_worker::RngWorker,
     integration::{
         embassy::{RequestQueueSink, RequestQueueSource, ResponseQueueSink, ResponseQueueSource},
@@ -109,6 +117,181 @@ async fn generate_symmetric_key() {
     assert_eq!(key.len(), SYM_256_KEY.ty.key_size()); // Large buffer was only used partially
 }
 
+#[async_std::test]
+async fn generate_symmetric_key_no_response() {
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GetRandom, RequestType::GenerateSymmetricKey],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+    let rng = init_rng();
+    let mut worker = RngWorker {
+        rng: &rng,
+        key_store: Some(&key_store),
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    // Generate key without waiting for a response
+    api.generate_symmetric_key_no_response(SYM_256_KEY.id, false)
+        .await
+        .expect("failed to send request");
+    core.execute().await.expect("failed to forward request");
+    worker.execute().await.expect("failed to process request");
+
+    // The next response must belong to the availability check
+    check_key_availability(&mut api, &mut core, SYM_256_KEY.id).await;
+
+    // Failures are still reported
+    let org_request_id = api
+        .generate_symmetric_key_no_response(SYM_256_KEY.id, false)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::KeyAlreadyExists));
+}
+
+#[async_std::test]
+async fn call_map_get_random() {
+    const REQUEST_SIZE: usize = 16;
+    let mut random_output = [0u8; REQUEST_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GetRandom],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let rng = init_rng();
+    let mut worker = RngWorker {
+        rng: &rng,
+        key_store:
+            Option::<&embassy_sync::mutex::Mutex<NoopRawMutex, &mut MemoryKeyStore<0, 0>>>::None,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    let request = Request::GetRandom {
+        client_id: ClientId::default(),
+        request_id: RequestId::default(),
+        output: &mut random_output,
+    };
+    let (data, ()) = join(api.call_map(request, random_bytes), async {
+        core.execute().await.expect("failed to forward request");
+        worker.execute().await.expect("failed to process request");
+        core.execute().await.expect("failed to forward response");
+    })
+    .await;
+    let data = data.expect("failed to get random bytes");
+    assert_eq!(data.len(), REQUEST_SIZE);
+    assert!(data.iter().any(|b| *b != 0));
+
+    // Error responses are returned without calling the mapper
+    let request = Request::IsKeyAvailable {
+        client_id: ClientId::default(),
+        request_id: RequestId::default(),
+        key_id: SYM_256_KEY.id,
+    };
+    let (result, ()) = join(api.call_map(request, random_bytes), async {
+        core.execute().await.expect("failed to process request");
+    })
+    .await;
+    assert_eq!(result, Err(api::Error::Job(Error::NoKeyStore)));
+}
+
+#[async_std::test]
+async fn request_get_random() {
+    const REQUEST_SIZE: usize = 16;
+    let mut random_output = [0u8; REQUEST_SIZE];
+    let mut other_random_output = [0u8; REQUEST_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GetRandom],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let rng = init_rng();
+    let mut worker = RngWorker {
+        rng: &rng,
+        key_store:
+            Option::<&embassy_sync::mutex::Mutex<NoopRawMutex, &mut MemoryKeyStore<0, 0>>>::None,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+
+    let request = Request::GetRandom {
+        client_id: ClientId::default(),
+        request_id: RequestId::default(),
+        output: &mut random_output,
+    };
+    let (response, ()) = join(api.request(request), async {
+        core.execute().await.expect("failed to forward request");
+        worker.execute().await.expect("failed to process request");
+        core.execute().await.expect("failed to forward response");
+    })
+    .await;
+    let Ok(Response::GetRandom { data, .. }) = response else {
+        panic!("Unexpected response type {:?}", response);
+    };
+    assert_eq!(data.len(), REQUEST_SIZE);
+
+    // Fire-and-forget requests are rejected without being sent
+    let request = Request::GenerateSymmetricKey {
+        client_id: ClientId::default(),
+        request_id: RequestId::default(),
+        key_id: SYM_256_KEY.id,
+        overwrite: false,
+        no_response: true,
+    };
+    let response = api.request(request).now_or_never();
+    assert!(
+        matches!(response, Some(Err(api::Error::NoResponseExpected))),
+        "Unexpected result {:?}",
+        response
+    );
+
+    // The next response still belongs to the next request
+    let request = Request::GetRandom {
+        client_id: ClientId::default(),
+        request_id: RequestId::default(),
+        output: &mut other_random_output,
+    };
+    let (response, ()) = join(api.request(request), async {
+        core.execute().await.expect("failed to forward request");
+        worker.execute().await.expect("failed to process request");
+        core.execute().await.expect("failed to forward response");
+    })
+    .await;
+    let Ok(Response::GetRandom { data, .. }) = response else {
+        panic!("Unexpected response type {:?}", response);
+    };
+    assert_eq!(data.len(), REQUEST_SIZE);
+}
+
 #[async_std::test]
 async fn multiple_clients() {
     const REQUEST1_SIZE: usize = 16;
@@ -202,6 +385,57 @@ async fn multiple_clients() {
     assert_ne!(client1_id, client2_id);
 }
 
+#[async_std::test]
+async fn clients_are_served_in_turn() {
+    let (mut client1_requests, mut client1_responses) = allocate_channel();
+    let (mut client2_requests, mut client2_responses) = allocate_channel();
+    let (req_client1_rx, req_client1_tx, resp_client1_rx, resp_client1_tx) =
+        split_queues(&mut client1_requests, &mut client1_responses);
+    let (req_client2_rx, req_client2_tx, resp_client2_rx, resp_client2_tx) =
+        split_queues(&mut client2_requests, &mut client2_responses);
+    let mut core = Builder::<
+        NoopRawMutex,
+        RequestQueueSource<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSink<'_, '_, QUEUE_SIZE>,
+        RequestQueueSink<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSource<'_, '_, QUEUE_SIZE>,
+        MemoryKeyStore<{ TOTAL_KEY_SIZE }, { NUM_KEYS }>,
+    >::default()
+    .with_client(req_client1_rx, resp_client1_tx)
+    .expect("failed to add client 1")
+    .with_client(req_client2_rx, resp_client2_tx)
+    .expect("failed to add client 2")
+    .build();
+    let mut api1 = Api::new(req_client1_tx, resp_client1_rx);
+    let mut api2 = Api::new(req_client2_tx, resp_client2_rx);
+
+    // Client 1 queues several requests before client 2 queues its only one
+    let mut org_request1_ids = [RequestId::default(); 3];
+    for id in org_request1_ids.iter_mut() {
+        *id = api1.version().await.expect("failed to send request");
+    }
+    let org_request2_id = api2.version().await.expect("failed to send request");
+
+    // Each client is served once before any client is served again
+    core.execute().await.expect("failed to process request");
+    core.execute().await.expect("failed to process request");
+    let Some(Some(Response::Version { request_id, .. })) = api2.recv_response().now_or_never()
+    else {
+        panic!("Client 2 was not served")
+    };
+    assert_eq!(request_id, org_request2_id);
+    for org_request_id in org_request1_ids {
+        if org_request_id != org_request1_ids[0] {
+            core.execute().await.expect("failed to process request");
+        }
+        let Some(Some(Response::Version { request_id, .. })) = api1.recv_response().now_or_never()
+        else {
+            panic!("Client 1 was not served")
+        };
+        assert_eq!(request_id, org_request_id);
+    }
+}
+
 #[async_std::test]
 async fn no_worker_for_request() {
     const REQUEST_SIZE: usize = 16;
@@ -273,3 +507,362 @@ async fn no_worker_for_request() {
     assert_eq!(request_id, org_request_id);
     assert_eq!(data.len(), REQUEST_SIZE);
 }
+
+#[async_std::test]
+async fn emergency_erase() {
+    let key = [1u8; SYM_256_KEY.ty.key_size()];
+    let mut random_output = [0u8; 16];
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, _req_worker_rx, _resp_worker_tx) = init_core(
+        &[RequestType::GetRandom],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key).await;
+    check_key_availability(&mut api, &mut core, SYM_256_KEY.id).await;
+
+    // Erase all keys
+    let org_request_id = api.emergency_erase().await.expect("failed to send request");
+    let Response::EmergencyErase {
+        client_id: _,
+        request_id,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert!(!KeyStore::is_key_available(
+        *key_store.lock().await,
+        SYM_256_KEY.id
+    ));
+
+    // Core is locked
+    for org_request_id in [
+        api.is_key_available(SYM_256_KEY.id).await,
+        api.get_random(&mut random_output).await,
+    ] {
+        let org_request_id = org_request_id.expect("failed to send request");
+        let Response::Error {
+            client_id: _,
+            request_id,
+            error,
+        } = get_response_from_core(&mut api, &mut core).await
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        assert_eq!(error, Error::Locked);
+    }
+}
+
+#[async_std::test]
+async fn list_keys() {
+    let key_128 = [1u8; SYM_128_KEY.ty.key_size()];
+    let key_256 = [2u8; SYM_256_KEY.ty.key_size()];
+    let mut first_page = [KeyId::default(); 1];
+    let mut second_page = [KeyId::default(); 2];
+    let mut empty_page: [KeyId; 0] = [];
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let (mut api, mut core, _req_worker_rx, _resp_worker_tx) = init_core(
+        &[RequestType::GetRandom],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        Some(&key_store),
+    );
+
+    import_symmetric_key(&mut api, &mut core, SYM_128_KEY.id, &key_128).await;
+    import_symmetric_key(&mut api, &mut core, SYM_256_KEY.id, &key_256).await;
+
+    // First page is full and points to the next stored key
+    let org_request_id = api
+        .list_keys(KeyId::default(), &mut first_page)
+        .await
+        .expect("failed to send request");
+    let Response::KeyList {
+        client_id: _,
+        request_id,
+        ids,
+        next,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(ids, [SYM_128_KEY.id]);
+    assert_eq!(next, Some(SYM_256_KEY.id));
+
+    // Second page contains the remaining key only
+    let org_request_id = api
+        .list_keys(next.expect("no continuation"), &mut second_page)
+        .await
+        .expect("failed to send request");
+    let Response::KeyList {
+        client_id: _,
+        request_id,
+        ids,
+        next,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(ids, [SYM_256_KEY.id]);
+    assert_eq!(next, None);
+
+    // Empty pages are rejected instead of returning the start key as continuation
+    let org_request_id = api
+        .list_keys(KeyId::default(), &mut empty_page)
+        .await
+        .expect("failed to send request");
+    let Response::Error {
+        client_id: _,
+        request_id,
+        error,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(error, Error::KeyStore(keystore::Error::InvalidBufferSize));
+}
+
+#[async_std::test]
+async fn allowed_request_types() {
+    const REQUEST_SIZE: usize = 16;
+    let mut random_output = [0u8; REQUEST_SIZE];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (req_client_rx, req_client_tx, resp_client_rx, resp_client_tx) =
+        split_queues(&mut client_requests, &mut client_responses);
+    let (rng_requests_rx, rng_requests_tx, rng_responses_rx, rng_responses_tx) =
+        split_queues(&mut worker_requests, &mut worker_responses);
+    let rng = init_rng();
+    let mut key_store = init_key_store(&KEY_INFOS);
+    let key_store: Mutex<NoopRawMutex, _> = Mutex::new(&mut key_store);
+    let mut worker = RngWorker {
+        rng: &rng,
+        key_store: Some(&key_store),
+        requests: rng_requests_rx,
+        responses: rng_responses_tx,
+    };
+    let mut core = Builder::<
+        NoopRawMutex,
+        RequestQueueSource<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSink<'_, '_, QUEUE_SIZE>,
+        RequestQueueSink<'_, '_, QUEUE_SIZE>,
+        ResponseQueueSource<'_, '_, QUEUE_SIZE>,
+        MemoryKeyStore<{ TOTAL_KEY_SIZE }, { NUM_KEYS }>,
+    >::default()
+    .with_keystore(&key_store)
+    .with_allowed_request_types(&[RequestType::GetRandom])
+    .expect("failed to set allowed request types")
+    .with_client(req_client_rx, resp_client_tx)
+    .expect("failed to add client")
+    .with_worker(
+        &[RequestType::GetRandom, RequestType::GenerateSymmetricKey],
+        rng_requests_tx,
+        rng_responses_rx,
+    )
+    .expect("failed to add worker")
+    .build();
+    let mut api = Api::new(req_client_tx, resp_client_rx);
+
+    // Allowed request
+    let org_request_id = api
+        .get_random(&mut random_output)
+        .await
+        .expect("failed to send request");
+    let Response::GetRandom {
+        client_id: _,
+        request_id,
+        data,
+    } = get_response_from_worker!(api, core, worker)
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(data.len(), REQUEST_SIZE);
+
+    // Requests for worker and core that are not on the allow-list
+    for org_request_id in [
+        api.generate_symmetric_key(SYM_256_KEY.id, false).await,
+        api.is_key_available(SYM_256_KEY.id).await,
+    ] {
+        let org_request_id = org_request_id.expect("failed to send request");
+        let Response::Error {
+            client_id: _,
+            request_id,
+            error,
+        } = get_response_from_core(&mut api, &mut core).await
+        else {
+            panic!("Unexpected response type")
+        };
+        assert_eq!(request_id, org_request_id);
+        assert_eq!(error, Error::OperationNotPermitted);
+    }
+}
+
+#[async_std::test]
+async fn version() {
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (mut api, mut core, _req_worker_rx, _resp_worker_tx) = init_core(
+        &[],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+
+    let org_request_id = api.version().await.expect("failed to send request");
+    let Response::Version {
+        client_id: _,
+        request_id,
+        crate_version,
+        protocol_version,
+        features,
+    } = get_response_from_core(&mut api, &mut core).await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+    assert_eq!(crate_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(protocol_version, version::PROTOCOL_VERSION);
+    assert_eq!(features, version::FEATURES);
+}
+
+#[async_std::test]
+async fn aead_output_len() {
+    const KEY_128: [u8; 16] = [1u8; 16];
+    const KEY_256: [u8; 32] = [1u8; 32];
+    let mut tag = [0u8; 16];
+
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (mut api, mut core, _req_worker_rx, _resp_worker_tx) = init_core(
+        &[],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+
+    for plaintext_len in [0, 1, 16, 33] {
+        let mut buffer = [0u8; 33];
+        let buffer = &mut buffer[..plaintext_len];
+        for algorithm in [
+            AeadAlgorithm::AesGcm,
+            AeadAlgorithm::AesCcm,
+            AeadAlgorithm::ChaChaPoly,
+        ] {
+            let actual_tag_len = match algorithm {
+                AeadAlgorithm::AesGcm => {
+                    aes128gcm_encrypt_in_place_detached(
+                        &KEY_128,
+                        &[0u8; 12],
+                        &[],
+                        buffer,
+                        &mut tag,
+                    )
+                    .expect("failed to encrypt");
+                    tag.len()
+                }
+                AeadAlgorithm::AesCcm => aes128ccm_encrypt(&KEY_128, &[0u8; 13], &[], buffer)
+                    .expect("failed to encrypt")
+                    .len(),
+                AeadAlgorithm::ChaChaPoly => {
+                    chacha20poly1305::encrypt_in_place_detached(
+                        &KEY_256,
+                        &[0u8; 12],
+                        &[],
+                        buffer,
+                        &mut tag,
+                    )
+                    .expect("failed to encrypt");
+                    tag.len()
+                }
+            };
+
+            let org_request_id = api
+                .aead_output_len(algorithm, plaintext_len)
+                .await
+                .expect("failed to send request");
+            let Response::AeadOutputLen {
+                client_id: _,
+                request_id,
+                ciphertext_len,
+                tag_len,
+            } = get_response_from_core(&mut api, &mut core).await
+            else {
+                panic!("Unexpected response type")
+            };
+            assert_eq!(request_id, org_request_id);
+            assert_eq!(ciphertext_len, buffer.len());
+            assert_eq!(tag_len, actual_tag_len);
+        }
+    }
+}
+
+#[async_std::test]
+async fn has_pending() {
+    let mut random_output = [0u8; 16];
+    let (mut client_requests, mut client_responses) = allocate_channel();
+    let (mut worker_requests, mut worker_responses) = allocate_channel();
+    let (mut api, mut core, req_worker_rx, resp_worker_tx) = init_core(
+        &[RequestType::GetRandom],
+        &mut client_requests,
+        &mut client_responses,
+        &mut worker_requests,
+        &mut worker_responses,
+        None,
+    );
+    let rng = init_rng();
+    let mut worker = RngWorker {
+        rng: &rng,
+        key_store:
+            Option::<&embassy_sync::mutex::Mutex<NoopRawMutex, &mut MemoryKeyStore<0, 0>>>::None,
+        requests: req_worker_rx,
+        responses: resp_worker_tx,
+    };
+    assert!(!core.has_pending());
+
+    // Queued client request
+    let org_request_id = api
+        .get_random(&mut random_output)
+        .await
+        .expect("failed to send request");
+    assert!(core.has_pending());
+    core.execute().await.expect("failed to forward request");
+    assert!(!core.has_pending());
+
+    // Queued worker response
+    worker.execute().await.expect("failed to process request");
+    assert!(core.has_pending());
+    core.execute().await.expect("failed to forward response");
+    assert!(!core.has_pending());
+
+    let Some(Response::GetRandom {
+        client_id: _,
+        request_id,
+        data: _,
+    }) = api.recv_response().await
+    else {
+        panic!("Unexpected response type")
+    };
+    assert_eq!(request_id, org_request_id);
+}